@@ -119,6 +119,9 @@ rm -f "$USB_BUNDLE"/*.desktop
 cp "$USB_TEMPLATE/RUN.sh" "$USB_TEMPLATE/motis-import.sh" "$USB_TEMPLATE/README.txt" "$USB_TEMPLATE/sweden-route-fix.lua" "$USB_BUNDLE/"
 cp "$SCRIPT_DIR/src-tauri/target/release/motis-gui-svelte" "$USB_BUNDLE/"
 cp "$BUILD_DIR/native/motis-ipc" "$USB_BUNDLE/"
+if [ -f "$BUILD_DIR/native/libmotis-native-embedded.so" ]; then
+    cp "$BUILD_DIR/native/libmotis-native-embedded.so" "$USB_BUNDLE/"
+fi
 cp "$MOTIS_BIN" "$USB_BUNDLE/motis"
 mkdir -p "$USB_BUNDLE/ui"
 cp -r "$PROJECT_ROOT/ui/build"/* "$USB_BUNDLE/ui/"
@@ -1,6 +1,7 @@
 fn main() {
     // Simple build - no C++ linking needed!
-    // We use subprocess IPC instead of FFI
+    // The default backend is subprocess IPC; the optional embedded backend
+    // loads libmotis-native-embedded.so at runtime (see native/embedded.rs).
     println!("cargo:rerun-if-changed=src/native.rs");
     println!("cargo:rerun-if-changed=src/main.rs");
 }
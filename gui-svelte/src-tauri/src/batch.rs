@@ -0,0 +1,435 @@
+mod csv;
+
+use crate::export::write_export;
+use crate::logging::{self, BATCH};
+use crate::native::{self, GeocodeOptions, LatLon, Match};
+use serde::{Deserialize, Serialize};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// motis-ipc and the embedded backend answer one request at a time, so
+/// extra workers do not make a batch faster; they only queue ahead of the
+/// UI's own requests. A second worker overlaps decoding with the next
+/// lookup and is the most allowed.
+const DEFAULT_CONCURRENCY: usize = 1;
+const MAX_CONCURRENCY: usize = 2;
+const DEFAULT_ALTERNATIVES: usize = 3;
+/// Header names (case-insensitive) used as the query when no column is given.
+const QUERY_HEADERS: [&str; 4] = ["query", "address", "adress", "adr"];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BatchOptions {
+    #[serde(default)]
+    pub geocode: GeocodeOptions,
+    /// Lookups in flight at once, clamped to 1..=2 (default 1). This bounds
+    /// the load on the backend rather than speeding the batch up.
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    /// Runner-up matches kept per row (default 3).
+    #[serde(default)]
+    pub alternatives: Option<usize>,
+    /// CSV columns joined with ", " to form the query, e.g. street, zip and
+    /// city kept in separate columns.
+    #[serde(default)]
+    pub query_columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchRow {
+    /// 1-based data row (the CSV header is not counted).
+    pub row: usize,
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best: Option<Match>,
+    /// Coordinate of `best`, ready for one-to-many requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<LatLon>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    pub alternatives: Vec<Match>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BatchSummary {
+    pub rows: usize,
+    pub matched: usize,
+    pub unmatched: usize,
+    pub failed: usize,
+}
+
+impl BatchSummary {
+    fn from_rows(rows: &[BatchRow]) -> Self {
+        let mut summary = BatchSummary {
+            rows: rows.len(),
+            ..BatchSummary::default()
+        };
+        for row in rows {
+            match (&row.best, &row.error) {
+                (_, Some(_)) => summary.failed += 1,
+                (Some(_), None) => summary.matched += 1,
+                (None, None) => summary.unmatched += 1,
+            }
+        }
+        summary
+    }
+}
+
+fn to_row(
+    row: usize,
+    query: &str,
+    result: Result<Vec<Match>, String>,
+    alternatives: usize,
+) -> BatchRow {
+    let mut batch_row = BatchRow {
+        row,
+        query: query.to_string(),
+        best: None,
+        location: None,
+        score: None,
+        alternatives: Vec::new(),
+        error: None,
+    };
+    match result {
+        Ok(matches) => {
+            let mut matches = matches.into_iter();
+            if let Some(best) = matches.next() {
+                batch_row.location = Some(LatLon {
+                    lat: best.lat,
+                    lon: best.lon,
+                });
+                batch_row.score = Some(best.score);
+                batch_row.best = Some(best);
+            }
+            batch_row.alternatives = matches.take(alternatives).collect();
+        }
+        Err(error) => batch_row.error = Some(error),
+    }
+    batch_row
+}
+
+/// Resolves `queries` with at most `concurrency` lookups in flight. Rows come
+/// back in input order; a failed or panicking lookup only fails its own row.
+pub(crate) fn run_batch<F>(
+    queries: &[String],
+    concurrency: usize,
+    alternatives: usize,
+    geocode: F,
+) -> Vec<BatchRow>
+where
+    F: Fn(&str) -> Result<Vec<Match>, String> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let workers = concurrency
+        .clamp(1, MAX_CONCURRENCY)
+        .min(queries.len().max(1));
+    let mut rows: Vec<Option<BatchRow>> = vec![None; queries.len()];
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(query) = queries.get(index) else {
+                            break;
+                        };
+                        let query = query.trim();
+                        let result = if query.is_empty() {
+                            Err("Empty query".to_string())
+                        } else {
+                            panic::catch_unwind(AssertUnwindSafe(|| geocode(query)))
+                                .unwrap_or_else(|_| Err("Geocoder panicked".to_string()))
+                        };
+                        done.push((index, to_row(index + 1, query, result, alternatives)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for handle in handles {
+            match handle.join() {
+                Ok(done) => {
+                    for (index, row) in done {
+                        rows[index] = Some(row);
+                    }
+                }
+                Err(_) => logging::error!(BATCH, "Batch worker panicked; its rows are failed"),
+            }
+        }
+    });
+    rows.into_iter()
+        .enumerate()
+        .map(|(index, row)| {
+            row.unwrap_or_else(|| {
+                let error = Err("Batch worker panicked".to_string());
+                to_row(index + 1, queries[index].trim(), error, alternatives)
+            })
+        })
+        .collect()
+}
+
+fn geocode_with(
+    options: &BatchOptions,
+) -> Result<impl Fn(&str) -> Result<Vec<Match>, String> + Sync + '_, String> {
+    options.geocode.validate()?;
+    Ok(move |query: &str| native::geocode_sync(query, &options.geocode).map_err(|e| e.to_string()))
+}
+
+/// Geocodes a list of queries; blocking, run it off the async runtime.
+pub fn geocode_batch(queries: &[String], options: &BatchOptions) -> Result<Vec<BatchRow>, String> {
+    let geocode = geocode_with(options)?;
+    logging::info!(BATCH, "geocode_batch: {} queries", queries.len());
+    Ok(run_batch(
+        queries,
+        options.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+        options.alternatives.unwrap_or(DEFAULT_ALTERNATIVES),
+        geocode,
+    ))
+}
+
+fn query_column_indices(header: &[String], options: &BatchOptions) -> Result<Vec<usize>, String> {
+    let find = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+    };
+    if !options.query_columns.is_empty() {
+        return options
+            .query_columns
+            .iter()
+            .map(|name| {
+                find(name).ok_or_else(|| {
+                    format!(
+                        "CSV has no column '{}'. Next action: pick one of: {}.",
+                        name,
+                        header.join(", ")
+                    )
+                })
+            })
+            .collect();
+    }
+    Ok(vec![QUERY_HEADERS
+        .iter()
+        .find_map(|name| find(name))
+        .unwrap_or(0)])
+}
+
+/// Reads queries from `text`, geocodes them and returns the input table with
+/// the match columns appended.
+pub(crate) fn geocode_csv_text<F>(
+    text: &str,
+    options: &BatchOptions,
+    geocode: F,
+) -> Result<(String, Vec<BatchRow>), String>
+where
+    F: Fn(&str) -> Result<Vec<Match>, String> + Sync,
+{
+    let table = csv::parse(text)?;
+    let columns = query_column_indices(&table.header, options)?;
+    let queries: Vec<String> = table
+        .rows
+        .iter()
+        .map(|record| {
+            columns
+                .iter()
+                .filter_map(|&i| record.get(i).map(|f| f.trim()))
+                .filter(|f| !f.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+    let rows = run_batch(
+        &queries,
+        options.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+        options.alternatives.unwrap_or(DEFAULT_ALTERNATIVES),
+        geocode,
+    );
+
+    // Rows longer than the header keep their extra fields under generated
+    // column names; short rows are padded so the match columns line up.
+    let width = table
+        .rows
+        .iter()
+        .map(Vec::len)
+        .fold(table.header.len(), usize::max);
+    if width > table.header.len() {
+        logging::warning!(
+            BATCH,
+            "CSV rows have up to {} fields but the header only {}; extra fields kept",
+            width,
+            table.header.len()
+        );
+    }
+
+    let mut out = String::new();
+    let mut header = table.header.clone();
+    header.extend((header.len()..width).map(|i| format!("column_{}", i + 1)));
+    header.extend(
+        [
+            "match_name",
+            "match_type",
+            "lat",
+            "lon",
+            "score",
+            "alternatives",
+            "error",
+        ]
+        .map(String::from),
+    );
+    csv::write_record(&mut out, &header, table.delimiter);
+    for (record, row) in table.rows.iter().zip(&rows) {
+        let mut fields = record.clone();
+        fields.resize(width, String::new());
+        let best = row.best.as_ref();
+        fields.push(best.map(|m| m.name.clone()).unwrap_or_default());
+        fields.push(best.map(|m| m.type_.clone()).unwrap_or_default());
+        fields.push(best.map(|m| m.lat.to_string()).unwrap_or_default());
+        fields.push(best.map(|m| m.lon.to_string()).unwrap_or_default());
+        fields.push(row.score.map(|s| s.to_string()).unwrap_or_default());
+        fields.push(
+            row.alternatives
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>()
+                .join(" | "),
+        );
+        fields.push(row.error.clone().unwrap_or_default());
+        csv::write_record(&mut out, &fields, table.delimiter);
+    }
+    Ok((out, rows))
+}
+
+/// Geocodes the CSV at `input` and writes the annotated table to `output`.
+pub fn geocode_batch_csv(
+    input: &Path,
+    output: &Path,
+    options: &BatchOptions,
+) -> Result<BatchSummary, String> {
+    let text = std::fs::read_to_string(input).map_err(|e| {
+        format!(
+            "Failed to read '{}': {}. Next action: save the list as UTF-8 CSV.",
+            input.display(),
+            e
+        )
+    })?;
+    let geocode = geocode_with(options)?;
+    let (csv, rows) = geocode_csv_text(&text, options, geocode)?;
+    write_export(output, &csv)?;
+    let summary = BatchSummary::from_rows(&rows);
+    logging::info!(
+        BATCH,
+        "geocode_batch_csv: {} rows, {} matched, {} unmatched, {} failed",
+        summary.rows, summary.matched, summary.unmatched, summary.failed
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{csv, geocode_csv_text, run_batch, BatchOptions, BatchSummary};
+    use crate::native::Match;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn fake_match(name: &str, score: f64) -> Match {
+        Match {
+            type_: "ADDRESS".to_string(),
+            name: name.to_string(),
+            id: name.to_string(),
+            lat: 59.3,
+            lon: 18.0,
+            score,
+            ..Match::default()
+        }
+    }
+
+    #[test]
+    fn batch_keeps_order_and_bounds_concurrency() {
+        let queries: Vec<String> = (0..20).map(|i| format!("q{}", i)).collect();
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let rows = run_batch(&queries, 8, 1, |q| {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(5));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            if q == "q7" {
+                return Err("backend down".to_string());
+            }
+            Ok(vec![
+                fake_match(q, 2.0),
+                fake_match("alt1", 1.0),
+                fake_match("alt2", 0.5),
+            ])
+        });
+
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert_eq!(rows.len(), 20);
+        assert!(rows.iter().enumerate().all(|(i, r)| r.row == i + 1));
+        assert_eq!(rows[0].best.as_ref().map(|m| m.name.as_str()), Some("q0"));
+        assert_eq!(rows[0].alternatives.len(), 1);
+        assert_eq!(rows[7].error.as_deref(), Some("backend down"));
+        let summary = BatchSummary::from_rows(&rows);
+        assert_eq!((summary.matched, summary.failed), (19, 1));
+    }
+
+    #[test]
+    fn panicking_lookup_fails_only_its_own_row() {
+        let input = "address\nStorgatan 1\nboom\nSlussen\n";
+        let options = BatchOptions {
+            concurrency: Some(2),
+            ..BatchOptions::default()
+        };
+        let (out, rows) = geocode_csv_text(input, &options, |q| {
+            assert_ne!(q, "boom", "geocoder bug");
+            Ok(vec![fake_match(q, 1.0)])
+        })
+        .expect("batch");
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1].error.as_deref(), Some("Geocoder panicked"));
+        let table = csv::parse(&out).expect("reparse");
+        let names: Vec<&str> = table.rows.iter().map(|r| r[1].as_str()).collect();
+        assert_eq!(names, ["Storgatan 1", "", "Slussen"]);
+    }
+
+    #[test]
+    fn csv_rows_are_annotated_with_matches() {
+        let input = "\u{feff}id;Adress;Ort\r\n1;\"Storgatan 1; uppg. B\";Lidingö\r\n\r\n2;;\r\n";
+        let options = BatchOptions {
+            query_columns: vec!["adress".to_string(), "ort".to_string()],
+            ..BatchOptions::default()
+        };
+        let (out, rows) =
+            geocode_csv_text(input, &options, |q| Ok(vec![fake_match(q, 1.5)])).expect("batch");
+
+        assert_eq!(rows[0].query, "Storgatan 1; uppg. B, Lidingö");
+        assert_eq!(rows[1].error.as_deref(), Some("Empty query"));
+        let table = csv::parse(&out).expect("reparse");
+        assert_eq!(table.delimiter, ';');
+        assert_eq!(table.header[3], "match_name");
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0][3], "Storgatan 1; uppg. B, Lidingö");
+        assert_eq!(table.rows[1][9], "Empty query");
+    }
+
+    #[test]
+    fn csv_rows_longer_than_the_header_keep_their_fields() {
+        let input = "id,address\n1,Storgatan 1,note,extra\n2,Slussen\n";
+        let (out, _) = geocode_csv_text(input, &BatchOptions::default(), |q| {
+            Ok(vec![fake_match(q, 1.0)])
+        })
+        .expect("batch");
+
+        let table = csv::parse(&out).expect("reparse");
+        assert_eq!(
+            table.header[..5],
+            ["id", "address", "column_3", "column_4", "match_name"]
+        );
+        assert_eq!(table.rows[0][..5], ["1", "Storgatan 1", "note", "extra", "Storgatan 1"]);
+        assert_eq!(table.rows[1][..5], ["2", "Slussen", "", "", "Slussen"]);
+    }
+}
@@ -0,0 +1,93 @@
+/// Minimal RFC 4180 reader/writer for address lists. Spreadsheets with a
+/// Swedish locale export `;`-separated files, so the delimiter is picked from
+/// the header line.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct CsvTable {
+    pub delimiter: char,
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn detect_delimiter(text: &str) -> char {
+    let first_line = text.lines().next().unwrap_or("");
+    let count = |c| first_line.chars().filter(|&ch| ch == c).count();
+    if count(';') > count(',') {
+        ';'
+    } else {
+        ','
+    }
+}
+
+fn parse_records(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.is_empty() => in_quotes = true,
+            '\n' if in_quotes => {
+                line += 1;
+                field.push(c);
+            }
+            c if in_quotes => field.push(c),
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!("Unterminated quoted field at line {}", line));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    // Blank lines carry no query; drop them instead of reporting empty rows.
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    Ok(records)
+}
+
+pub(super) fn parse(text: &str) -> Result<CsvTable, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let delimiter = detect_delimiter(text);
+    let mut records = parse_records(text, delimiter)?.into_iter();
+    let header = records.next().ok_or_else(|| {
+        "CSV file is empty. Next action: add a header row and one query per row.".to_string()
+    })?;
+    Ok(CsvTable {
+        delimiter,
+        header,
+        rows: records.collect(),
+    })
+}
+
+fn escape_field(field: &str, delimiter: char) -> String {
+    if field.contains(delimiter) || field.contains(['"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub(super) fn write_record(out: &mut String, fields: &[String], delimiter: char) {
+    let line: Vec<String> = fields.iter().map(|f| escape_field(f, delimiter)).collect();
+    out.push_str(&line.join(&delimiter.to_string()));
+    out.push_str("\r\n");
+}
@@ -0,0 +1,192 @@
+//! Test-support stand-in for `motis-ipc`: speaks the same stdin/stdout JSON
+//! line protocol without MOTIS or a dataset.
+//!
+//! Behavior is read from `<data_path>/mock-ipc.json` (or the JSON in
+//! `MOTIS_MOCK_IPC`):
+//!
+//! ```json
+//! {
+//!   "latency_ms": 50,
+//!   "crash_after": 3,
+//!   "malformed_on": ["geocode"],
+//!   "stall_on": ["plan_route"],
+//!   "oversized_on": { "get_tile": 8388608 },
+//!   "stray_reply_at": 1,
+//!   "responses": { "dataset_info": { "feeds": ["test"] } }
+//! }
+//! ```
+//!
+//! `crash_after` counts commands per process, so a respawned mock starts over.
+//! `stray_reply_at` (same count) sends an extra reply for trace `stray` ahead
+//! of that command's own, like a late answer to a command that timed out.
+//! Like `motis-ipc`, a request's `trace_id` is echoed in the response; the
+//! default `api_get`/`api_post` payloads also report it (and the POST body)
+//! so tests can see what was sent.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Default)]
+struct MockBehavior {
+    latency: Duration,
+    crash_after: Option<u64>,
+    stray_reply_at: Option<u64>,
+    malformed_on: Vec<String>,
+    stall_on: Vec<String>,
+    oversized_on: HashMap<String, usize>,
+    responses: HashMap<String, Value>,
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl MockBehavior {
+    fn from_json(config: &Value) -> Self {
+        MockBehavior {
+            latency: Duration::from_millis(config["latency_ms"].as_u64().unwrap_or(0)),
+            crash_after: config["crash_after"].as_u64(),
+            stray_reply_at: config["stray_reply_at"].as_u64(),
+            malformed_on: string_list(&config["malformed_on"]),
+            stall_on: string_list(&config["stall_on"]),
+            oversized_on: config["oversized_on"]
+                .as_object()
+                .map(|map| {
+                    map.iter()
+                        .filter_map(|(cmd, size)| Some((cmd.clone(), size.as_u64()? as usize)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            responses: config["responses"]
+                .as_object()
+                .map(|map| map.clone().into_iter().collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn load(data_path: &str) -> Result<Self, String> {
+        let text = match std::env::var("MOTIS_MOCK_IPC") {
+            Ok(text) => text,
+            Err(_) => {
+                let path = Path::new(data_path).join("mock-ipc.json");
+                match std::fs::read_to_string(&path) {
+                    Ok(text) => text,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+                    Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+                }
+            }
+        };
+        let config: Value =
+            serde_json::from_str(&text).map_err(|e| format!("Invalid mock config: {}", e))?;
+        Ok(Self::from_json(&config))
+    }
+}
+
+/// Minimal payloads in the shapes `native::commands` expects.
+fn default_data(cmd: &str, request: &Value) -> Value {
+    match cmd {
+        "geocode" => json!([]),
+        "plan_route" => json!([]),
+        "reverse_geocode" => Value::Null,
+        "get_tile" | "get_glyph" => json!({ "found": false }),
+        "dataset_info" => json!({
+            "feeds": ["mock"],
+            "agencies": [],
+            "stop_count": 0,
+            "route_count": 0
+        }),
+        "api_get" => json!({
+            "mock": true,
+            "path": request["path"],
+            "trace_id": request["trace_id"]
+        }),
+        "api_post" => json!({
+            "mock": true,
+            "path": request["path"],
+            "body": request["body"],
+            "trace_id": request["trace_id"]
+        }),
+        "load_elevators" => json!({ "elevators": 0 }),
+        _ => json!({ "mock": true, "cmd": cmd }),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <data_path>", args[0]);
+        std::process::exit(1);
+    }
+
+    let behavior = match MockBehavior::load(&args[1]) {
+        Ok(behavior) => behavior,
+        Err(e) => {
+            println!("{}", json!({ "status": "error", "message": e }));
+            std::process::exit(1);
+        }
+    };
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+
+    for (handled, line) in stdin.lock().lines().enumerate() {
+        let Ok(line) = line else {
+            break;
+        };
+        if behavior.crash_after.is_some_and(|n| handled as u64 >= n) {
+            eprintln!("[mock-motis-ipc] crashing after {} commands", handled);
+            std::process::exit(101);
+        }
+
+        let request: Value = serde_json::from_str(&line).unwrap_or(Value::Null);
+        let cmd = request["cmd"].as_str().unwrap_or("").to_string();
+
+        if !behavior.latency.is_zero() {
+            std::thread::sleep(behavior.latency);
+        }
+        if behavior.stall_on.contains(&cmd) {
+            loop {
+                std::thread::sleep(Duration::from_secs(3600));
+            }
+        }
+
+        let response = if behavior.malformed_on.contains(&cmd) {
+            "{\"status\":\"ok\",\"data\":".to_string()
+        } else if let Some(size) = behavior.oversized_on.get(&cmd) {
+            json!({ "status": "ok", "data": { "padding": "x".repeat(*size) } }).to_string()
+        } else {
+            let data = behavior
+                .responses
+                .get(&cmd)
+                .cloned()
+                .unwrap_or_else(|| default_data(&cmd, &request));
+            let mut response = json!({ "status": "ok", "data": data });
+            if let Some(trace_id) = request.get("trace_id") {
+                response["trace_id"] = trace_id.clone();
+            }
+            response.to_string()
+        };
+
+        if behavior.stray_reply_at == Some(handled as u64) {
+            let stray = json!({ "status": "ok", "data": {}, "trace_id": "stray" });
+            let _ = writeln!(stdout, "{}", stray);
+        }
+        if writeln!(stdout, "{}", response)
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
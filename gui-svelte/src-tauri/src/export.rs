@@ -0,0 +1,423 @@
+//! Itinerary export to files other tools can open (GPX, GeoJSON, KML,
+//! iCalendar).
+//!
+//! Accepts a single itinerary from `/api/v5/plan` (leg geometry is decoded
+//! from the encoded polylines) or a native `RouteResult` (straight lines
+//! between leg endpoints, no times).
+
+mod geojson;
+mod gpx;
+mod ics;
+mod kml;
+pub mod polyline;
+pub(crate) mod zip;
+
+use crate::native::{LatLon, RouteResult};
+pub use ics::{IcsGrouping, IcsOptions};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Gpx,
+    GeoJson,
+    Kml,
+}
+
+impl ExportFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gpx" => Some(ExportFormat::Gpx),
+            "geojson" | "json" => Some(ExportFormat::GeoJson),
+            "kml" => Some(ExportFormat::Kml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportLeg {
+    pub mode: String,
+    pub from_name: String,
+    pub to_name: String,
+    pub from_track: Option<String>,
+    pub to_track: Option<String>,
+    /// ISO 8601 timestamps as returned by MOTIS.
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub route_name: Option<String>,
+    pub headsign: Option<String>,
+    pub agency_name: Option<String>,
+    pub duration_seconds: i64,
+    pub distance_meters: Option<f64>,
+    pub points: Vec<LatLon>,
+}
+
+impl ExportLeg {
+    /// "Bus 4 → Gullmarsplan", falling back to the mode for street legs.
+    pub fn title(&self) -> String {
+        match (&self.route_name, &self.headsign) {
+            (Some(route), Some(headsign)) => format!("{} → {}", route, headsign),
+            (Some(route), None) => route.clone(),
+            _ => format!("{}: {} → {}", self.mode, self.from_name, self.to_name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportItinerary {
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub legs: Vec<ExportLeg>,
+}
+
+fn string_field(value: &Value) -> Option<String> {
+    value.as_str().filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+fn place_point(place: &Value) -> Option<LatLon> {
+    Some(LatLon {
+        lat: place["lat"].as_f64()?,
+        lon: place["lon"].as_f64()?,
+    })
+}
+
+impl ExportItinerary {
+    /// Parses one `/api/v5/plan` itinerary or a serialized `RouteResult`.
+    pub fn from_value(value: &Value) -> Result<Self, String> {
+        if value.get("itineraries").is_some() {
+            return Err("Expected a single itinerary, got a full plan response".to_string());
+        }
+        if value.get("duration_seconds").is_some() {
+            let route: RouteResult = serde_json::from_value(value.clone())
+                .map_err(|e| format!("Invalid route: {}", e))?;
+            return Ok(Self::from(&route));
+        }
+        Self::from_plan_itinerary(value)
+    }
+
+    fn from_plan_itinerary(value: &Value) -> Result<Self, String> {
+        let legs = value["legs"]
+            .as_array()
+            .ok_or_else(|| "Itinerary has no legs".to_string())?;
+
+        let legs = legs
+            .iter()
+            .enumerate()
+            .map(|(index, leg)| {
+                let from = &leg["from"];
+                let to = &leg["to"];
+                let geometry = &leg["legGeometry"];
+                let points = match geometry["points"].as_str() {
+                    Some(encoded) => {
+                        let precision = geometry["precision"].as_u64().unwrap_or(6) as u32;
+                        polyline::decode(encoded, precision)
+                            .map_err(|e| format!("Leg {}: {}", index + 1, e))?
+                    }
+                    None => place_point(from)
+                        .into_iter()
+                        .chain(place_point(to))
+                        .collect(),
+                };
+
+                Ok(ExportLeg {
+                    mode: string_field(&leg["mode"]).unwrap_or_default(),
+                    from_name: string_field(&from["name"]).unwrap_or_default(),
+                    to_name: string_field(&to["name"]).unwrap_or_default(),
+                    from_track: string_field(&from["track"])
+                        .or_else(|| string_field(&from["scheduledTrack"])),
+                    to_track: string_field(&to["track"])
+                        .or_else(|| string_field(&to["scheduledTrack"])),
+                    start_time: string_field(&leg["startTime"]),
+                    end_time: string_field(&leg["endTime"]),
+                    route_name: string_field(&leg["displayName"])
+                        .or_else(|| string_field(&leg["routeShortName"]))
+                        .or_else(|| string_field(&leg["tripShortName"])),
+                    headsign: string_field(&leg["headsign"]),
+                    agency_name: string_field(&leg["agencyName"]),
+                    duration_seconds: leg["duration"].as_i64().unwrap_or(0),
+                    distance_meters: leg["distance"].as_f64(),
+                    points,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(ExportItinerary {
+            start_time: string_field(&value["startTime"]),
+            end_time: string_field(&value["endTime"]),
+            legs,
+        })
+    }
+}
+
+impl From<&RouteResult> for ExportItinerary {
+    fn from(route: &RouteResult) -> Self {
+        let legs = route
+            .legs
+            .iter()
+            .map(|leg| ExportLeg {
+                mode: leg.mode.clone(),
+                from_name: leg.from_name.clone(),
+                to_name: leg.to_name.clone(),
+                route_name: leg.route_short_name.clone(),
+                headsign: leg.headsign.clone(),
+                duration_seconds: i64::from(leg.duration_seconds),
+                distance_meters: Some(f64::from(leg.distance_meters)),
+                points: vec![leg.from.clone(), leg.to.clone()],
+                ..ExportLeg::default()
+            })
+            .collect();
+        ExportItinerary {
+            legs,
+            ..ExportItinerary::default()
+        }
+    }
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn render(itinerary: &ExportItinerary, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Gpx => gpx::render(itinerary),
+        ExportFormat::GeoJson => geojson::render(itinerary),
+        ExportFormat::Kml => kml::render(itinerary),
+    }
+}
+
+/// Writes via a temporary file so an interrupted export never leaves a
+/// truncated file at the chosen path.
+pub(crate) fn write_export(path: &Path, contents: &str) -> Result<(), String> {
+    write_export_bytes(path, contents.as_bytes())
+}
+
+/// Binary variant of [`write_export`]. The temporary file sits next to
+/// `path`, is fsynced before the rename and removed again on failure.
+pub(crate) fn write_export_bytes(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("export.tmp");
+    let write_tmp = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()
+    };
+    write_tmp().map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        format!("Failed to write '{}': {}", tmp.display(), e)
+    })?;
+    std::fs::rename(&tmp, path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        format!("Failed to write '{}': {}", path.display(), e)
+    })
+}
+
+/// Renders `itinerary` and writes it to `path`. Without an explicit format
+/// the file extension decides (`.gpx`, `.geojson`/`.json`, `.kml`).
+pub fn export_itinerary(
+    itinerary: &Value,
+    format: Option<ExportFormat>,
+    path: &Path,
+) -> Result<ExportFormat, String> {
+    let format = format
+        .or_else(|| ExportFormat::from_path(path))
+        .ok_or_else(|| {
+            format!(
+                "Cannot tell export format from '{}'. Next action: use a .gpx, .geojson or .kml file name.",
+                path.display()
+            )
+        })?;
+    let itinerary = ExportItinerary::from_value(itinerary)?;
+    if itinerary.legs.is_empty() {
+        return Err("Itinerary has no legs to export".to_string());
+    }
+    write_export(path, &render(&itinerary, format))?;
+    Ok(format)
+}
+
+/// Writes `itinerary` as an `.ics` calendar (one event per transit leg or
+/// one for the whole trip). Needs departure/arrival times, so a plain
+/// `RouteResult` is rejected.
+pub fn export_itinerary_ics(
+    itinerary: &Value,
+    options: &IcsOptions,
+    path: &Path,
+) -> Result<(), String> {
+    let itinerary = ExportItinerary::from_value(itinerary)?;
+    if itinerary.legs.is_empty() {
+        return Err("Itinerary has no legs to export".to_string());
+    }
+    write_export(path, &ics::render(&itinerary, options)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ics, polyline, render, write_export_bytes, ExportFormat, ExportItinerary, IcsGrouping,
+        IcsOptions,
+    };
+
+    fn plan_itinerary() -> serde_json::Value {
+        serde_json::json!({
+            "startTime": "2026-10-18T08:00:00Z",
+            "endTime": "2026-10-18T08:20:00Z",
+            "legs": [
+                {
+                    "mode": "WALK",
+                    "from": { "name": "Start", "lat": 59.33, "lon": 18.06 },
+                    "to": { "name": "T-Centralen", "lat": 59.331, "lon": 18.061 },
+                    "startTime": "2026-10-18T08:00:00Z",
+                    "endTime": "2026-10-18T08:05:00Z",
+                    "duration": 300,
+                    "distance": 120.5
+                },
+                {
+                    "mode": "SUBWAY",
+                    "from": { "name": "T-Centralen", "lat": 59.331, "lon": 18.061, "track": "3" },
+                    "to": { "name": "Slussen & Co", "lat": 59.32, "lon": 18.07 },
+                    "startTime": "2026-10-18T08:06:00Z",
+                    "endTime": "2026-10-18T08:20:00Z",
+                    "duration": 840,
+                    "routeShortName": "14",
+                    "headsign": "Fruängen",
+                    "legGeometry": { "points": "_p~iF~ps|U_ulLnnqC_mqNvxq`@", "precision": 5, "length": 3 }
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn export_writes_leave_no_temporary_file() {
+        let dir = std::env::temp_dir().join(format!("motis-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("matrix.json.gz");
+
+        write_export_bytes(&path, &[0x1f, 0x8b, 0x00]).expect("write");
+        write_export_bytes(&path, &[0x1f, 0x8b]).expect("overwrite");
+        assert_eq!(std::fs::read(&path).expect("read"), vec![0x1f, 0x8b]);
+        assert_eq!(std::fs::read_dir(&dir).expect("read dir").count(), 1);
+
+        assert!(write_export_bytes(&dir.join("missing").join("out.json"), b"{}").is_err());
+        assert_eq!(std::fs::read_dir(&dir).expect("read dir").count(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn polyline_decodes_reference_example() {
+        let points = polyline::decode("_p~iF~ps|U_ulLnnqC_mqNvxq`@", 5).expect("decode");
+        let pairs: Vec<(f64, f64)> = points.iter().map(|p| (p.lat, p.lon)).collect();
+        assert_eq!(
+            pairs,
+            vec![(38.5, -120.2), (40.7, -120.95), (43.252, -126.453)]
+        );
+        assert!(polyline::decode("_p~iF~ps|U_", 5).is_err());
+    }
+
+    #[test]
+    fn plan_itinerary_exports_geojson_features_per_leg() {
+        let itinerary = ExportItinerary::from_value(&plan_itinerary()).expect("itinerary");
+        assert_eq!(
+            itinerary.legs[0].points.len(),
+            2,
+            "walk leg falls back to endpoints"
+        );
+        assert_eq!(itinerary.legs[1].points.len(), 3);
+
+        let geojson: serde_json::Value =
+            serde_json::from_str(&render(&itinerary, ExportFormat::GeoJson)).expect("valid json");
+        let features = geojson["features"].as_array().expect("features");
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[1]["properties"]["mode"], "SUBWAY");
+        assert_eq!(features[1]["properties"]["routeName"], "14");
+        assert_eq!(features[1]["geometry"]["coordinates"][0][0], -120.2);
+    }
+
+    #[test]
+    fn xml_exports_escape_names() {
+        let itinerary = ExportItinerary::from_value(&plan_itinerary()).expect("itinerary");
+        for format in [ExportFormat::Gpx, ExportFormat::Kml] {
+            let xml = render(&itinerary, format);
+            assert!(
+                xml.contains("Slussen &amp; Co"),
+                "{:?} output not escaped",
+                format
+            );
+            assert!(!xml.contains("Slussen & Co"));
+        }
+        assert!(render(&itinerary, ExportFormat::Gpx).contains(
+            "<trkpt lat=\"38.5\" lon=\"-120.2\"><time>2026-10-18T08:06:00Z</time></trkpt>"
+        ));
+    }
+
+    #[test]
+    fn ics_has_one_event_per_transit_leg_with_alarm() {
+        let itinerary = ExportItinerary::from_value(&plan_itinerary()).expect("itinerary");
+        let options = IcsOptions {
+            grouping: IcsGrouping::PerLeg,
+            alarm_minutes: Some(10),
+        };
+        let calendar = ics::render(&itinerary, &options).expect("ics");
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert_eq!(
+            calendar.matches("BEGIN:VEVENT").count(),
+            1,
+            "walk leg has no event"
+        );
+        assert!(calendar.contains("DTSTART:20261018T080600Z\r\n"));
+        assert!(calendar.contains("SUMMARY:14 → Fruängen\r\n"));
+        assert!(calendar.contains("LOCATION:T-Centralen (track 3)\r\n"));
+        assert!(calendar.contains("TRIGGER:-PT10M\r\n"));
+        assert!(calendar.split("\r\n").all(|line| line.len() <= 75));
+
+        let whole = IcsOptions {
+            grouping: IcsGrouping::WholeTrip,
+            alarm_minutes: None,
+        };
+        let calendar = ics::render(&itinerary, &whole).expect("ics");
+        assert!(calendar.contains("DTSTART:20261018T080000Z\r\n"));
+        assert!(calendar.contains("SUMMARY:Start → Slussen & Co\r\n"));
+        assert!(!calendar.contains("VALARM"));
+    }
+
+    #[test]
+    fn ics_uids_differ_for_itineraries_leaving_at_the_same_time() {
+        let uids = |itinerary: &ExportItinerary| -> Vec<String> {
+            let options = IcsOptions {
+                grouping: IcsGrouping::WholeTrip,
+                alarm_minutes: None,
+            };
+            ics::render(itinerary, &options)
+                .expect("ics")
+                .split("\r\n")
+                .filter(|line| line.starts_with("UID:"))
+                .map(str::to_string)
+                .collect()
+        };
+
+        let first = ExportItinerary::from_value(&plan_itinerary()).expect("itinerary");
+        let mut other = plan_itinerary();
+        other["legs"][1]["routeShortName"] = "13".into();
+        other["legs"][1]["headsign"] = "Ropsten".into();
+        let second = ExportItinerary::from_value(&other).expect("itinerary");
+
+        assert_eq!(uids(&first), uids(&first));
+        assert_ne!(uids(&first), uids(&second));
+        assert!(uids(&first)[0].starts_with("UID:20261018T080000Z-"));
+    }
+}
@@ -0,0 +1,59 @@
+use super::{ExportItinerary, ExportLeg};
+use serde_json::{json, Map, Value};
+
+fn leg_properties(index: usize, leg: &ExportLeg) -> Value {
+    let mut properties = Map::new();
+    properties.insert("legIndex".into(), json!(index));
+    properties.insert("mode".into(), json!(leg.mode));
+    properties.insert("from".into(), json!(leg.from_name));
+    properties.insert("to".into(), json!(leg.to_name));
+    properties.insert("duration".into(), json!(leg.duration_seconds));
+
+    let optional = [
+        ("startTime", &leg.start_time),
+        ("endTime", &leg.end_time),
+        ("routeName", &leg.route_name),
+        ("headsign", &leg.headsign),
+        ("agencyName", &leg.agency_name),
+        ("fromTrack", &leg.from_track),
+        ("toTrack", &leg.to_track),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            properties.insert(key.into(), json!(value));
+        }
+    }
+    if let Some(distance) = leg.distance_meters {
+        properties.insert("distance".into(), json!(distance));
+    }
+    Value::Object(properties)
+}
+
+/// One LineString feature per leg, coordinates in `[lon, lat]` order.
+pub(super) fn render(itinerary: &ExportItinerary) -> String {
+    let features: Vec<Value> = itinerary
+        .legs
+        .iter()
+        .enumerate()
+        .map(|(index, leg)| {
+            let coordinates: Vec<[f64; 2]> = leg.points.iter().map(|p| [p.lon, p.lat]).collect();
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": coordinates },
+                "properties": leg_properties(index, leg),
+            })
+        })
+        .collect();
+
+    let mut collection = json!({
+        "type": "FeatureCollection",
+        "features": features,
+    });
+    if let Some(start) = &itinerary.start_time {
+        collection["startTime"] = json!(start);
+    }
+    if let Some(end) = &itinerary.end_time {
+        collection["endTime"] = json!(end);
+    }
+    serde_json::to_string_pretty(&collection).unwrap_or_default()
+}
@@ -0,0 +1,56 @@
+use super::{xml_escape, ExportItinerary};
+use std::fmt::Write;
+
+/// One `<trk>` per leg so apps can show mode and line per segment. Only the
+/// first and last point of a leg carry a `<time>` (departure/arrival).
+pub(super) fn render(itinerary: &ExportItinerary) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(
+        "<gpx version=\"1.1\" creator=\"MOTIS\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+    if let Some(start) = &itinerary.start_time {
+        let _ = writeln!(
+            out,
+            "  <metadata><time>{}</time></metadata>",
+            xml_escape(start)
+        );
+    }
+
+    for leg in &itinerary.legs {
+        out.push_str("  <trk>\n");
+        let _ = writeln!(out, "    <name>{}</name>", xml_escape(&leg.title()));
+        let _ = writeln!(
+            out,
+            "    <desc>{} → {}</desc>",
+            xml_escape(&leg.from_name),
+            xml_escape(&leg.to_name)
+        );
+        let _ = writeln!(out, "    <type>{}</type>", xml_escape(&leg.mode));
+        out.push_str("    <trkseg>\n");
+
+        let last = leg.points.len().saturating_sub(1);
+        for (index, point) in leg.points.iter().enumerate() {
+            let time = match index {
+                0 => leg.start_time.as_ref(),
+                i if i == last => leg.end_time.as_ref(),
+                _ => None,
+            };
+            let _ = write!(
+                out,
+                "      <trkpt lat=\"{}\" lon=\"{}\">",
+                point.lat, point.lon
+            );
+            if let Some(time) = time {
+                let _ = write!(out, "<time>{}</time>", xml_escape(time));
+            }
+            out.push_str("</trkpt>\n");
+        }
+
+        out.push_str("    </trkseg>\n");
+        out.push_str("  </trk>\n");
+    }
+
+    out.push_str("</gpx>\n");
+    out
+}
@@ -0,0 +1,233 @@
+use super::{ExportItinerary, ExportLeg};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IcsGrouping {
+    /// One event per transit leg; walking and cycling legs get no event of
+    /// their own.
+    #[default]
+    PerLeg,
+    WholeTrip,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IcsOptions {
+    #[serde(default)]
+    pub grouping: IcsGrouping,
+    /// Minutes before departure for a display alarm; `None` adds no alarm.
+    #[serde(default)]
+    pub alarm_minutes: Option<u32>,
+}
+
+struct Event {
+    uid: String,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    summary: String,
+    location: String,
+    description: String,
+}
+
+fn parse_time(value: Option<&String>, what: &str) -> Result<DateTime<FixedOffset>, String> {
+    let value = value.ok_or_else(|| {
+        format!(
+            "Itinerary has no {} time. Next action: export an itinerary from /api/v5/plan.",
+            what
+        )
+    })?;
+    DateTime::parse_from_rfc3339(value)
+        .map_err(|e| format!("Invalid {} time '{}': {}", what, value, e))
+}
+
+fn ics_time(time: &DateTime<FixedOffset>) -> String {
+    time.with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+fn local_clock(value: Option<&String>) -> String {
+    value
+        .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+        .map(|t| t.with_timezone(&Local).format("%H:%M").to_string())
+        .unwrap_or_else(|| "--:--".to_string())
+}
+
+fn with_track(name: &str, track: Option<&String>) -> String {
+    match track {
+        Some(track) => format!("{} (track {})", name, track),
+        None => name.to_string(),
+    }
+}
+
+fn is_transit(leg: &ExportLeg) -> bool {
+    !matches!(
+        leg.mode.as_str(),
+        "WALK" | "BIKE" | "CAR" | "RENTAL" | "CAR_PARKING" | "FLEX" | "ODM" | ""
+    )
+}
+
+fn leg_line(leg: &ExportLeg) -> String {
+    let mut line = format!(
+        "{} {} → {} {}: {}",
+        local_clock(leg.start_time.as_ref()),
+        with_track(&leg.from_name, leg.from_track.as_ref()),
+        local_clock(leg.end_time.as_ref()),
+        with_track(&leg.to_name, leg.to_track.as_ref()),
+        leg.title()
+    );
+    if let Some(agency) = &leg.agency_name {
+        line.push_str(&format!(" ({})", agency));
+    }
+    line
+}
+
+fn leg_event(leg: &ExportLeg, uid_base: &str, index: usize) -> Result<Event, String> {
+    Ok(Event {
+        uid: format!("{}-{}@motis", uid_base, index),
+        start: parse_time(leg.start_time.as_ref(), "departure")?,
+        end: parse_time(leg.end_time.as_ref(), "arrival")?,
+        summary: leg.title(),
+        location: with_track(&leg.from_name, leg.from_track.as_ref()),
+        description: leg_line(leg),
+    })
+}
+
+fn trip_event(itinerary: &ExportItinerary, uid_base: &str) -> Result<Event, String> {
+    let first = &itinerary.legs[0];
+    let last = &itinerary.legs[itinerary.legs.len() - 1];
+    Ok(Event {
+        uid: format!("{}-trip@motis", uid_base),
+        start: parse_time(
+            itinerary.start_time.as_ref().or(first.start_time.as_ref()),
+            "departure",
+        )?,
+        end: parse_time(
+            itinerary.end_time.as_ref().or(last.end_time.as_ref()),
+            "arrival",
+        )?,
+        summary: format!("{} → {}", first.from_name, last.to_name),
+        location: with_track(&first.from_name, first.from_track.as_ref()),
+        description: itinerary
+            .legs
+            .iter()
+            .map(leg_line)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
+}
+
+/// RFC 5545 TEXT escaping.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds content lines at 75 octets without splitting UTF-8 sequences.
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// Departure plus a digest of the legs, so two itineraries leaving at the
+/// same time get distinct UIDs while re-exporting one keeps them stable.
+fn uid_base(departure: &DateTime<FixedOffset>, legs: &[ExportLeg]) -> String {
+    let mut hasher = Sha256::new();
+    for leg in legs {
+        for field in [
+            Some(&leg.mode),
+            Some(&leg.from_name),
+            Some(&leg.to_name),
+            leg.start_time.as_ref(),
+            leg.end_time.as_ref(),
+            leg.route_name.as_ref(),
+            leg.headsign.as_ref(),
+        ] {
+            hasher.update(field.map(String::as_str).unwrap_or_default());
+            hasher.update([0x1f]);
+        }
+        hasher.update([0x1e]);
+    }
+    let digest: String = hasher.finalize()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("{}-{}", ics_time(departure), digest)
+}
+
+pub(super) fn render(itinerary: &ExportItinerary, options: &IcsOptions) -> Result<String, String> {
+    let departure = parse_time(
+        itinerary
+            .start_time
+            .as_ref()
+            .or_else(|| itinerary.legs.first().and_then(|l| l.start_time.as_ref())),
+        "departure",
+    )?;
+    let uid_base = uid_base(&departure, &itinerary.legs);
+
+    let transit: Vec<(usize, &ExportLeg)> = itinerary
+        .legs
+        .iter()
+        .enumerate()
+        .filter(|(_, leg)| is_transit(leg))
+        .collect();
+    let events = match options.grouping {
+        // A walk-only trip still gets an entry.
+        IcsGrouping::PerLeg if !transit.is_empty() => transit
+            .into_iter()
+            .map(|(index, leg)| leg_event(leg, &uid_base, index))
+            .collect::<Result<Vec<_>, String>>()?,
+        _ => vec![trip_event(itinerary, &uid_base)?],
+    };
+
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//MOTIS//Itinerary export//EN");
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+    for event in events {
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:{}", event.uid));
+        push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut out, &format!("DTSTART:{}", ics_time(&event.start)));
+        push_line(&mut out, &format!("DTEND:{}", ics_time(&event.end)));
+        push_line(
+            &mut out,
+            &format!("SUMMARY:{}", escape_text(&event.summary)),
+        );
+        push_line(
+            &mut out,
+            &format!("LOCATION:{}", escape_text(&event.location)),
+        );
+        push_line(
+            &mut out,
+            &format!("DESCRIPTION:{}", escape_text(&event.description)),
+        );
+        if let Some(minutes) = options.alarm_minutes {
+            push_line(&mut out, "BEGIN:VALARM");
+            push_line(&mut out, "ACTION:DISPLAY");
+            push_line(&mut out, &format!("TRIGGER:-PT{}M", minutes));
+            push_line(
+                &mut out,
+                &format!("DESCRIPTION:{}", escape_text(&event.summary)),
+            );
+            push_line(&mut out, "END:VALARM");
+        }
+        push_line(&mut out, "END:VEVENT");
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    Ok(out)
+}
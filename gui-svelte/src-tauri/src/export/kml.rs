@@ -0,0 +1,65 @@
+use super::{xml_escape, ExportItinerary, ExportLeg};
+use std::fmt::Write;
+
+fn extended_data(leg: &ExportLeg) -> String {
+    let fields = [
+        ("mode", Some(&leg.mode)),
+        ("from", Some(&leg.from_name)),
+        ("to", Some(&leg.to_name)),
+        ("routeName", leg.route_name.as_ref()),
+        ("headsign", leg.headsign.as_ref()),
+        ("startTime", leg.start_time.as_ref()),
+        ("endTime", leg.end_time.as_ref()),
+    ];
+    let mut out = String::from("      <ExtendedData>\n");
+    for (name, value) in fields {
+        if let Some(value) = value {
+            let _ = writeln!(
+                out,
+                "        <Data name=\"{}\"><value>{}</value></Data>",
+                name,
+                xml_escape(value)
+            );
+        }
+    }
+    out.push_str("      </ExtendedData>\n");
+    out
+}
+
+/// One Placemark per leg; coordinates are `lon,lat` as KML requires.
+pub(super) fn render(itinerary: &ExportItinerary) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n");
+    out.push_str("  <Document>\n");
+    out.push_str("    <name>MOTIS itinerary</name>\n");
+
+    for leg in &itinerary.legs {
+        out.push_str("    <Placemark>\n");
+        let _ = writeln!(out, "      <name>{}</name>", xml_escape(&leg.title()));
+        if let (Some(begin), Some(end)) = (&leg.start_time, &leg.end_time) {
+            let _ = writeln!(
+                out,
+                "      <TimeSpan><begin>{}</begin><end>{}</end></TimeSpan>",
+                xml_escape(begin),
+                xml_escape(end)
+            );
+        }
+        out.push_str(&extended_data(leg));
+        let coordinates: Vec<String> = leg
+            .points
+            .iter()
+            .map(|p| format!("{},{},0", p.lon, p.lat))
+            .collect();
+        let _ = writeln!(
+            out,
+            "      <LineString><tessellate>1</tessellate><coordinates>{}</coordinates></LineString>",
+            coordinates.join(" ")
+        );
+        out.push_str("    </Placemark>\n");
+    }
+
+    out.push_str("  </Document>\n");
+    out.push_str("</kml>\n");
+    out
+}
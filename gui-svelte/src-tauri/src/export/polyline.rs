@@ -0,0 +1,47 @@
+use crate::native::LatLon;
+
+/// Decodes a Google encoded polyline. MOTIS uses precision 7 for `/api/v1`
+/// and 6 for `/api/v2`+ responses (`legGeometry.precision`).
+pub fn decode(encoded: &str, precision: u32) -> Result<Vec<LatLon>, String> {
+    let factor = 10f64.powi(precision as i32);
+    let bytes = encoded.as_bytes();
+    let mut index = 0;
+    let mut lat: i64 = 0;
+    let mut lon: i64 = 0;
+    let mut points = Vec::new();
+
+    while index < bytes.len() {
+        lat += next_value(bytes, &mut index)?;
+        lon += next_value(bytes, &mut index)?;
+        points.push(LatLon {
+            lat: lat as f64 / factor,
+            lon: lon as f64 / factor,
+        });
+    }
+    Ok(points)
+}
+
+fn next_value(bytes: &[u8], index: &mut usize) -> Result<i64, String> {
+    let mut result: i64 = 0;
+    let mut shift = 0;
+    loop {
+        let Some(&byte) = bytes.get(*index) else {
+            return Err("Truncated polyline".to_string());
+        };
+        if !(63..=126).contains(&byte) || shift > 60 {
+            return Err(format!("Invalid polyline character at offset {}", *index));
+        }
+        *index += 1;
+        let chunk = i64::from(byte - 63);
+        result |= (chunk & 0x1f) << shift;
+        shift += 5;
+        if chunk < 0x20 {
+            break;
+        }
+    }
+    Ok(if result & 1 != 0 {
+        !(result >> 1)
+    } else {
+        result >> 1
+    })
+}
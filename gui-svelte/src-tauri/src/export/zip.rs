@@ -0,0 +1,110 @@
+//! Minimal ZIP writer (deflate, UTF-8 names) for multi-file exports such as
+//! zipped Shapefiles. No ZIP64: entries and archive stay below 4 GiB.
+
+use chrono::{Datelike, Local, Timelike};
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::Write;
+
+struct Entry {
+    name: String,
+    crc: u32,
+    compressed: u32,
+    size: u32,
+    offset: u32,
+}
+
+pub(crate) struct ZipWriter {
+    out: Vec<u8>,
+    entries: Vec<Entry>,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+const UTF8_NAMES: u16 = 0x0800;
+const DEFLATE: u16 = 8;
+const VERSION: u16 = 20;
+
+impl ZipWriter {
+    pub(crate) fn new() -> Self {
+        let now = Local::now();
+        let dos_time =
+            ((now.hour() as u16) << 11) | ((now.minute() as u16) << 5) | (now.second() as u16 / 2);
+        let dos_date = (((now.year().clamp(1980, 2107) - 1980) as u16) << 9)
+            | ((now.month() as u16) << 5)
+            | now.day() as u16;
+        ZipWriter {
+            out: Vec::new(),
+            entries: Vec::new(),
+            dos_time,
+            dos_date,
+        }
+    }
+
+    pub(crate) fn add(&mut self, name: &str, data: &[u8]) -> std::io::Result<()> {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let too_large = || std::io::Error::other(format!("'{}' is too large for ZIP", name));
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc.sum(),
+            compressed: u32::try_from(compressed.len()).map_err(|_| too_large())?,
+            size: u32::try_from(data.len()).map_err(|_| too_large())?,
+            offset: u32::try_from(self.out.len()).map_err(|_| too_large())?,
+        };
+
+        let out = &mut self.out;
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&UTF8_NAMES.to_le_bytes());
+        out.extend_from_slice(&DEFLATE.to_le_bytes());
+        out.extend_from_slice(&self.dos_time.to_le_bytes());
+        out.extend_from_slice(&self.dos_date.to_le_bytes());
+        out.extend_from_slice(&entry.crc.to_le_bytes());
+        out.extend_from_slice(&entry.compressed.to_le_bytes());
+        out.extend_from_slice(&entry.size.to_le_bytes());
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&compressed);
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.out.len() as u32;
+        for entry in &self.entries {
+            let out = &mut self.out;
+            out.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            out.extend_from_slice(&VERSION.to_le_bytes());
+            out.extend_from_slice(&VERSION.to_le_bytes());
+            out.extend_from_slice(&UTF8_NAMES.to_le_bytes());
+            out.extend_from_slice(&DEFLATE.to_le_bytes());
+            out.extend_from_slice(&self.dos_time.to_le_bytes());
+            out.extend_from_slice(&self.dos_date.to_le_bytes());
+            out.extend_from_slice(&entry.crc.to_le_bytes());
+            out.extend_from_slice(&entry.compressed.to_le_bytes());
+            out.extend_from_slice(&entry.size.to_le_bytes());
+            out.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            // Extra field, comment, disk number, internal and external
+            // attributes.
+            out.extend_from_slice(&[0u8; 12]);
+            out.extend_from_slice(&entry.offset.to_le_bytes());
+            out.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = self.out.len() as u32 - directory_offset;
+        let count = self.entries.len() as u16;
+        let out = &mut self.out;
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        out.extend_from_slice(&[0u8; 4]);
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&directory_size.to_le_bytes());
+        out.extend_from_slice(&directory_offset.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        self.out
+    }
+}
@@ -0,0 +1,755 @@
+//! Isochrone polygons from `/api/v1/one-to-all` results.
+//!
+//! Reached stops plus walking buffers are rasterized into earliest-arrival
+//! cells, traced into contours with holes per time band and simplified. The
+//! result is GeoJSON, or a zipped Shapefile for GIS tools.
+
+mod contour;
+mod shapefile;
+mod simplify;
+
+use crate::export::{write_export, write_export_bytes};
+use crate::export::zip::ZipWriter;
+use crate::logging::{self, ISOCHRONE};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+
+const METERS_PER_DEGREE: f64 = 6_371_008.8 * std::f64::consts::PI / 180.0;
+const DEFAULT_WALK_SPEED: f64 = 1.2;
+const DEFAULT_CELL_METERS: f64 = 50.0;
+/// Raster size cap; the cell size grows for very large reaches instead.
+const MAX_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IsochroneFormat {
+    GeoJson,
+    /// `.shp`/`.shx`/`.dbf`/`.prj`/`.cpg` plus the metadata JSON in one ZIP.
+    Shapefile,
+}
+
+impl IsochroneFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "geojson" | "json" => Some(IsochroneFormat::GeoJson),
+            "zip" => Some(IsochroneFormat::Shapefile),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IsochroneOptions {
+    /// Travel time budget in seconds, the `maxTravelTime` of the one-to-all
+    /// request.
+    pub max_travel_seconds: u32,
+    /// Upper bounds of the time bands in minutes; defaults to one band at
+    /// `max_travel_seconds`.
+    #[serde(default)]
+    pub bands_minutes: Vec<u32>,
+    /// Speed used for the walking buffer around each reached stop, in m/s
+    /// (1.2 walking, 0.8 wheelchair, 3.8 bike, as in the map view).
+    #[serde(default)]
+    pub speed_meters_per_second: Option<f64>,
+    #[serde(default)]
+    pub cell_meters: Option<f64>,
+    /// Simplification tolerance in meters; defaults to the cell size.
+    #[serde(default)]
+    pub simplify_meters: Option<f64>,
+}
+
+/// Search parameters saved with an export so the isochrone can be
+/// reproduced; the origin coordinate is taken from the one-to-all response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IsochroneMetadata {
+    #[serde(default)]
+    pub origin_name: Option<String>,
+    #[serde(default)]
+    pub departure_time: Option<String>,
+    #[serde(default)]
+    pub arrive_by: bool,
+    #[serde(default)]
+    pub transit_modes: Vec<String>,
+    #[serde(default)]
+    pub street_modes: Vec<String>,
+    /// Query string of the `/api/v1/one-to-all` request.
+    #[serde(default)]
+    pub query: Option<String>,
+}
+
+/// A reached point with the seconds already spent getting there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Reached {
+    lat: f64,
+    lon: f64,
+    seconds: f64,
+}
+
+fn coordinate(place: &Value) -> Option<(f64, f64)> {
+    Some((place["lat"].as_f64()?, place["lon"].as_f64()?))
+}
+
+/// Reads a `/api/v1/one-to-all` response. `duration` is in minutes there; the
+/// origin itself is reached at zero.
+fn reached_places(reachable: &Value, max_seconds: f64) -> Result<Vec<Reached>, String> {
+    let (lat, lon) = coordinate(&reachable["one"]).ok_or_else(|| {
+        "One-to-all response has no origin. Next action: pass the /api/v1/one-to-all result unchanged."
+            .to_string()
+    })?;
+    let mut places = vec![Reached {
+        lat,
+        lon,
+        seconds: 0.0,
+    }];
+    for entry in reachable["all"].as_array().into_iter().flatten() {
+        let Some((lat, lon)) = coordinate(&entry["place"]) else {
+            continue;
+        };
+        // Without a duration the arrival is unknown; zero would draw the
+        // entry as reached immediately.
+        let Some(minutes) = entry["duration"].as_f64() else {
+            continue;
+        };
+        let seconds = minutes * 60.0;
+        if seconds <= max_seconds {
+            places.push(Reached { lat, lon, seconds });
+        }
+    }
+    Ok(places)
+}
+
+/// Earliest arrival per cell on a local equirectangular grid centred on the
+/// origin. Cell `(0, 0)` is the south-west corner.
+struct Raster {
+    origin: (f64, f64),
+    meters_per_degree_lon: f64,
+    min_x: f64,
+    min_y: f64,
+    cell: f64,
+    width: usize,
+    height: usize,
+    seconds: Vec<f64>,
+}
+
+impl Raster {
+    fn build(places: &[Reached], max_seconds: f64, speed: f64, cell: f64) -> Raster {
+        let origin = (places[0].lat, places[0].lon);
+        let meters_per_degree_lon = METERS_PER_DEGREE * origin.0.to_radians().cos();
+        let project = |p: &Reached| {
+            (
+                (p.lon - origin.1) * meters_per_degree_lon,
+                (p.lat - origin.0) * METERS_PER_DEGREE,
+            )
+        };
+
+        let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+        let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for place in places {
+            let (x, y) = project(place);
+            let radius = (max_seconds - place.seconds) * speed;
+            min_x = min_x.min(x - radius);
+            min_y = min_y.min(y - radius);
+            max_x = max_x.max(x + radius);
+            max_y = max_y.max(y + radius);
+        }
+        let mut cell = cell;
+        let cells = |cell: f64| ((max_x - min_x) / cell + 3.0) * ((max_y - min_y) / cell + 3.0);
+        if cells(cell) > MAX_CELLS as f64 {
+            cell *= (cells(cell) / MAX_CELLS as f64).sqrt().ceil();
+        }
+        // One empty cell of margin so every ring closes inside the grid.
+        let (min_x, min_y) = (min_x - cell, min_y - cell);
+        let width = ((max_x - min_x) / cell).ceil() as usize + 2;
+        let height = ((max_y - min_y) / cell).ceil() as usize + 2;
+
+        let mut seconds = vec![f64::INFINITY; width * height];
+        for place in places {
+            let (px, py) = project(place);
+            let radius = (max_seconds - place.seconds) * speed;
+            let x0 = (((px - radius - min_x) / cell).floor().max(0.0)) as usize;
+            let y0 = (((py - radius - min_y) / cell).floor().max(0.0)) as usize;
+            let x1 = (((px + radius - min_x) / cell).ceil() as usize).min(width - 1);
+            let y1 = (((py + radius - min_y) / cell).ceil() as usize).min(height - 1);
+            for y in y0..=y1 {
+                let cy = min_y + (y as f64 + 0.5) * cell;
+                for x in x0..=x1 {
+                    let cx = min_x + (x as f64 + 0.5) * cell;
+                    let distance = ((cx - px).powi(2) + (cy - py).powi(2)).sqrt();
+                    let arrival = place.seconds + distance / speed;
+                    let slot = &mut seconds[y * width + x];
+                    if arrival <= max_seconds && arrival < *slot {
+                        *slot = arrival;
+                    }
+                }
+            }
+        }
+
+        Raster {
+            origin,
+            meters_per_degree_lon,
+            min_x,
+            min_y,
+            cell,
+            width,
+            height,
+            seconds,
+        }
+    }
+
+    /// Grid vertex to `[lon, lat]`, rounded to ~1 cm.
+    fn to_lon_lat(&self, (x, y): (f64, f64)) -> [f64; 2] {
+        let round = |v: f64| (v * 1e7).round() / 1e7;
+        [
+            round(self.origin.1 + (self.min_x + x * self.cell) / self.meters_per_degree_lon),
+            round(self.origin.0 + (self.min_y + y * self.cell) / METERS_PER_DEGREE),
+        ]
+    }
+
+    fn ring_coordinates(&self, ring: &[(f64, f64)]) -> Vec<[f64; 2]> {
+        let mut coordinates: Vec<[f64; 2]> = ring.iter().map(|&p| self.to_lon_lat(p)).collect();
+        coordinates.push(coordinates[0]);
+        coordinates
+    }
+
+    /// Dissolved MultiPolygon coordinates for all cells reached within
+    /// `limit` seconds.
+    fn band(&self, limit: f64, tolerance_cells: f64) -> Vec<Vec<Vec<[f64; 2]>>> {
+        let mask: Vec<bool> = self.seconds.iter().map(|&s| s <= limit).collect();
+        let polygons = contour::trace(&mask, self.width, self.height);
+        let rings: Vec<&[(f64, f64)]> = polygons
+            .iter()
+            .flat_map(|polygon| std::iter::once(&polygon.outer).chain(&polygon.holes))
+            .map(Vec::as_slice)
+            .collect();
+        let mut simplified = simplify::simplify_rings(&rings, tolerance_cells).into_iter();
+        polygons
+            .iter()
+            .filter_map(|polygon| {
+                let outer = simplified.next().flatten();
+                let holes: Vec<_> = simplified
+                    .by_ref()
+                    .take(polygon.holes.len())
+                    .flatten()
+                    .collect();
+                let mut rings = vec![self.ring_coordinates(&outer?)];
+                rings.extend(holes.iter().map(|hole| self.ring_coordinates(hole)));
+                Some(rings)
+            })
+            .collect()
+    }
+}
+
+/// Turns a one-to-all response into one dissolved (Multi)Polygon feature per
+/// time band. Each band covers everything reachable within its limit, so
+/// features are ordered largest first for drawing.
+pub fn isochrone_polygons(reachable: &Value, options: &IsochroneOptions) -> Result<Value, String> {
+    if options.max_travel_seconds == 0 {
+        return Err("Isochrones need a travel time budget (max_travel_seconds).".to_string());
+    }
+    let max_seconds = f64::from(options.max_travel_seconds);
+    let speed = options
+        .speed_meters_per_second
+        .unwrap_or(DEFAULT_WALK_SPEED);
+    let cell = options.cell_meters.unwrap_or(DEFAULT_CELL_METERS);
+    if !(speed.is_finite() && speed > 0.0 && cell.is_finite() && cell >= 1.0) {
+        return Err(format!(
+            "Invalid isochrone speed {} m/s or cell size {} m",
+            speed, cell
+        ));
+    }
+
+    let mut bands: Vec<u32> = options
+        .bands_minutes
+        .iter()
+        .map(|m| m * 60)
+        .filter(|&s| s > 0 && s <= options.max_travel_seconds)
+        .collect();
+    if bands.is_empty() {
+        bands.push(options.max_travel_seconds);
+    }
+    bands.sort_unstable_by(|a, b| b.cmp(a));
+    bands.dedup();
+
+    let places = reached_places(reachable, max_seconds)?;
+    let raster = Raster::build(&places, max_seconds, speed, cell);
+    let tolerance_cells = options.simplify_meters.unwrap_or(cell) / raster.cell;
+    logging::debug!(
+        ISOCHRONE,
+        "isochrones: {} places, {}x{} cells of {:.0} m, {} bands",
+        places.len(),
+        raster.width,
+        raster.height,
+        raster.cell,
+        bands.len()
+    );
+
+    let features: Vec<Value> = bands
+        .iter()
+        .map(|&seconds| {
+            let polygons = raster.band(f64::from(seconds), tolerance_cells);
+            let geometry = match polygons.len() {
+                0 => Value::Null,
+                1 => json!({ "type": "Polygon", "coordinates": polygons[0] }),
+                _ => json!({ "type": "MultiPolygon", "coordinates": polygons }),
+            };
+            json!({
+                "type": "Feature",
+                "properties": { "seconds": seconds, "minutes": seconds / 60 },
+                "geometry": geometry,
+            })
+        })
+        .collect();
+    Ok(json!({ "type": "FeatureCollection", "features": features }))
+}
+
+fn metadata_document(
+    reachable: &Value,
+    options: &IsochroneOptions,
+    metadata: &IsochroneMetadata,
+) -> Value {
+    json!({
+        "generator": "MOTIS",
+        "created_at": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        "origin": {
+            "lat": reachable["one"]["lat"],
+            "lon": reachable["one"]["lon"],
+            "name": metadata.origin_name.clone().or_else(|| reachable["one"]["name"].as_str().map(String::from)),
+        },
+        "departure_time": metadata.departure_time,
+        "arrive_by": metadata.arrive_by,
+        "transit_modes": metadata.transit_modes,
+        "street_modes": metadata.street_modes,
+        "query": metadata.query,
+        "options": options,
+    })
+}
+
+fn shapefile_zip(collection: &Value, document: &Value, base: &str) -> Result<Vec<u8>, String> {
+    let fields = [
+        shapefile::Field {
+            name: "MINUTES",
+            kind: b'N',
+            width: 6,
+            decimals: 0,
+        },
+        shapefile::Field {
+            name: "SECONDS",
+            kind: b'N',
+            width: 8,
+            decimals: 0,
+        },
+        shapefile::Field {
+            name: "ORIG_LAT",
+            kind: b'N',
+            width: 12,
+            decimals: 7,
+        },
+        shapefile::Field {
+            name: "ORIG_LON",
+            kind: b'N',
+            width: 12,
+            decimals: 7,
+        },
+        shapefile::Field {
+            name: "ORIG_NAME",
+            kind: b'C',
+            width: 100,
+            decimals: 0,
+        },
+        shapefile::Field {
+            name: "DEPARTURE",
+            kind: b'C',
+            width: 25,
+            decimals: 0,
+        },
+        shapefile::Field {
+            name: "ARRIVE_BY",
+            kind: b'L',
+            width: 1,
+            decimals: 0,
+        },
+        shapefile::Field {
+            name: "MODES",
+            kind: b'C',
+            width: 100,
+            decimals: 0,
+        },
+    ];
+    let features = collection["features"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let text = |v: &Value| v.as_str().unwrap_or_default().to_string();
+    let coordinate = |v: &Value| v.as_f64().map(|c| format!("{:.7}", c)).unwrap_or_default();
+    let modes: Vec<String> = ["transit_modes", "street_modes"]
+        .iter()
+        .flat_map(|key| document[*key].as_array().cloned().unwrap_or_default())
+        .map(|m| text(&m))
+        .collect();
+    let records: Vec<Vec<String>> = features
+        .iter()
+        .map(|feature| {
+            vec![
+                feature["properties"]["minutes"].to_string(),
+                feature["properties"]["seconds"].to_string(),
+                coordinate(&document["origin"]["lat"]),
+                coordinate(&document["origin"]["lon"]),
+                text(&document["origin"]["name"]),
+                text(&document["departure_time"]),
+                if document["arrive_by"].as_bool().unwrap_or(false) {
+                    "T"
+                } else {
+                    "F"
+                }
+                .to_string(),
+                modes.join(","),
+            ]
+        })
+        .collect();
+    let shp = shapefile::write(&features, &fields, &records);
+
+    let mut zip = ZipWriter::new();
+    let metadata = serde_json::to_string_pretty(document).map_err(|e| e.to_string())?;
+    let add = |zip: &mut ZipWriter, ext: &str, data: &[u8]| {
+        zip.add(&format!("{}.{}", base, ext), data)
+            .map_err(|e| format!("Failed to build Shapefile archive: {}", e))
+    };
+    add(&mut zip, "shp", &shp.shp)?;
+    add(&mut zip, "shx", &shp.shx)?;
+    add(&mut zip, "dbf", &shp.dbf)?;
+    add(&mut zip, "prj", shp.prj.as_bytes())?;
+    add(&mut zip, "cpg", shp.cpg.as_bytes())?;
+    add(&mut zip, "json", metadata.as_bytes())?;
+    Ok(zip.finish())
+}
+
+/// Computes isochrone polygons and writes them with their search parameters
+/// to `path`. Without an explicit format the file extension decides
+/// (`.geojson`/`.json`, `.zip` for a zipped Shapefile).
+pub fn export_isochrones(
+    reachable: &Value,
+    options: &IsochroneOptions,
+    metadata: &IsochroneMetadata,
+    format: Option<IsochroneFormat>,
+    path: &Path,
+) -> Result<IsochroneFormat, String> {
+    let format = format
+        .or_else(|| IsochroneFormat::from_path(path))
+        .ok_or_else(|| {
+            format!(
+                "Cannot tell isochrone format from '{}'. Next action: use a .geojson or .zip file name.",
+                path.display()
+            )
+        })?;
+    let mut collection = isochrone_polygons(reachable, options)?;
+    let document = metadata_document(reachable, options, metadata);
+
+    match format {
+        IsochroneFormat::GeoJson => {
+            collection["metadata"] = document;
+            write_export(path, &collection.to_string())?;
+        }
+        IsochroneFormat::Shapefile => {
+            let base = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .filter(|s| !s.is_empty())
+                .unwrap_or("isochrones");
+            write_export_bytes(path, &shapefile_zip(&collection, &document, base)?)?;
+        }
+    }
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        contour, isochrone_polygons, metadata_document, shapefile, shapefile_zip, simplify,
+        IsochroneMetadata, IsochroneOptions,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn trace_finds_holes_and_splits_diagonal_cells() {
+        // A 3x3 block with an empty centre, plus a cell touching the block
+        // only at a corner.
+        #[rustfmt::skip]
+        let rows = [
+            "...#.",
+            "###..",
+            "#.#..",
+            "###..",
+        ];
+        let height = rows.len();
+        let width = rows[0].len();
+        // Row 0 of the mask is the south edge.
+        let mask: Vec<bool> = rows
+            .iter()
+            .rev()
+            .flat_map(|r| r.chars().map(|c| c == '#'))
+            .collect();
+        let mut polygons = contour::trace(&mask, width, height);
+        polygons.sort_by(|a, b| {
+            contour::signed_area(&b.outer).total_cmp(&contour::signed_area(&a.outer))
+        });
+
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(contour::signed_area(&polygons[0].outer), 9.0);
+        assert_eq!(polygons[0].holes.len(), 1);
+        assert_eq!(contour::signed_area(&polygons[0].holes[0]), -1.0);
+        assert_eq!(contour::signed_area(&polygons[1].outer), 1.0);
+        assert!(polygons[1].holes.is_empty());
+    }
+
+    #[test]
+    fn simplify_keeps_square_and_drops_staircase_noise() {
+        let square = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        assert_eq!(simplify::simplify_ring(&square, 1.0), Some(square.clone()));
+
+        let mut stairs = vec![(0.0, 0.0), (10.0, 0.0)];
+        for i in 0..10 {
+            let x = 10.0 - i as f64;
+            stairs.push((x, 1.0 + i as f64));
+            stairs.push((x - 1.0, 1.0 + i as f64));
+        }
+        let simplified = simplify::simplify_ring(&stairs, 1.0).expect("ring");
+        assert!(simplified.len() <= 4, "{:?}", simplified);
+    }
+
+    #[test]
+    fn simplified_rings_keep_a_one_cell_gap() {
+        // e.g. `simplify_meters: 150` on 50 m cells.
+        const TOLERANCE: f64 = 3.0;
+        // Two interlocking combs, one empty cell apart everywhere.
+        #[rustfmt::skip]
+        let rows = [
+            "#########.##",
+            "##........##",
+            "##.#########",
+            "##........##",
+            "#########.##",
+            "##........##",
+            "##.#########",
+            "##........##",
+            "#########.##",
+        ];
+        let height = rows.len();
+        let width = rows[0].len();
+        let mask: Vec<bool> = rows
+            .iter()
+            .rev()
+            .flat_map(|r| r.chars().map(|c| c == '#'))
+            .collect();
+        let polygons = contour::trace(&mask, width, height);
+        assert_eq!(polygons.len(), 2);
+        let rings: Vec<&[(f64, f64)]> = polygons.iter().map(|p| p.outer.as_slice()).collect();
+        let cells_in_both = |rings: &[Vec<(f64, f64)>]| {
+            (0..width * height)
+                .map(|i| ((i % width) as f64 + 0.5, (i / width) as f64 + 0.5))
+                .filter(|&c| rings.iter().all(|ring| contour::contains(ring, c)))
+                .count()
+        };
+
+        // Simplified one by one, each comb swallows the other's teeth.
+        let separate: Vec<_> = rings
+            .iter()
+            .filter_map(|ring| simplify::simplify_ring(ring, TOLERANCE))
+            .collect();
+        assert_eq!(separate.len(), 2);
+        assert!(cells_in_both(&separate) > 0);
+
+        let together: Vec<_> = simplify::simplify_rings(&rings, TOLERANCE)
+            .into_iter()
+            .map(|ring| ring.expect("ring"))
+            .collect();
+        assert_eq!(cells_in_both(&together), 0);
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let centre = (x as f64 + 0.5, y as f64 + 0.5);
+                let covered = together.iter().any(|ring| contour::contains(ring, centre));
+                assert_eq!(covered, c == '#', "cell {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn bands_are_nested_polygons_around_reached_stops() {
+        let reachable = json!({
+            "one": { "lat": 59.33, "lon": 18.06 },
+            "all": [
+                { "place": { "lat": 59.36, "lon": 18.14 }, "duration": 10 },
+                { "place": { "lat": 59.20, "lon": 18.00 }, "duration": 40 },
+                // No duration: skipped rather than treated as reached at once.
+                { "place": { "lat": 59.10, "lon": 17.90 } },
+            ],
+        });
+        let options = IsochroneOptions {
+            max_travel_seconds: 1200,
+            bands_minutes: vec![10, 20, 20],
+            ..IsochroneOptions::default()
+        };
+        let collection = isochrone_polygons(&reachable, &options).expect("isochrones");
+        let features = collection["features"].as_array().expect("features");
+
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0]["properties"]["minutes"], 20);
+        // 20 min: origin and the 10-minute stop are separate islands.
+        assert_eq!(features[0]["geometry"]["type"], "MultiPolygon");
+        assert_eq!(
+            features[0]["geometry"]["coordinates"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        // 10 min: only the walking buffer around the origin.
+        assert_eq!(features[1]["geometry"]["type"], "Polygon");
+        let ring = features[1]["geometry"]["coordinates"][0]
+            .as_array()
+            .unwrap();
+        assert_eq!(ring.first(), ring.last());
+        let lats: Vec<f64> = ring.iter().map(|p| p[1].as_f64().unwrap()).collect();
+        let north = lats.iter().cloned().fold(f64::MIN, f64::max);
+        // 600 s at 1.2 m/s is 720 m, about 0.0065 degrees of latitude.
+        assert!(
+            (north - 59.33 - 0.0065).abs() < 0.001,
+            "north edge {}",
+            north
+        );
+    }
+
+    #[test]
+    fn shapefile_parts_have_valid_headers_and_records() {
+        let be_i32 = |b: &[u8], at: usize| i32::from_be_bytes(b[at..at + 4].try_into().unwrap());
+        let le_i32 = |b: &[u8], at: usize| i32::from_le_bytes(b[at..at + 4].try_into().unwrap());
+        let le_f64 = |b: &[u8], at: usize| f64::from_le_bytes(b[at..at + 8].try_into().unwrap());
+        let le_u16 = |b: &[u8], at: usize| u16::from_le_bytes([b[at], b[at + 1]]) as usize;
+
+        // GeoJSON winding: counter-clockwise outer ring, clockwise hole.
+        let features = vec![
+            json!({ "geometry": { "type": "Polygon", "coordinates": [
+                [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0], [0.0, 0.0]],
+                [[0.5, 0.5], [0.5, 1.5], [1.5, 1.5], [1.5, 0.5], [0.5, 0.5]],
+            ] } }),
+            json!({ "geometry": null }),
+        ];
+        let fields = [
+            shapefile::Field {
+                name: "MINUTES",
+                kind: b'N',
+                width: 6,
+                decimals: 0,
+            },
+            shapefile::Field {
+                name: "NAME",
+                kind: b'C',
+                width: 10,
+                decimals: 0,
+            },
+        ];
+        let records = vec![
+            vec!["10".to_string(), "Slussen".to_string()],
+            vec!["20".to_string(), String::new()],
+        ];
+        let parts = shapefile::write(&features, &fields, &records);
+
+        // .shp header: file code, length in 16-bit words, version, type, bbox.
+        let shp = &parts.shp;
+        assert_eq!(be_i32(shp, 0), 9994);
+        assert_eq!(be_i32(shp, 24) as usize * 2, shp.len());
+        assert_eq!((le_i32(shp, 28), le_i32(shp, 32)), (1000, 5));
+        let bbox: Vec<f64> = (0..4).map(|i| le_f64(shp, 36 + 8 * i)).collect();
+        assert_eq!(bbox, [0.0, 0.0, 2.0, 2.0]);
+
+        // First record: polygon with two parts of five points each.
+        assert_eq!(be_i32(shp, 100), 1);
+        let first_len = be_i32(shp, 104) as usize * 2;
+        let content = &shp[108..108 + first_len];
+        assert_eq!(le_i32(content, 0), 5);
+        assert_eq!((le_i32(content, 36), le_i32(content, 40)), (2, 10));
+        assert_eq!((le_i32(content, 44), le_i32(content, 48)), (0, 5));
+        assert_eq!(first_len, 52 + 10 * 16);
+        let ring_area = |start: usize| {
+            let points: Vec<(f64, f64)> = (start..start + 5)
+                .map(|i| (le_f64(content, 52 + 16 * i), le_f64(content, 60 + 16 * i)))
+                .collect();
+            points
+                .windows(2)
+                .map(|w| w[0].0 * w[1].1 - w[1].0 * w[0].1)
+                .sum::<f64>()
+                / 2.0
+        };
+        // Shapefile winding is the reverse: clockwise outer, counter-clockwise hole.
+        assert_eq!(ring_area(0), -4.0);
+        assert_eq!(ring_area(5), 1.0);
+
+        // Second record: null shape, and nothing after it.
+        let second = 108 + first_len;
+        assert_eq!(be_i32(shp, second), 2);
+        assert_eq!(be_i32(shp, second + 4), 2);
+        assert_eq!(le_i32(shp, second + 8), 0);
+        assert_eq!(shp.len(), second + 12);
+
+        // .shx: same header, then offset and length per record in words.
+        let shx = &parts.shx;
+        assert_eq!(shx[..24], shp[..24]);
+        assert_eq!(be_i32(shx, 24) as usize * 2, shx.len());
+        assert_eq!(shx.len(), 100 + 2 * 8);
+        assert_eq!(be_i32(shx, 100) as usize * 2, 100);
+        assert_eq!(be_i32(shx, 104) as usize * 2, first_len);
+        assert_eq!(be_i32(shx, 108) as usize * 2, second);
+        assert_eq!(be_i32(shx, 112), 2);
+
+        // .dbf: header and record sizes match the fields, records are padded.
+        let dbf = &parts.dbf;
+        let header_size = le_u16(dbf, 8);
+        let record_size = le_u16(dbf, 10);
+        assert_eq!(dbf[0], 0x03);
+        assert_eq!(u32::from_le_bytes(dbf[4..8].try_into().unwrap()), 2);
+        assert_eq!(header_size, 32 + 32 * 2 + 1);
+        assert_eq!(record_size, 1 + 6 + 10);
+        assert_eq!(&dbf[32..43], b"MINUTES\0\0\0\0");
+        assert_eq!((dbf[43], dbf[48], dbf[49]), (b'N', 6, 0));
+        assert_eq!(dbf[header_size - 1], 0x0d);
+        assert_eq!(dbf.len(), header_size + 2 * record_size + 1);
+        assert_eq!(
+            &dbf[header_size..header_size + record_size],
+            b"     10Slussen   "
+        );
+        assert_eq!(dbf[dbf.len() - 1], 0x1a);
+    }
+
+    #[test]
+    fn shapefile_archive_holds_all_parts_and_metadata() {
+        let reachable = json!({ "one": { "lat": 59.33, "lon": 18.06, "name": "T-Centralen" } });
+        let options = IsochroneOptions {
+            max_travel_seconds: 600,
+            ..IsochroneOptions::default()
+        };
+        let metadata = IsochroneMetadata {
+            departure_time: Some("2026-03-02T08:00:00Z".to_string()),
+            transit_modes: vec!["BUS".to_string()],
+            street_modes: vec!["WALK".to_string()],
+            ..IsochroneMetadata::default()
+        };
+        let collection = isochrone_polygons(&reachable, &options).expect("isochrones");
+        let document = metadata_document(&reachable, &options, &metadata);
+        assert_eq!(document["origin"]["name"], "T-Centralen");
+        assert_eq!(document["options"]["max_travel_seconds"], 600);
+
+        let zip = shapefile_zip(&collection, &document, "iso").expect("zip");
+        let names: Vec<&str> = [
+            "iso.shp", "iso.shx", "iso.dbf", "iso.prj", "iso.cpg", "iso.json",
+        ]
+        .into_iter()
+        .filter(|name| zip.windows(name.len()).any(|w| w == name.as_bytes()))
+        .collect();
+        assert_eq!(names.len(), 6);
+        // End of central directory lists six entries.
+        let eocd = zip.len() - 22;
+        assert_eq!(&zip[eocd..eocd + 4], &0x0605_4b50u32.to_le_bytes());
+        assert_eq!(u16::from_le_bytes([zip[eocd + 10], zip[eocd + 11]]), 6);
+    }
+}
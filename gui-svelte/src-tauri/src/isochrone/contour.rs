@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+/// Closed ring in grid vertex coordinates; the first point is not repeated.
+pub(super) type Ring = Vec<(f64, f64)>;
+
+/// Outer ring (counter-clockwise) with its holes (clockwise).
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Polygon {
+    pub outer: Ring,
+    pub holes: Vec<Ring>,
+}
+
+// Edge directions, counter-clockwise: east, north, west, south.
+const STEPS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+pub(super) fn signed_area(ring: &[(f64, f64)]) -> f64 {
+    let n = ring.len();
+    (0..n)
+        .map(|i| {
+            let (x0, y0) = ring[i];
+            let (x1, y1) = ring[(i + 1) % n];
+            x0 * y1 - x1 * y0
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+pub(super) fn contains(ring: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    let n = ring.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (x0, y0) = ring[i];
+        let (x1, y1) = ring[(i + 1) % n];
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) / (y1 - y0) * (x1 - x0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Traces the boundary of the filled cells of a `width` x `height` mask into
+/// polygons. Boundary edges keep filled cells on their left, so outer rings
+/// come out counter-clockwise and holes clockwise. Cells touching only at a
+/// corner end up in separate rings, which keeps every ring simple.
+pub(super) fn trace(mask: &[bool], width: usize, height: usize) -> Vec<Polygon> {
+    let filled = |x: i64, y: i64| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < width
+            && (y as usize) < height
+            && mask[y as usize * width + x as usize]
+    };
+
+    // Outgoing boundary edges per vertex, by direction.
+    let mut edges: HashMap<(i64, i64), [bool; 4]> = HashMap::new();
+    let mut add = |from: (i64, i64), dir: usize| {
+        edges.entry(from).or_default()[dir] = true;
+    };
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            if !filled(x, y) {
+                continue;
+            }
+            if !filled(x, y - 1) {
+                add((x, y), 0);
+            }
+            if !filled(x + 1, y) {
+                add((x + 1, y), 1);
+            }
+            if !filled(x, y + 1) {
+                add((x + 1, y + 1), 2);
+            }
+            if !filled(x - 1, y) {
+                add((x, y + 1), 3);
+            }
+        }
+    }
+
+    let mut starts: Vec<(i64, i64)> = edges.keys().copied().collect();
+    starts.sort_unstable();
+    let mut outers: Vec<Ring> = Vec::new();
+    let mut holes: Vec<(Ring, (f64, f64))> = Vec::new();
+    for start in starts {
+        while let Some(first) = edges
+            .get(&start)
+            .and_then(|dirs| dirs.iter().position(|&d| d))
+        {
+            let mut ring = Vec::new();
+            let mut vertex = start;
+            let mut dir = first;
+            loop {
+                edges.get_mut(&vertex).expect("edge")[dir] = false;
+                ring.push((vertex.0 as f64, vertex.1 as f64));
+                vertex = (vertex.0 + STEPS[dir].0, vertex.1 + STEPS[dir].1);
+                if vertex == start {
+                    break;
+                }
+                let Some(next) = edges.get(&vertex).and_then(|dirs| {
+                    // Left turn first, then straight, then right.
+                    [(dir + 1) % 4, dir, (dir + 3) % 4]
+                        .into_iter()
+                        .find(|&d| dirs[d])
+                }) else {
+                    break;
+                };
+                dir = next;
+            }
+            // Drop collinear vertices; the staircase keeps only corners.
+            let n = ring.len();
+            let ring: Ring = (0..n)
+                .filter(|&i| {
+                    let (px, py) = ring[(i + n - 1) % n];
+                    let (x, y) = ring[i];
+                    let (nx, ny) = ring[(i + 1) % n];
+                    (x - px) * (ny - y) - (y - py) * (nx - x) != 0.0
+                })
+                .map(|i| ring[i])
+                .collect();
+
+            if signed_area(&ring) > 0.0 {
+                outers.push(ring);
+            } else {
+                // Centre of the filled cell left of the first edge: inside the
+                // polygon that owns this hole, never on a boundary.
+                let (x0, y0) = ring[0];
+                let (x1, y1) = ring[1 % ring.len()];
+                let (dx, dy) = ((x1 - x0).signum(), (y1 - y0).signum());
+                let probe = (x0 + dx * 0.5 - dy * 0.5, y0 + dy * 0.5 + dx * 0.5);
+                holes.push((ring, probe));
+            }
+        }
+    }
+
+    let mut polygons: Vec<Polygon> = outers
+        .into_iter()
+        .map(|outer| Polygon {
+            outer,
+            holes: Vec::new(),
+        })
+        .collect();
+    for (hole, probe) in holes {
+        let owner = polygons
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| contains(&p.outer, probe))
+            .min_by(|(_, a), (_, b)| signed_area(&a.outer).total_cmp(&signed_area(&b.outer)))
+            .map(|(i, _)| i);
+        if let Some(owner) = owner {
+            polygons[owner].holes.push(hole);
+        }
+    }
+    polygons
+}
@@ -0,0 +1,223 @@
+//! ESRI Shapefile (polygon) writer for isochrone bands: `.shp`, `.shx`,
+//! `.dbf` with one record per band, plus `.prj` (WGS 84) and `.cpg`.
+
+use chrono::{Datelike, Utc};
+use serde_json::Value;
+
+const POLYGON: i32 = 5;
+const NULL_SHAPE: i32 = 0;
+const WGS84_PRJ: &str = "GEOGCS[\"GCS_WGS_1984\",DATUM[\"D_WGS_1984\",SPHEROID[\"WGS_1984\",6378137.0,298.257223563]],PRIMEM[\"Greenwich\",0.0],UNIT[\"Degree\",0.0174532925199433]]";
+
+/// dBase field: name (max 10 chars), type, width, decimals.
+pub(super) struct Field {
+    pub name: &'static str,
+    pub kind: u8,
+    pub width: u8,
+    pub decimals: u8,
+}
+
+pub(super) struct Shapefile {
+    pub shp: Vec<u8>,
+    pub shx: Vec<u8>,
+    pub dbf: Vec<u8>,
+    pub prj: &'static str,
+    pub cpg: &'static str,
+}
+
+type Ring = Vec<[f64; 2]>;
+
+/// Polygon rings of a GeoJSON Polygon/MultiPolygon geometry; `None` for a
+/// missing geometry (written as a null shape).
+fn geometry_rings(geometry: &Value) -> Option<Vec<Vec<Ring>>> {
+    let parse_polygon = |polygon: &Value| -> Vec<Ring> {
+        polygon
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|ring| {
+                ring.as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|p| Some([p[0].as_f64()?, p[1].as_f64()?]))
+                    .collect()
+            })
+            .collect()
+    };
+    match geometry["type"].as_str()? {
+        "Polygon" => Some(vec![parse_polygon(&geometry["coordinates"])]),
+        "MultiPolygon" => Some(
+            geometry["coordinates"]
+                .as_array()?
+                .iter()
+                .map(parse_polygon)
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn ring_area(ring: &[[f64; 2]]) -> f64 {
+    ring.windows(2)
+        .map(|w| w[0][0] * w[1][1] - w[1][0] * w[0][1])
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Shapefile rings run clockwise for outer boundaries and counter-clockwise
+/// for holes, the reverse of GeoJSON.
+fn shape_rings(polygons: &[Vec<Ring>]) -> Vec<Ring> {
+    let mut rings = Vec::new();
+    for polygon in polygons {
+        for (index, ring) in polygon.iter().enumerate() {
+            let mut ring = ring.clone();
+            let clockwise = ring_area(&ring) < 0.0;
+            if clockwise != (index == 0) {
+                ring.reverse();
+            }
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+fn bbox(rings: &[Ring]) -> [f64; 4] {
+    let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+    for point in rings.iter().flatten() {
+        bbox[0] = bbox[0].min(point[0]);
+        bbox[1] = bbox[1].min(point[1]);
+        bbox[2] = bbox[2].max(point[0]);
+        bbox[3] = bbox[3].max(point[1]);
+    }
+    bbox
+}
+
+fn shape_content(rings: Option<&[Ring]>) -> Vec<u8> {
+    let mut out = Vec::new();
+    let Some(rings) = rings.filter(|r| !r.is_empty()) else {
+        out.extend_from_slice(&NULL_SHAPE.to_le_bytes());
+        return out;
+    };
+    out.extend_from_slice(&POLYGON.to_le_bytes());
+    for value in bbox(rings) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    let points: usize = rings.iter().map(Vec::len).sum();
+    out.extend_from_slice(&(rings.len() as i32).to_le_bytes());
+    out.extend_from_slice(&(points as i32).to_le_bytes());
+    let mut start = 0i32;
+    for ring in rings {
+        out.extend_from_slice(&start.to_le_bytes());
+        start += ring.len() as i32;
+    }
+    for point in rings.iter().flatten() {
+        out.extend_from_slice(&point[0].to_le_bytes());
+        out.extend_from_slice(&point[1].to_le_bytes());
+    }
+    out
+}
+
+fn file_header(shape_type: i32, length_bytes: usize, bbox: [f64; 4]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(100);
+    out.extend_from_slice(&9994i32.to_be_bytes());
+    out.extend_from_slice(&[0u8; 20]);
+    out.extend_from_slice(&((length_bytes / 2) as i32).to_be_bytes());
+    out.extend_from_slice(&1000i32.to_le_bytes());
+    out.extend_from_slice(&shape_type.to_le_bytes());
+    for value in bbox {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    // Z and M ranges, unused for 2D polygons.
+    out.extend_from_slice(&[0u8; 32]);
+    out
+}
+
+/// Truncates to `width` bytes on a character boundary and pads with spaces.
+fn dbf_value(value: &str, width: usize, right_align: bool) -> Vec<u8> {
+    let mut end = value.len().min(width);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    let value = &value[..end];
+    let padding = " ".repeat(width - value.len());
+    let text = if right_align {
+        padding + value
+    } else {
+        value.to_string() + &padding
+    };
+    text.into_bytes()
+}
+
+fn dbf(fields: &[Field], records: &[Vec<String>]) -> Vec<u8> {
+    let today = Utc::now();
+    let record_size = 1 + fields.iter().map(|f| f.width as usize).sum::<usize>();
+    let header_size = 32 + 32 * fields.len() + 1;
+
+    let mut out = vec![
+        0x03,
+        (today.year() - 1900) as u8,
+        today.month() as u8,
+        today.day() as u8,
+    ];
+    out.extend_from_slice(&(records.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(header_size as u16).to_le_bytes());
+    out.extend_from_slice(&(record_size as u16).to_le_bytes());
+    out.extend_from_slice(&[0u8; 20]);
+    for field in fields {
+        let mut name = [0u8; 11];
+        name[..field.name.len()].copy_from_slice(field.name.as_bytes());
+        out.extend_from_slice(&name);
+        out.push(field.kind);
+        out.extend_from_slice(&[0u8; 4]);
+        out.push(field.width);
+        out.push(field.decimals);
+        out.extend_from_slice(&[0u8; 14]);
+    }
+    out.push(0x0d);
+    for record in records {
+        out.push(b' ');
+        for (field, value) in fields.iter().zip(record) {
+            out.extend(dbf_value(value, field.width as usize, field.kind == b'N'));
+        }
+    }
+    out.push(0x1a);
+    out
+}
+
+/// Builds the Shapefile parts for `features` (GeoJSON polygon features) with
+/// one attribute row per feature.
+pub(super) fn write(features: &[Value], fields: &[Field], records: &[Vec<String>]) -> Shapefile {
+    let shapes: Vec<Option<Vec<Ring>>> = features
+        .iter()
+        .map(|f| geometry_rings(&f["geometry"]).map(|p| shape_rings(&p)))
+        .collect();
+    let all_rings: Vec<Ring> = shapes.iter().flatten().flatten().cloned().collect();
+    let bbox = if all_rings.is_empty() {
+        [0.0; 4]
+    } else {
+        bbox(&all_rings)
+    };
+
+    let mut shp_records = Vec::new();
+    let mut shx_records = Vec::new();
+    for (index, shape) in shapes.iter().enumerate() {
+        let content = shape_content(shape.as_deref());
+        let offset = 100 + shp_records.len();
+        shx_records.extend_from_slice(&((offset / 2) as i32).to_be_bytes());
+        shx_records.extend_from_slice(&((content.len() / 2) as i32).to_be_bytes());
+        shp_records.extend_from_slice(&(index as i32 + 1).to_be_bytes());
+        shp_records.extend_from_slice(&((content.len() / 2) as i32).to_be_bytes());
+        shp_records.extend_from_slice(&content);
+    }
+
+    let mut shp = file_header(POLYGON, 100 + shp_records.len(), bbox);
+    shp.extend_from_slice(&shp_records);
+    let mut shx = file_header(POLYGON, 100 + shx_records.len(), bbox);
+    shx.extend_from_slice(&shx_records);
+    Shapefile {
+        shp,
+        shx,
+        dbf: dbf(fields, records),
+        prj: WGS84_PRJ,
+        cpg: "UTF-8",
+    }
+}
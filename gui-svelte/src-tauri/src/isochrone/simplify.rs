@@ -0,0 +1,148 @@
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    let (x, y) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - x).powi(2) + (p.1 - y).powi(2)).sqrt()
+}
+
+fn rdp(points: &[(f64, f64)], tolerance: f64, out: &mut Vec<(f64, f64)>) {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let farthest = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, &p)| (i + 1, distance_to_segment(p, first, last)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match farthest {
+        Some((index, distance)) if distance > tolerance => {
+            rdp(&points[..=index], tolerance, out);
+            rdp(&points[index..], tolerance, out);
+        }
+        _ => out.push(first),
+    }
+}
+
+/// Ramer-Douglas-Peucker on a closed ring (first point not repeated). The
+/// ring is split at its two mutually distant points so both halves are open
+/// polylines. Returns `None` when fewer than three points survive.
+pub(super) fn simplify_ring(ring: &[(f64, f64)], tolerance: f64) -> Option<Vec<(f64, f64)>> {
+    if ring.len() < 3 {
+        return None;
+    }
+    if tolerance <= 0.0 || ring.len() == 3 {
+        return Some(ring.to_vec());
+    }
+    let split = (1..ring.len())
+        .max_by(|&a, &b| {
+            let da = (ring[a].0 - ring[0].0).powi(2) + (ring[a].1 - ring[0].1).powi(2);
+            let db = (ring[b].0 - ring[0].0).powi(2) + (ring[b].1 - ring[0].1).powi(2);
+            da.total_cmp(&db)
+        })
+        .unwrap_or(1);
+
+    let mut closed = ring.to_vec();
+    closed.push(ring[0]);
+    let mut out = Vec::new();
+    rdp(&closed[..=split], tolerance, &mut out);
+    rdp(&closed[split..], tolerance, &mut out);
+    (out.len() >= 3).then_some(out)
+}
+
+/// Ring index and the two endpoints.
+type Segment = (usize, (f64, f64), (f64, f64));
+
+fn orientation(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+/// `p` lies on segment `a`-`b` (collinear already checked) other than at an
+/// endpoint.
+fn inside_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> bool {
+    p != a
+        && p != b
+        && p.0 >= a.0.min(b.0)
+        && p.0 <= a.0.max(b.0)
+        && p.1 >= a.1.min(b.1)
+        && p.1 <= a.1.max(b.1)
+}
+
+/// Segments cross or one runs into the other. Meeting at a shared endpoint
+/// is allowed: traced rings touch like that where cells meet at a corner.
+/// Grid vertices are integers, so the orientation tests are exact.
+fn segments_meet(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> bool {
+    let (d1, d2) = (orientation(c, d, a), orientation(c, d, b));
+    let (d3, d4) = (orientation(a, b, c), orientation(a, b, d));
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+    (d1 == 0.0 && inside_segment(a, c, d))
+        || (d2 == 0.0 && inside_segment(b, c, d))
+        || (d3 == 0.0 && inside_segment(c, a, b))
+        || (d4 == 0.0 && inside_segment(d, a, b))
+}
+
+/// Indices of the rings with a segment meeting another segment, of the same
+/// or another ring. Sweeps segments by their western end so only segments
+/// overlapping in x are compared.
+fn meeting_rings(rings: &[Option<Vec<(f64, f64)>>]) -> Vec<usize> {
+    let mut segments: Vec<Segment> = rings
+        .iter()
+        .enumerate()
+        .filter_map(|(i, ring)| Some((i, ring.as_ref()?)))
+        .flat_map(|(i, ring)| {
+            (0..ring.len()).map(move |k| (i, ring[k], ring[(k + 1) % ring.len()]))
+        })
+        .collect();
+    segments.sort_by(|a, b| a.1 .0.min(a.2 .0).total_cmp(&b.1 .0.min(b.2 .0)));
+
+    let mut meeting = vec![false; rings.len()];
+    let mut active: Vec<usize> = Vec::new();
+    for (k, &(ring, a, b)) in segments.iter().enumerate() {
+        let west = a.0.min(b.0);
+        active.retain(|&j| segments[j].1 .0.max(segments[j].2 .0) >= west);
+        for &j in &active {
+            let (other, c, d) = segments[j];
+            if a.1.max(b.1) >= c.1.min(d.1)
+                && c.1.max(d.1) >= a.1.min(b.1)
+                && segments_meet(a, b, c, d)
+            {
+                meeting[ring] = true;
+                meeting[other] = true;
+            }
+        }
+        active.push(k);
+    }
+    (0..rings.len()).filter(|&i| meeting[i]).collect()
+}
+
+/// Simplifies all rings of one band (outers and holes) together. A ring
+/// whose simplified form would cross itself or another ring is kept as
+/// traced, so holes stay inside their outer ring and polygons separated by
+/// a single cell do not overlap. Traced rings never cross, so this ends.
+pub(super) fn simplify_rings(
+    rings: &[&[(f64, f64)]],
+    tolerance: f64,
+) -> Vec<Option<Vec<(f64, f64)>>> {
+    let mut simplified: Vec<Option<Vec<(f64, f64)>>> = rings
+        .iter()
+        .map(|ring| simplify_ring(ring, tolerance))
+        .collect();
+    let mut traced = vec![tolerance <= 0.0; rings.len()];
+    loop {
+        let mut changed = false;
+        for i in meeting_rings(&simplified) {
+            if !traced[i] {
+                simplified[i] = Some(rings[i].to_vec());
+                traced[i] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            return simplified;
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! Departure board kiosk mode (`--kiosk-stop <stopId>`).
+//!
+//! A background thread polls `/api/v5/stoptimes` for the configured stops and
+//! keeps the latest board in memory; the kiosk window renders
+//! `motis://localhost/kiosk/` and reads `board.json`. Backend failures keep
+//! the last good departures on screen (marked stale) until polling recovers.
+
+use crate::logging::{self, KIOSK};
+use crate::native;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const BOARD_HTML: &str = include_str!("kiosk/board.html");
+pub const BOARD_JS: &str = include_str!("kiosk/board.js");
+
+const DEFAULT_REFRESH: Duration = Duration::from_secs(30);
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+const DEFAULT_ROWS: usize = 12;
+
+static BOARD: Lazy<Mutex<Board>> = Lazy::new(|| Mutex::new(Board::default()));
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KioskConfig {
+    pub stop_ids: Vec<String>,
+    pub refresh: Duration,
+    pub rows: usize,
+}
+
+impl KioskConfig {
+    /// `--kiosk-stop <id>` (repeatable or comma-separated), optional
+    /// `--kiosk-refresh <seconds>` and `--kiosk-rows <n>`.
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        let mut stop_ids = Vec::new();
+        let mut refresh = DEFAULT_REFRESH;
+        let mut rows = DEFAULT_ROWS;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--kiosk-stop" => {
+                    if let Some(value) = iter.next() {
+                        stop_ids.extend(
+                            value
+                                .split(',')
+                                .map(str::trim)
+                                .filter(|id| !id.is_empty())
+                                .map(str::to_string),
+                        );
+                    }
+                }
+                "--kiosk-refresh" => {
+                    if let Some(secs) = iter.next().and_then(|v| v.parse::<u64>().ok()) {
+                        refresh = Duration::from_secs(secs.max(5));
+                    }
+                }
+                "--kiosk-rows" => {
+                    if let Some(n) = iter.next().and_then(|v| v.parse::<usize>().ok()) {
+                        rows = n.clamp(1, 50);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if stop_ids.is_empty() {
+            return None;
+        }
+        Some(KioskConfig {
+            stop_ids,
+            refresh,
+            rows,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Departure {
+    /// Expected departure (ISO 8601, UTC).
+    pub time: String,
+    pub scheduled_time: String,
+    pub line: String,
+    pub headsign: String,
+    pub mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_text_color: Option<String>,
+    pub realtime: bool,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StopBoard {
+    pub stop_id: String,
+    pub name: String,
+    pub departures: Vec<Departure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Board {
+    pub stops: Vec<StopBoard>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// True while showing departures from an earlier successful poll.
+    pub stale: bool,
+    pub refresh_seconds: u64,
+}
+
+fn non_empty(value: &Value) -> Option<String> {
+    value.as_str().filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+pub(crate) fn parse_stop_times(stop_id: &str, response: &Value) -> StopBoard {
+    let departures = response["stopTimes"]
+        .as_array()
+        .map(|stop_times| {
+            stop_times
+                .iter()
+                .filter_map(|st| {
+                    let place = &st["place"];
+                    let time = non_empty(&place["departure"])?;
+                    Some(Departure {
+                        scheduled_time: non_empty(&place["scheduledDeparture"])
+                            .unwrap_or_else(|| time.clone()),
+                        time,
+                        line: non_empty(&st["displayName"])
+                            .or_else(|| non_empty(&st["routeShortName"]))
+                            .or_else(|| non_empty(&st["tripShortName"]))
+                            .unwrap_or_default(),
+                        headsign: non_empty(&st["headsign"]).unwrap_or_default(),
+                        mode: non_empty(&st["mode"]).unwrap_or_default(),
+                        track: non_empty(&place["track"]),
+                        route_color: non_empty(&st["routeColor"]),
+                        route_text_color: non_empty(&st["routeTextColor"]),
+                        realtime: st["realTime"].as_bool().unwrap_or(false),
+                        cancelled: st["cancelled"].as_bool().unwrap_or(false)
+                            || st["tripCancelled"].as_bool().unwrap_or(false),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    StopBoard {
+        stop_id: stop_id.to_string(),
+        name: non_empty(&response["place"]["name"]).unwrap_or_else(|| stop_id.to_string()),
+        departures,
+        error: None,
+    }
+}
+
+fn fetch_stop(stop_id: &str, rows: usize) -> Result<StopBoard, String> {
+    let path = format!(
+        "/api/v5/stoptimes?stopId={}&n={}",
+        urlencoding::encode(stop_id),
+        rows
+    );
+    native::api_get_sync(&path)
+        .map(|response| parse_stop_times(stop_id, &response))
+        .map_err(|e| e.to_string())
+}
+
+/// Builds the next board; stops that fail keep their previous departures.
+pub(crate) fn merge_poll_results(
+    previous: &Board,
+    results: Vec<(String, Result<StopBoard, String>)>,
+) -> Board {
+    let mut stale = false;
+    let stops = results
+        .into_iter()
+        .map(|(stop_id, result)| match result {
+            Ok(stop) => stop,
+            Err(error) => {
+                stale = true;
+                let mut stop = previous
+                    .stops
+                    .iter()
+                    .find(|s| s.stop_id == stop_id)
+                    .cloned()
+                    .unwrap_or_else(|| StopBoard {
+                        name: stop_id.clone(),
+                        stop_id,
+                        ..StopBoard::default()
+                    });
+                stop.error = Some(error);
+                stop
+            }
+        })
+        .collect();
+
+    Board {
+        stops,
+        updated_at: if stale {
+            previous.updated_at.clone()
+        } else {
+            Some(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        },
+        stale,
+        refresh_seconds: previous.refresh_seconds,
+    }
+}
+
+fn poll_once(config: &KioskConfig) -> bool {
+    if !native::is_ipc_initialized() && !native::try_auto_init() {
+        logging::warning!(KIOSK, "Backend not ready, retrying");
+    }
+
+    let results = config
+        .stop_ids
+        .iter()
+        .map(|id| (id.clone(), fetch_stop(id, config.rows)))
+        .collect();
+
+    let Ok(mut board) = BOARD.lock() else {
+        return false;
+    };
+    let next = merge_poll_results(&board, results);
+    for stop in next.stops.iter().filter(|s| s.error.is_some()) {
+        logging::warning!(
+            KIOSK,
+            "Poll failed for {}: {}",
+            stop.stop_id,
+            stop.error.as_deref().unwrap_or("")
+        );
+    }
+    let ok = !next.stale;
+    *board = next;
+    ok
+}
+
+/// Starts the polling thread. Safe to call once per process.
+pub fn start(config: KioskConfig) {
+    if let Ok(mut board) = BOARD.lock() {
+        board.refresh_seconds = config.refresh.as_secs();
+    }
+    logging::info!(
+        KIOSK,
+        "Departure board for {:?}, refresh every {}s",
+        config.stop_ids,
+        config.refresh.as_secs()
+    );
+
+    std::thread::spawn(move || loop {
+        let delay = if poll_once(&config) {
+            config.refresh
+        } else {
+            RETRY_DELAY.min(config.refresh)
+        };
+        std::thread::sleep(delay);
+    });
+}
+
+pub fn board_json() -> Vec<u8> {
+    let board = BOARD.lock().map(|b| b.clone()).unwrap_or_default();
+    serde_json::to_vec(&board).unwrap_or_else(|_| b"{}".to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_poll_results, parse_stop_times, Board, KioskConfig, StopBoard};
+    use std::time::Duration;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn kiosk_args_collect_stops_and_options() {
+        let config = KioskConfig::from_args(&args(&[
+            "motis-gui-svelte",
+            "--kiosk-stop",
+            "A,B",
+            "--data-path",
+            "data",
+            "--kiosk-stop",
+            "C",
+            "--kiosk-refresh",
+            "2",
+        ]))
+        .expect("kiosk config");
+        assert_eq!(config.stop_ids, vec!["A", "B", "C"]);
+        assert_eq!(config.refresh, Duration::from_secs(5), "refresh is clamped");
+        assert!(KioskConfig::from_args(&args(&["motis-gui-svelte"])).is_none());
+    }
+
+    #[test]
+    fn stop_times_are_mapped_to_departures() {
+        let response = serde_json::json!({
+            "place": { "name": "Odenplan" },
+            "stopTimes": [
+                {
+                    "place": {
+                        "departure": "2026-10-18T12:05:00Z",
+                        "scheduledDeparture": "2026-10-18T12:03:00Z",
+                        "track": "2"
+                    },
+                    "mode": "BUS",
+                    "realTime": true,
+                    "headsign": "Karolinska",
+                    "routeShortName": "3",
+                    "displayName": "Bus 3",
+                    "routeColor": "d71d24",
+                    "cancelled": false,
+                    "tripCancelled": false
+                },
+                { "place": { "arrival": "2026-10-18T12:10:00Z" }, "headsign": "last stop" }
+            ]
+        });
+
+        let stop = parse_stop_times("odenplan", &response);
+        assert_eq!(stop.name, "Odenplan");
+        assert_eq!(stop.departures.len(), 1, "arrival-only entries are skipped");
+        let departure = &stop.departures[0];
+        assert_eq!(departure.line, "Bus 3");
+        assert_eq!(departure.track.as_deref(), Some("2"));
+        assert!(departure.realtime);
+    }
+
+    #[test]
+    fn failed_poll_keeps_previous_departures() {
+        let previous = Board {
+            stops: vec![StopBoard {
+                stop_id: "A".to_string(),
+                name: "Stop A".to_string(),
+                departures: vec![Default::default()],
+                error: None,
+            }],
+            updated_at: Some("2026-10-18T12:00:00Z".to_string()),
+            stale: false,
+            refresh_seconds: 30,
+        };
+
+        let board = merge_poll_results(&previous, vec![("A".to_string(), Err("IPC down".into()))]);
+        assert!(board.stale);
+        assert_eq!(board.stops[0].departures.len(), 1);
+        assert_eq!(board.stops[0].error.as_deref(), Some("IPC down"));
+        assert_eq!(board.updated_at, previous.updated_at);
+    }
+}
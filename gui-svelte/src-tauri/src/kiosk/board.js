@@ -0,0 +1,108 @@
+// Departure board renderer. Data comes from the Rust poller via board.json;
+// this page only re-reads the cached board and recomputes countdowns.
+(function () {
+  var FETCH_INTERVAL_MS = 10000;
+  var board = null;
+
+  function pad(n) {
+    return n < 10 ? "0" + n : String(n);
+  }
+
+  function clock(date) {
+    return pad(date.getHours()) + ":" + pad(date.getMinutes());
+  }
+
+  function text(tag, className, value) {
+    var el = document.createElement(tag);
+    if (className) el.className = className;
+    if (value !== undefined) el.textContent = value;
+    return el;
+  }
+
+  function departureCell(dep, now) {
+    var cell = text("td", "time");
+    var time = new Date(dep.time);
+    var scheduled = new Date(dep.scheduled_time);
+    var delayMin = Math.round((time - scheduled) / 60000);
+    var minutes = Math.floor((time - now) / 60000);
+
+    if (delayMin >= 1) {
+      cell.appendChild(text("span", "scheduled", clock(scheduled)));
+    }
+    var label = minutes <= 0 ? "Nu" : minutes < 15 ? minutes + " min" : clock(time);
+    cell.appendChild(text("span", delayMin >= 1 ? "late" : "", label));
+    return cell;
+  }
+
+  function renderStop(stop, now) {
+    var section = text("section");
+    section.appendChild(text("h2", "", stop.name));
+    if (stop.error) {
+      section.appendChild(text("div", "error", "Uppdatering misslyckades – visar senaste avgångar"));
+    }
+
+    var upcoming = stop.departures.filter(function (dep) {
+      return new Date(dep.time) - now > -60000;
+    });
+    if (upcoming.length === 0) {
+      section.appendChild(text("div", "empty", "Inga avgångar"));
+      return section;
+    }
+
+    var table = text("table");
+    upcoming.forEach(function (dep) {
+      var row = text("tr", dep.cancelled ? "cancelled" : "");
+      var line = text("span", "line", dep.line);
+      if (dep.route_color) line.style.background = "#" + dep.route_color;
+      if (dep.route_text_color) line.style.color = "#" + dep.route_text_color;
+      var lineCell = text("td");
+      lineCell.appendChild(line);
+      row.appendChild(lineCell);
+      row.appendChild(text("td", "headsign", dep.headsign));
+      row.appendChild(text("td", "", dep.track ? "Läge " + dep.track : ""));
+      row.appendChild(departureCell(dep, now));
+      table.appendChild(row);
+    });
+    section.appendChild(table);
+    return section;
+  }
+
+  function render() {
+    var now = new Date();
+    document.getElementById("clock").textContent = clock(now);
+    if (!board) return;
+
+    var status = document.getElementById("status");
+    var updated = board.updated_at ? clock(new Date(board.updated_at)) : "–";
+    status.textContent = board.stale
+      ? "Ingen kontakt med tidtabellen – senast uppdaterad " + updated
+      : "Uppdaterad " + updated;
+    status.className = board.stale ? "stale" : "";
+
+    var container = document.getElementById("board");
+    container.replaceChildren.apply(
+      container,
+      board.stops.map(function (stop) {
+        return renderStop(stop, now);
+      })
+    );
+  }
+
+  function refresh() {
+    fetch("board.json", { cache: "no-store" })
+      .then(function (response) {
+        return response.json();
+      })
+      .then(function (data) {
+        board = data;
+        render();
+      })
+      .catch(function () {
+        // Keep showing the previous board; the next tick retries.
+      });
+  }
+
+  refresh();
+  setInterval(refresh, FETCH_INTERVAL_MS);
+  setInterval(render, 1000);
+})();
@@ -1 +1,10 @@
+pub mod batch;
+pub mod export;
+pub mod isochrone;
+pub mod kiosk;
+pub mod logging;
+pub mod matrix;
+pub mod metrics;
 pub mod native;
+pub mod protocol;
+pub mod store;
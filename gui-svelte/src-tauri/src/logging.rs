@@ -0,0 +1,637 @@
+//! Leveled diagnostics with per-module targets.
+//!
+//! Lines go to stderr and to a rotating `motis-gui.log`, written next to the
+//! launcher's `launcher.log` on the USB bundle, or to the user cache dir
+//! when that location is read-only. Coordinates and free-text queries are
+//! redacted unless `MOTIS_LOG_REDACT=off`.
+//!
+//! Levels come from `--debug` (default `debug` instead of `info`) and
+//! `MOTIS_LOG`, e.g. `MOTIS_LOG=info,protocol=trace,ipc=debug`.
+//!
+//! While a motis:// request is handled its trace id (see [`enter_trace`]) is
+//! added to every line, so one search can be followed through protocol, IPC
+//! and `motis-ipc`.
+
+use once_cell::sync::Lazy;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const GUI: &str = "gui";
+pub const PROTOCOL: &str = "protocol";
+pub const IPC: &str = "ipc";
+pub const KIOSK: &str = "kiosk";
+pub const STORE: &str = "store";
+pub const BATCH: &str = "batch";
+pub const MATRIX: &str = "matrix";
+pub const ISOCHRONE: &str = "isochrone";
+
+const LOG_FILE: &str = "motis-gui.log";
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated files kept as `motis-gui.log.1` .. `.3`.
+const KEEP_ROTATED: usize = 3;
+/// URL parameters and JSON keys whose values are user-typed text.
+const SENSITIVE_PARAMS: [&str; 4] = ["query", "text", "q", "name"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn parse(value: &str) -> Option<Level> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" | "warning" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    default: Level,
+    targets: Vec<(String, Level)>,
+}
+
+impl Filter {
+    /// `MOTIS_LOG` syntax: comma-separated `level` or `target=level`.
+    /// Unknown entries are ignored so a typo never silences everything.
+    fn parse(spec: &str, default: Level) -> Filter {
+        let mut filter = Filter {
+            default,
+            targets: Vec::new(),
+        };
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((target, level)) => {
+                    if let Some(level) = Level::parse(level) {
+                        filter
+                            .targets
+                            .push((target.trim().to_ascii_lowercase(), level));
+                    }
+                }
+                None => {
+                    if let Some(level) = Level::parse(part) {
+                        filter.default = level;
+                    }
+                }
+            }
+        }
+        filter
+    }
+
+    fn level_for(&self, target: &str) -> Level {
+        self.targets
+            .iter()
+            .rev()
+            .find(|(t, _)| t == target)
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+}
+
+struct Config {
+    filter: Filter,
+    redact: bool,
+}
+
+fn redact_from_env() -> bool {
+    !matches!(
+        std::env::var("MOTIS_LOG_REDACT")
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+            .as_str(),
+        "0" | "off" | "false" | "no"
+    )
+}
+
+static CONFIG: Lazy<Mutex<Config>> = Lazy::new(|| {
+    Mutex::new(Config {
+        filter: Filter::parse(&std::env::var("MOTIS_LOG").unwrap_or_default(), Level::Info),
+        redact: redact_from_env(),
+    })
+});
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// Rotation threshold, [`MAX_FILE_BYTES`] outside tests.
+    max_bytes: u64,
+}
+
+impl LogFile {
+    fn open(path: PathBuf, max_bytes: u64) -> std::io::Result<LogFile> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile {
+            path,
+            file,
+            size,
+            max_bytes,
+        })
+    }
+
+    fn rotated(path: &Path, index: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        for index in (1..KEEP_ROTATED).rev() {
+            let from = Self::rotated(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, Self::rotated(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, Self::rotated(&self.path, 1))?;
+        *self = LogFile::open(self.path.clone(), self.max_bytes)?;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.size + line.len() as u64 > self.max_bytes && self.size > 0 {
+            if let Err(e) = self.rotate() {
+                eprintln!(
+                    "[MOTIS-GUI] Log rotation failed for {}: {}",
+                    self.path.display(),
+                    e
+                );
+            }
+        }
+        if self.file.write_all(line.as_bytes()).is_ok() {
+            self.size += line.len() as u64;
+        }
+    }
+}
+
+static LOG_FILE_HANDLE: Lazy<Mutex<Option<LogFile>>> = Lazy::new(|| Mutex::new(None));
+
+/// Per-user base directories under which the app keeps a `motis-gui/`
+/// folder.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum UserDir {
+    /// Settings and saved places: `%APPDATA%`, `~/Library/Application
+    /// Support`, `$XDG_CONFIG_HOME` or `~/.config`.
+    Config,
+    /// Logs: `%LOCALAPPDATA%`, `~/Library/Caches`, `$XDG_CACHE_HOME` or
+    /// `~/.cache`.
+    Cache,
+}
+
+impl UserDir {
+    pub(crate) fn path(self) -> Option<PathBuf> {
+        let (windows_var, macos_dir, xdg_var, xdg_default) = match self {
+            UserDir::Config => (
+                "APPDATA",
+                "Application Support",
+                "XDG_CONFIG_HOME",
+                ".config",
+            ),
+            UserDir::Cache => ("LOCALAPPDATA", "Caches", "XDG_CACHE_HOME", ".cache"),
+        };
+        if cfg!(windows) {
+            return std::env::var_os(windows_var).map(PathBuf::from);
+        }
+        let home = || std::env::var_os("HOME").map(PathBuf::from);
+        if cfg!(target_os = "macos") {
+            return home().map(|home| home.join("Library").join(macos_dir));
+        }
+        std::env::var_os(xdg_var)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(xdg_default)))
+    }
+}
+
+/// `MOTIS_LOG_FILE`, then `MOTIS_LOG_DIR` (set by RUN.sh to the bundle
+/// directory holding `launcher.log`), then the bundle next to
+/// `MOTIS_DATA_PATH`, then `<cache dir>/motis-gui/`.
+fn log_file_candidates() -> Vec<PathBuf> {
+    let env_path = |name: &str| {
+        std::env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    let mut candidates = Vec::new();
+    if let Some(path) = env_path("MOTIS_LOG_FILE") {
+        candidates.push(path);
+    }
+    if let Some(dir) = env_path("MOTIS_LOG_DIR") {
+        candidates.push(dir.join(LOG_FILE));
+    }
+    if let Some(bundle) =
+        env_path("MOTIS_DATA_PATH").and_then(|p| p.parent().map(Path::to_path_buf))
+    {
+        candidates.push(bundle.join(LOG_FILE));
+    }
+    if let Some(dir) = UserDir::Cache.path() {
+        candidates.push(dir.join("motis-gui").join(LOG_FILE));
+    }
+    candidates
+}
+
+/// Applies `--debug` and opens the log file. Before this runs (and in
+/// tests) lines only go to stderr.
+pub fn init(debug: bool) {
+    let default = if debug { Level::Debug } else { Level::Info };
+    if let Ok(mut config) = CONFIG.lock() {
+        config.filter = Filter::parse(&std::env::var("MOTIS_LOG").unwrap_or_default(), default);
+        config.redact = redact_from_env();
+    }
+
+    for path in log_file_candidates() {
+        match LogFile::open(path.clone(), MAX_FILE_BYTES) {
+            Ok(file) => {
+                if let Ok(mut guard) = LOG_FILE_HANDLE.lock() {
+                    *guard = Some(file);
+                }
+                log_at(
+                    Level::Info,
+                    GUI,
+                    format_args!("Logging to {}", path.display()),
+                );
+                return;
+            }
+            Err(e) => eprintln!(
+                "[MOTIS-GUI] Cannot write log file {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+}
+
+pub fn log_file_path() -> Option<PathBuf> {
+    LOG_FILE_HANDLE
+        .lock()
+        .ok()
+        .and_then(|guard| guard.as_ref().map(|f| f.path.clone()))
+}
+
+pub fn enabled(level: Level, target: &str) -> bool {
+    CONFIG
+        .lock()
+        .map(|config| level <= config.filter.level_for(target))
+        .unwrap_or(true)
+}
+
+fn redacting() -> bool {
+    CONFIG.lock().map(|config| config.redact).unwrap_or(true)
+}
+
+/// Marks user-typed text (search queries, place names) for the log; shows
+/// only its length while redaction is on.
+pub fn sensitive(text: &str) -> Cow<'_, str> {
+    if redacting() {
+        Cow::Owned(format!("<{} chars>", text.chars().count()))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// True if `chars[i]` directly follows a `%XX` escape, e.g. the `%2C` in an
+/// encoded `fromPlace=59.33%2C18.06`.
+fn follows_percent_escape(chars: &[char], i: usize) -> bool {
+    i >= 3
+        && chars[i - 3] == '%'
+        && chars[i - 2].is_ascii_hexdigit()
+        && chars[i - 1].is_ascii_hexdigit()
+}
+
+/// Cuts coordinate-like decimals (three or more fraction digits) to two
+/// digits, about 1 km, also inside percent-encoded URLs, and blanks
+/// sensitive URL parameter and JSON string values.
+pub(crate) fn redact(message: &str) -> String {
+    let chars: Vec<char> = message.chars().collect();
+    let mut out = String::with_capacity(message.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let starts_number = chars[i].is_ascii_digit()
+            && (i == 0
+                || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '.')
+                || follows_percent_escape(&chars, i));
+        if starts_number {
+            let int_end = (i..chars.len())
+                .find(|&j| !chars[j].is_ascii_digit())
+                .unwrap_or(chars.len());
+            if int_end - i <= 3 && chars.get(int_end) == Some(&'.') {
+                let frac_end = (int_end + 1..chars.len())
+                    .find(|&j| !chars[j].is_ascii_digit())
+                    .unwrap_or(chars.len());
+                if frac_end - int_end > 3 {
+                    if out.ends_with('-') {
+                        out.pop();
+                        out.push_str("~-");
+                    } else {
+                        out.push('~');
+                    }
+                    out.extend(&chars[i..int_end + 3]);
+                    i = frac_end;
+                    continue;
+                }
+            }
+        }
+
+        if (chars[i] == '?' || chars[i] == '&') && i + 1 < chars.len() {
+            let rest: String = chars[i + 1..].iter().take(8).collect();
+            if let Some(param) = SENSITIVE_PARAMS
+                .iter()
+                .find(|p| rest.starts_with(&format!("{}=", p)))
+            {
+                out.push(chars[i]);
+                out.push_str(param);
+                out.push_str("=<redacted>");
+                i += 1 + param.len() + 1;
+                while i < chars.len() && chars[i] != '&' && !chars[i].is_whitespace() {
+                    i += 1;
+                }
+                continue;
+            }
+        }
+
+        if chars[i] == '"' {
+            let rest: String = chars[i + 1..].iter().take(10).collect();
+            let json_value = SENSITIVE_PARAMS.iter().find_map(|p| {
+                let after_key = rest.strip_prefix(p)?.strip_prefix("\":")?;
+                let spaces = after_key.len() - after_key.trim_start().len();
+                after_key
+                    .trim_start()
+                    .starts_with('"')
+                    .then_some((p, p.len() + 2 + spaces))
+            });
+            if let Some((param, key_len)) = json_value {
+                out.push('"');
+                out.push_str(param);
+                out.push_str("\":\"<redacted>\"");
+                i += 1 + key_len + 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                i += 1;
+                continue;
+            }
+        }
+
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+thread_local! {
+    static CURRENT_TRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static TRACE_COUNTER: AtomicU64 = AtomicU64::new(0);
+/// Start-time bits so ids from different runs in one log file differ.
+static TRACE_PREFIX: Lazy<u32> = Lazy::new(|| {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    (nanos as u32) ^ std::process::id().rotate_left(16)
+});
+
+/// New 16-hex-digit trace id, unique within the process.
+pub fn new_trace_id() -> String {
+    let sequence = TRACE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:08x}{:08x}", *TRACE_PREFIX, sequence as u32)
+}
+
+/// Accepts caller-supplied ids (e.g. an `X-Trace-Id` header from fetch())
+/// that are safe to put into log lines, headers and IPC JSON.
+pub fn is_valid_trace_id(id: &str) -> bool {
+    (1..=64).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+pub fn current_trace_id() -> Option<String> {
+    CURRENT_TRACE.with(|current| current.borrow().clone())
+}
+
+/// Restores the previous trace id of the thread on drop.
+pub struct TraceGuard {
+    previous: Option<String>,
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_TRACE.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Tags log lines and IPC commands on this thread with `id` until the
+/// guard is dropped.
+#[must_use]
+pub fn enter_trace(id: &str) -> TraceGuard {
+    let previous = CURRENT_TRACE.with(|current| current.replace(Some(id.to_string())));
+    TraceGuard { previous }
+}
+
+#[doc(hidden)]
+pub fn log_at(level: Level, target: &str, args: fmt::Arguments) {
+    let message = args.to_string();
+    let message = if redacting() {
+        redact(&message)
+    } else {
+        message
+    };
+    let message = match current_trace_id() {
+        Some(id) => format!("[trace={}] {}", id, message),
+        None => message,
+    };
+    let tag = format!("MOTIS-{}", target.to_ascii_uppercase());
+    eprintln!("[{}] {}", tag, message);
+
+    if let Ok(mut guard) = LOG_FILE_HANDLE.lock() {
+        if let Some(file) = guard.as_mut() {
+            let line = format!(
+                "{} {:<5} [{}] {}\n",
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                level.label(),
+                tag,
+                message
+            );
+            file.write_line(&line);
+        }
+    }
+}
+
+macro_rules! log {
+    ($level:expr, $target:expr, $($arg:tt)+) => {
+        if $crate::logging::enabled($level, $target) {
+            $crate::logging::log_at($level, $target, format_args!($($arg)+));
+        }
+    };
+}
+
+macro_rules! error {
+    ($target:expr, $($arg:tt)+) => { $crate::logging::log!($crate::logging::Level::Error, $target, $($arg)+) };
+}
+
+macro_rules! warning {
+    ($target:expr, $($arg:tt)+) => { $crate::logging::log!($crate::logging::Level::Warn, $target, $($arg)+) };
+}
+
+macro_rules! info {
+    ($target:expr, $($arg:tt)+) => { $crate::logging::log!($crate::logging::Level::Info, $target, $($arg)+) };
+}
+
+macro_rules! debug {
+    ($target:expr, $($arg:tt)+) => { $crate::logging::log!($crate::logging::Level::Debug, $target, $($arg)+) };
+}
+
+macro_rules! trace {
+    ($target:expr, $($arg:tt)+) => { $crate::logging::log!($crate::logging::Level::Trace, $target, $($arg)+) };
+}
+
+// `warn` would clash with the built-in lint attribute.
+pub(crate) use {debug, error, info, log, trace, warning};
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        current_trace_id, enter_trace, is_valid_trace_id, new_trace_id, redact, Filter, Level,
+        LogFile,
+    };
+
+    #[test]
+    fn filter_applies_per_target_levels() {
+        let filter = Filter::parse(
+            "warn, protocol=trace ,ipc=debug,bogus=loud,nonsense",
+            Level::Info,
+        );
+        assert_eq!(filter.level_for("protocol"), Level::Trace);
+        assert_eq!(filter.level_for("ipc"), Level::Debug);
+        assert_eq!(filter.level_for("kiosk"), Level::Warn);
+        assert_eq!(filter.level_for("bogus"), Level::Warn);
+        assert_eq!(
+            Filter::parse("", Level::Debug).level_for("gui"),
+            Level::Debug
+        );
+    }
+
+    #[test]
+    fn redact_rounds_coordinates_and_hides_queries() {
+        assert_eq!(
+            redact("plan_route() called: (59.334591, 18.063240) to (-33.8688, 151.2093)"),
+            "plan_route() called: (~59.33, ~18.06) to (~-33.86, ~151.20)"
+        );
+        assert_eq!(
+            redact("Request: /api/v1/geocode?text=Storgatan%2012&language=sv"),
+            "Request: /api/v1/geocode?text=<redacted>&language=sv"
+        );
+        assert_eq!(
+            redact("Request: /api/v1/geocode?language=sv&text=Storgatan"),
+            "Request: /api/v1/geocode?language=sv&text=<redacted>"
+        );
+        assert_eq!(
+            redact(r#"Sending command: {"cmd":"geocode","query":"Drottning \"gatan\"","x":1}"#),
+            r#"Sending command: {"cmd":"geocode","query":"<redacted>","x":1}"#
+        );
+        assert_eq!(
+            redact(r#"Params: {"text": "Storgatan"}"#),
+            r#"Params: {"text":"<redacted>"}"#
+        );
+        // URL-encoded separators (`,` and `;`) do not protect the numbers
+        // after them.
+        assert_eq!(
+            redact(
+                "Request: /api/v5/plan?fromPlace=59.334591%2C18.063240&toPlace=59.319%2c18.0721%3B0"
+            ),
+            "Request: /api/v5/plan?fromPlace=~59.33%2C~18.06&toPlace=~59.31%2c~18.07%3B0"
+        );
+        assert_eq!(
+            redact(
+                "Request: /api/v1/one-to-many?one=59.3345%3B18.0632\
+                 &many=59.3201%2C-18.0735%3B59.3455%2C18.0512"
+            ),
+            "Request: /api/v1/one-to-many?one=~59.33%3B~18.06\
+             &many=~59.32%2C~-18.07%3B~59.34%2C~18.05"
+        );
+        // Durations, versions and tile numbers stay readable.
+        assert_eq!(
+            redact("took 1.25 s, v1.2.3, tile 12/2214/1185"),
+            "took 1.25 s, v1.2.3, tile 12/2214/1185"
+        );
+    }
+
+    #[test]
+    fn trace_scopes_nest_and_restore() {
+        let first = new_trace_id();
+        let second = new_trace_id();
+        assert_ne!(first, second);
+        assert_eq!(first.len(), 16);
+        assert!(is_valid_trace_id(&first));
+        assert!(!is_valid_trace_id(""));
+        assert!(!is_valid_trace_id("a b"));
+        assert!(!is_valid_trace_id(&"x".repeat(65)));
+
+        assert_eq!(current_trace_id(), None);
+        {
+            let _outer = enter_trace(&first);
+            {
+                let _inner = enter_trace(&second);
+                assert_eq!(current_trace_id(), Some(second.clone()));
+            }
+            assert_eq!(current_trace_id(), Some(first.clone()));
+            // Other threads (e.g. batch workers) start untraced.
+            std::thread::spawn(|| assert_eq!(current_trace_id(), None))
+                .join()
+                .unwrap();
+        }
+        assert_eq!(current_trace_id(), None);
+    }
+
+    #[test]
+    fn log_file_rotates_and_keeps_three() {
+        let dir = std::env::temp_dir().join(format!("motis-log-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("motis-gui.log");
+        let max_bytes = 4 * 1024;
+        let mut file = LogFile::open(path.clone(), max_bytes).expect("open");
+        let line = "x".repeat(1024) + "\n";
+        let lines = (max_bytes as usize / line.len() + 1) * 5;
+        for _ in 0..lines {
+            file.write_line(&line);
+        }
+
+        assert!(path.exists());
+        assert!(LogFile::rotated(&path, 3).exists());
+        assert!(!LogFile::rotated(&path, 4).exists());
+        assert!(std::fs::metadata(&path).unwrap().len() <= max_bytes);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
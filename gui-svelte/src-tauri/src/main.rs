@@ -1,7 +1,27 @@
+pub mod batch;
+pub mod export;
+pub mod isochrone;
+pub mod kiosk;
+pub mod logging;
+pub mod matrix;
+pub mod metrics;
 pub mod native;
 pub mod protocol;
+pub mod store;
 
-use native::{Match as Location, RouteResult as Route};
+use batch::{BatchOptions, BatchRow, BatchSummary};
+use export::{ExportFormat, IcsOptions};
+use isochrone::{IsochroneFormat, IsochroneMetadata, IsochroneOptions};
+use kiosk::KioskConfig;
+use logging::GUI;
+use matrix::{MatrixFormat, MatrixRequest, MatrixSummary};
+use metrics::MetricsSnapshot;
+use native::{
+    ConfigDocument, ConfigFeature, ConfigIssue, DatasetInfo, GeocodeOptions, LatLon,
+    ManifestReport, ManifestVerifyMode, Match as Location, MotisConfig, RouteResult as Route,
+};
+use once_cell::sync::OnceCell;
+use store::{FavoritePlace, SavedItinerary, UserStore};
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -10,6 +30,9 @@ use tauri::Manager;
 // Global debug flag
 static DEBUG_MODE: AtomicBool = AtomicBool::new(false);
 
+// Set when started with --kiosk-stop; replaces the main window with the board.
+static KIOSK_CONFIG: OnceCell<KioskConfig> = OnceCell::new();
+
 struct IpcShutdownGuard;
 
 impl Drop for IpcShutdownGuard {
@@ -74,6 +97,74 @@ fn install_linux_zoom_lock<R: tauri::Runtime>(_window: &tauri::WebviewWindow<R>)
     Ok(())
 }
 
+/// The UI shows its debug panel (transfers, elevators, street routes, data
+/// QA layers) only with `?debug` in the page URL.
+fn open_debug_panel<R: tauri::Runtime>(window: &tauri::WebviewWindow<R>) -> Result<(), String> {
+    let mut url = window
+        .url()
+        .map_err(|e| format!("Could not read the main window URL: {}", e))?;
+    url.set_query(Some("debug"));
+    window
+        .navigate(url)
+        .map_err(|e| format!("Could not open the debug panel: {}", e))
+}
+
+/// Asks the session not to blank the screen or suspend while the board is up.
+#[cfg(target_os = "linux")]
+fn inhibit_linux_idle<R: tauri::Runtime>(window: &tauri::WebviewWindow<R>) -> Result<(), String> {
+    use gtk::prelude::{GtkApplicationExt, GtkWindowExt};
+
+    let gtk_window = window
+        .gtk_window()
+        .map_err(|e| format!("Failed to access kiosk window: {}", e))?;
+    let Some(application) = gtk_window.application() else {
+        return Err("Kiosk window has no GTK application; screen may blank".to_string());
+    };
+    let cookie = application.inhibit(
+        Some(&gtk_window),
+        gtk::ApplicationInhibitFlags::IDLE | gtk::ApplicationInhibitFlags::SUSPEND,
+        Some("Departure board"),
+    );
+    if cookie == 0 {
+        return Err(
+            "Session refused idle inhibit. Next action: disable screen blanking (e.g. `xset s off -dpms`)."
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn inhibit_linux_idle<R: tauri::Runtime>(_window: &tauri::WebviewWindow<R>) -> Result<(), String> {
+    Ok(())
+}
+
+fn open_kiosk_window<R: tauri::Runtime>(app: &tauri::App<R>) -> Result<(), String> {
+    #[cfg(windows)]
+    let url = "http://motis.localhost/kiosk/";
+    #[cfg(not(windows))]
+    let url = "motis://localhost/kiosk/";
+
+    let url = url
+        .parse()
+        .map_err(|e| format!("Invalid kiosk URL {}: {}", url, e))?;
+    let window = tauri::WebviewWindowBuilder::new(app, "kiosk", tauri::WebviewUrl::CustomProtocol(url))
+        .title("Departures")
+        .fullscreen(true)
+        .decorations(false)
+        .build()
+        .map_err(|e| format!("Failed to open kiosk window: {}", e))?;
+
+    if let Err(err) = inhibit_linux_idle(&window) {
+        logging::warning!(GUI, "{}", err);
+    }
+    // No search UI in kiosk mode.
+    if let Some(main_window) = app.get_webview_window("main") {
+        let _ = main_window.destroy();
+    }
+    Ok(())
+}
+
 fn get_exe_dir() -> Result<PathBuf, String> {
     std::env::current_exe()
         .map_err(|e| format!("Failed to get executable path: {}", e))?
@@ -123,14 +214,18 @@ async fn init_backend(
     let data = data_path.as_deref()
         .or(default_data.as_deref());
 
-    native::auto_init(exe, data).await
-        .map_err(|e| e.to_string())
+    let message = native::auto_init(exe, data).await
+        .map_err(|e| e.to_string())?;
+    std::thread::spawn(native::warn_if_outside_service_range);
+    Ok(message)
 }
 
 #[tauri::command]
 async fn get_backend_mode() -> Result<String, String> {
     match native::get_mode() {
         native::BackendMode::Ipc => Ok("ipc".to_string()),
+        native::BackendMode::Embedded => Ok("embedded".to_string()),
+        native::BackendMode::Replay => Ok("replay".to_string()),
     }
 }
 
@@ -147,9 +242,47 @@ async fn plan_route_cmd(
 }
 
 #[tauri::command]
-async fn geocode_cmd(query: String) -> Result<Vec<Location>, String> {
-    native::geocode(&query).await
-        .map_err(|e| e.to_string())
+async fn geocode_cmd(
+    query: String,
+    options: Option<GeocodeOptions>,
+) -> Result<Vec<Location>, String> {
+    let options = options.unwrap_or_default();
+    let results = native::geocode(&query, &options).await
+        .map_err(|e| e.to_string())?;
+    // Saved places keep their MOTIS type, so a type filter applies to them too.
+    let saved = store::saved_suggestions(&query, options.type_.as_deref());
+    let mut blended = store::blend_matches(saved, results);
+    if let Some(limit) = options.limit {
+        blended.truncate(limit);
+    }
+    Ok(blended)
+}
+
+/// Geocodes many queries at once (address lists), best match first.
+#[tauri::command]
+async fn geocode_batch_cmd(
+    queries: Vec<String>,
+    options: Option<BatchOptions>,
+) -> Result<Vec<BatchRow>, String> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || batch::geocode_batch(&queries, &options))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Geocodes every row of a CSV file and writes it back with match columns.
+#[tauri::command]
+async fn geocode_batch_csv_cmd(
+    input_path: String,
+    output_path: String,
+    options: Option<BatchOptions>,
+) -> Result<BatchSummary, String> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        batch::geocode_batch_csv(Path::new(&input_path), Path::new(&output_path), &options)
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
@@ -158,11 +291,260 @@ async fn reverse_geocode_cmd(lat: f64, lon: f64) -> Result<Option<Location>, Str
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn dataset_info_cmd() -> Result<DatasetInfo, String> {
+    native::dataset_info().await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn verify_data_bundle_cmd(data_path: String, full: bool) -> Result<ManifestReport, String> {
+    let mode = if full {
+        ManifestVerifyMode::Full
+    } else {
+        ManifestVerifyMode::Quick
+    };
+    tauri::async_runtime::spawn_blocking(move || native::verify_data_manifest(&data_path, mode))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Walking, cycling or driving directions between two points without
+/// transit, as GeoJSON line segments for the map.
+#[tauri::command]
+async fn street_route_cmd(
+    from: LatLon,
+    to: LatLon,
+    profile: Option<String>,
+    max_duration_seconds: Option<u32>,
+) -> Result<serde_json::Value, String> {
+    let profile = profile.unwrap_or_else(|| "foot".to_string());
+    tauri::async_runtime::spawn_blocking(move || {
+        native::street_route_sync(&from, &to, &profile, max_duration_seconds)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Loads elevator status from a local FASTA JSON export (the format of the
+/// `elevators.init` config file). Returns the number of elevators.
+#[tauri::command]
+async fn load_elevator_status_cmd(path: String) -> Result<usize, String> {
+    if !Path::new(&path).is_file() {
+        return Err(format!(
+            "Elevator status file not found: {}. Next action: choose a FASTA JSON export.",
+            path
+        ));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        native::load_elevators_sync(&path).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Marks one elevator as working or out of service (optionally with ISO 8601
+/// maintenance windows) so accessible routing avoids it.
+#[tauri::command]
+async fn set_elevator_status_cmd(
+    id: i64,
+    active: bool,
+    out_of_service: Option<Vec<(String, String)>>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        native::set_elevator_status_sync(id, active, &out_of_service.unwrap_or_default())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Writes a planned itinerary (`/api/v5/plan` itinerary or `RouteResult`) to
+/// the path the user picked. Returns the format that was written.
+#[tauri::command]
+async fn export_itinerary_cmd(
+    itinerary: serde_json::Value,
+    format: Option<ExportFormat>,
+    path: String,
+) -> Result<ExportFormat, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        export::export_itinerary(&itinerary, format, Path::new(&path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Builds dissolved isochrone polygons per time band from a one-to-all
+/// response, as a GeoJSON FeatureCollection.
+#[tauri::command]
+async fn isochrones_cmd(
+    reachable: serde_json::Value,
+    options: IsochroneOptions,
+) -> Result<serde_json::Value, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        isochrone::isochrone_polygons(&reachable, &options)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Saves isochrone polygons with their search parameters as GeoJSON or a
+/// zipped Shapefile.
+#[tauri::command]
+async fn export_isochrones_cmd(
+    reachable: serde_json::Value,
+    options: IsochroneOptions,
+    metadata: Option<IsochroneMetadata>,
+    format: Option<IsochroneFormat>,
+    path: String,
+) -> Result<IsochroneFormat, String> {
+    let metadata = metadata.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        isochrone::export_isochrones(&reachable, &options, &metadata, format, Path::new(&path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Computes a many-to-many travel time matrix and writes it as CSV or
+/// columnar JSON.
+#[tauri::command]
+async fn export_travel_time_matrix_cmd(
+    request: MatrixRequest,
+    path: String,
+    format: Option<MatrixFormat>,
+) -> Result<MatrixSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        matrix::export_travel_time_matrix(&request, format, Path::new(&path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Writes an itinerary as an `.ics` file for calendar apps.
+#[tauri::command]
+async fn export_itinerary_ics(
+    itinerary: serde_json::Value,
+    path: String,
+    options: Option<IcsOptions>,
+) -> Result<(), String> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        export::export_itinerary_ics(&itinerary, &options, Path::new(&path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn get_user_store_cmd() -> Result<UserStore, String> {
+    store::read_store()
+}
+
+#[tauri::command]
+async fn get_user_store_path_cmd() -> Result<String, String> {
+    store::store_path().map(|path| path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+async fn add_favorite_cmd(place: Location, label: Option<String>) -> Result<FavoritePlace, String> {
+    store::update_store(|s| s.add_favorite(place, label))
+}
+
+#[tauri::command]
+async fn set_favorite_label_cmd(id: String, label: Option<String>) -> Result<bool, String> {
+    store::update_store(|s| s.set_favorite_label(&id, label))
+}
+
+#[tauri::command]
+async fn remove_favorite_cmd(id: String) -> Result<bool, String> {
+    store::update_store(|s| s.remove_favorite(&id))
+}
+
+#[tauri::command]
+async fn record_search_cmd(query: String, selected: Option<Location>) -> Result<(), String> {
+    store::update_store(|s| s.record_search(&query, selected))
+}
+
+#[tauri::command]
+async fn clear_search_history_cmd() -> Result<(), String> {
+    store::update_store(|s| s.recent_searches.clear())
+}
+
+#[tauri::command]
+async fn save_itinerary_cmd(
+    name: String,
+    itinerary: serde_json::Value,
+) -> Result<SavedItinerary, String> {
+    store::update_store(|s| s.save_itinerary(name, itinerary))
+}
+
+#[tauri::command]
+async fn remove_saved_itinerary_cmd(id: String) -> Result<bool, String> {
+    store::update_store(|s| s.remove_itinerary(&id))
+}
+
+#[tauri::command]
+async fn pin_stop_cmd(stop_id: String, name: String) -> Result<(), String> {
+    store::update_store(|s| s.pin_stop(stop_id, name))
+}
+
+#[tauri::command]
+async fn unpin_stop_cmd(stop_id: String) -> Result<bool, String> {
+    store::update_store(|s| s.unpin_stop(&stop_id))
+}
+
+#[tauri::command]
+async fn export_user_store_cmd(path: String) -> Result<(), String> {
+    store::export_store(Path::new(&path))
+}
+
+#[tauri::command]
+async fn import_user_store_cmd(path: String, replace: bool) -> Result<UserStore, String> {
+    store::import_store(Path::new(&path), replace)
+}
+
+#[tauri::command]
+async fn read_config_cmd(data_path: String) -> Result<ConfigDocument, String> {
+    native::read_config(&data_path)
+}
+
+#[tauri::command]
+async fn update_config_cmd(data_path: String, config: MotisConfig) -> Result<Vec<ConfigIssue>, String> {
+    native::write_config(&data_path, &config)
+}
+
+#[tauri::command]
+async fn set_config_feature_cmd(
+    data_path: String,
+    feature: ConfigFeature,
+    enabled: bool,
+) -> Result<ConfigDocument, String> {
+    native::set_config_feature(&data_path, feature, enabled)
+}
+
+#[tauri::command]
+async fn add_config_dataset_cmd(
+    data_path: String,
+    id: String,
+    path: String,
+    script: Option<String>,
+) -> Result<ConfigDocument, String> {
+    native::add_config_dataset(&data_path, &id, &path, script)
+}
+
 #[tauri::command]
 async fn destroy_backend() {
     native::destroy();
 }
 
+/// Request counts, error counts and latency histograms since startup.
+#[tauri::command]
+async fn get_metrics() -> MetricsSnapshot {
+    metrics::snapshot()
+}
+
 #[tauri::command]
 async fn is_debug_mode() -> bool {
     DEBUG_MODE.load(Ordering::Relaxed)
@@ -173,9 +555,18 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
+            if KIOSK_CONFIG.get().is_some() {
+                open_kiosk_window(app)?;
+                return Ok(());
+            }
             if let Some(main_webview) = app.get_webview_window("main") {
                 if let Err(err) = install_linux_zoom_lock(&main_webview) {
-                    eprintln!("[MOTIS-GUI] {}", err);
+                    logging::warning!(GUI, "{}", err);
+                }
+                if DEBUG_MODE.load(Ordering::Relaxed) {
+                    if let Err(err) = open_debug_panel(&main_webview) {
+                        logging::warning!(GUI, "{}", err);
+                    }
                 }
             }
             Ok(())
@@ -187,9 +578,39 @@ pub fn run() {
             check_data_path_exists,
             get_backend_mode,
             plan_route_cmd,
+            street_route_cmd,
             geocode_cmd,
+            geocode_batch_cmd,
+            geocode_batch_csv_cmd,
             reverse_geocode_cmd,
+            dataset_info_cmd,
+            verify_data_bundle_cmd,
+            load_elevator_status_cmd,
+            set_elevator_status_cmd,
+            export_itinerary_cmd,
+            export_itinerary_ics,
+            export_travel_time_matrix_cmd,
+            isochrones_cmd,
+            export_isochrones_cmd,
+            get_user_store_cmd,
+            get_user_store_path_cmd,
+            add_favorite_cmd,
+            set_favorite_label_cmd,
+            remove_favorite_cmd,
+            record_search_cmd,
+            clear_search_history_cmd,
+            save_itinerary_cmd,
+            remove_saved_itinerary_cmd,
+            pin_stop_cmd,
+            unpin_stop_cmd,
+            export_user_store_cmd,
+            import_user_store_cmd,
+            read_config_cmd,
+            update_config_cmd,
+            set_config_feature_cmd,
+            add_config_dataset_cmd,
             destroy_backend,
+            get_metrics,
             is_debug_mode,
         ])
         .register_uri_scheme_protocol("motis", |_app, request| {
@@ -202,18 +623,31 @@ pub fn run() {
 fn main() {
     let _ipc_shutdown_guard = IpcShutdownGuard;
     let args: Vec<String> = std::env::args().collect();
+
+    // Used by motis-import.sh before `motis import`; no window is opened.
+    if let Some(pos) = args.iter().position(|a| a == "--check-import-config") {
+        let dir = args.get(pos + 1).map(String::as_str).unwrap_or(".");
+        if let Err(e) = native::check_import_config(dir) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
     let debug_mode = args.contains(&"--debug".to_string());
     DEBUG_MODE.store(debug_mode, Ordering::Relaxed);
+    logging::init(debug_mode);
+    protocol::set_data_qa_enabled(debug_mode);
     
     if debug_mode {
-        eprintln!("[MOTIS-GUI] Debug mode enabled");
+        logging::info!(GUI, "Debug mode enabled");
     }
     
     // Auto-initialize IPC if data path provided via CLI
     let data_path_flag = args.iter().position(|a| a == "--data-path");
     if let Some(pos) = data_path_flag {
         if let Some(path) = args.get(pos + 1) {
-            eprintln!("[MOTIS-GUI] Auto-initializing with data path: {}", path);
+            logging::info!(GUI, "Auto-initializing with data path: {}", path);
             
             // Try multiple locations for motis-ipc
             // First check environment variable (set by RUN.sh for USB/FAT32)
@@ -230,12 +664,13 @@ fn main() {
             let mut initialized = false;
             for exe_path in possible_exes.into_iter().flatten() {
                 if exe_path.exists() {
-                    eprintln!("[MOTIS-GUI] Trying motis-ipc at: {:?}", exe_path);
+                    logging::debug!(GUI, "Trying motis-ipc at: {:?}", exe_path);
                     if let Some(exe_str) = exe_path.to_str() {
                         if let Err(e) = native::init_ipc(exe_str, path) {
-                            eprintln!("[MOTIS-GUI] Auto-init failed: {}", e);
+                            logging::warning!(GUI, "Auto-init failed: {}", e);
                         } else {
-                            eprintln!("[MOTIS-GUI] IPC auto-initialized successfully");
+                            logging::info!(GUI, "IPC auto-initialized successfully");
+                            std::thread::spawn(native::warn_if_outside_service_range);
                             initialized = true;
                             break;
                         }
@@ -244,10 +679,15 @@ fn main() {
             }
             
             if !initialized {
-                eprintln!("[MOTIS-GUI] Could not find or initialize motis-ipc");
+                logging::error!(GUI, "Could not find or initialize motis-ipc");
             }
         }
     }
+
+    if let Some(config) = KioskConfig::from_args(&args) {
+        kiosk::start(config.clone());
+        let _ = KIOSK_CONFIG.set(config);
+    }
     
     run();
 }
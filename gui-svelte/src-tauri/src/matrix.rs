@@ -0,0 +1,493 @@
+mod columnar;
+
+use crate::export::write_export;
+use crate::logging::{self, MATRIX};
+use crate::native;
+use chrono::{DateTime, Duration, FixedOffset, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// `/api/v5/plan` calls allowed for one transit matrix (origins x
+/// destinations x departures). The export has no progress or cancel, and a
+/// search takes up to a few hundred ms, so this keeps a run within minutes.
+const MAX_TRANSIT_QUERIES: usize = 1_000;
+const MAX_DEPARTURES: usize = 96;
+const DEFAULT_MAX_TRAVEL_SECONDS: u32 = 3600;
+const DEFAULT_MAX_MATCHING_DISTANCE: f64 = 250.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatrixFormat {
+    Csv,
+    /// Column arrays in one JSON document (`.json`, gzipped as `.json.gz`),
+    /// loadable as a data frame without row parsing. Plain JSON, not Parquet.
+    ColumnarJson,
+}
+
+impl MatrixFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".csv") {
+            Some(MatrixFormat::Csv)
+        } else if name.ends_with(".json") || name.ends_with(".json.gz") {
+            Some(MatrixFormat::ColumnarJson)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixPoint {
+    pub id: String,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+/// Departures every `every_minutes` from `from` up to and including `to`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimeSampling {
+    pub from: String,
+    pub to: String,
+    pub every_minutes: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MatrixRequest {
+    pub origins: Vec<MatrixPoint>,
+    pub destinations: Vec<MatrixPoint>,
+    /// `WALK`, `BIKE` and `CAR` use street routing (`/api/v1/one-to-many`);
+    /// `TRANSIT` plans every pair with `/api/v5/plan`.
+    pub mode: String,
+    #[serde(default)]
+    pub departures: Vec<String>,
+    #[serde(default)]
+    pub sampling: Option<TimeSampling>,
+    #[serde(default)]
+    pub max_travel_seconds: Option<u32>,
+    #[serde(default)]
+    pub max_matching_distance: Option<f64>,
+}
+
+/// Travel times in `[departure][origin][destination]` order; `None` means
+/// unreachable within the limit. Street matrices have a single departure.
+#[derive(Debug, Clone)]
+pub struct TravelTimeMatrix {
+    pub origins: Vec<MatrixPoint>,
+    pub destinations: Vec<MatrixPoint>,
+    pub departures: Vec<Option<String>>,
+    pub seconds: Vec<Option<u32>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatrixSummary {
+    pub format: MatrixFormat,
+    pub cells: usize,
+    pub reachable: usize,
+    pub queries: usize,
+}
+
+fn is_street_mode(mode: &str) -> bool {
+    matches!(mode, "WALK" | "BIKE" | "CAR")
+}
+
+fn parse_time(value: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map_err(|e| format!("Invalid departure time '{}': {}", value, e))
+}
+
+fn departure_samples(request: &MatrixRequest) -> Result<Vec<String>, String> {
+    let mut departures = request.departures.clone();
+    if let Some(sampling) = &request.sampling {
+        if sampling.every_minutes == 0 {
+            return Err("Invalid sampling interval 0 minutes".to_string());
+        }
+        let to = parse_time(&sampling.to)?;
+        let mut time = parse_time(&sampling.from)?;
+        while time <= to && departures.len() <= MAX_DEPARTURES {
+            departures.push(time.to_rfc3339_opts(SecondsFormat::Secs, true));
+            time += Duration::minutes(i64::from(sampling.every_minutes));
+        }
+    }
+    if departures.len() > MAX_DEPARTURES {
+        return Err(format!(
+            "Too many departure times ({} max). Next action: widen the sampling interval.",
+            MAX_DEPARTURES
+        ));
+    }
+    for departure in &departures {
+        parse_time(departure)?;
+    }
+    Ok(departures)
+}
+
+fn validate(request: &MatrixRequest) -> Result<(), String> {
+    if request.origins.is_empty() || request.destinations.is_empty() {
+        return Err("Matrix needs at least one origin and one destination.".to_string());
+    }
+    for point in request.origins.iter().chain(&request.destinations) {
+        if !(-90.0..=90.0).contains(&point.lat) || !(-180.0..=180.0).contains(&point.lon) {
+            return Err(format!(
+                "Invalid coordinate {},{} for '{}'",
+                point.lat, point.lon, point.id
+            ));
+        }
+    }
+    if !is_street_mode(&request.mode) && request.mode != "TRANSIT" {
+        return Err(format!(
+            "Unsupported matrix mode '{}'. Next action: use WALK, BIKE, CAR or TRANSIT.",
+            request.mode
+        ));
+    }
+    Ok(())
+}
+
+fn one_to_many_path(
+    origin: &MatrixPoint,
+    destinations: &[MatrixPoint],
+    mode: &str,
+    max_seconds: u32,
+    max_matching_distance: f64,
+) -> String {
+    let many: Vec<String> = destinations
+        .iter()
+        .map(|d| format!("{};{}", d.lat, d.lon))
+        .collect();
+    format!(
+        "/api/v1/one-to-many?one={}&many={}&mode={}&max={}&maxMatchingDistance={}&arriveBy=false",
+        urlencoding::encode(&format!("{};{}", origin.lat, origin.lon)),
+        urlencoding::encode(&many.join(",")),
+        mode,
+        max_seconds,
+        max_matching_distance
+    )
+}
+
+fn plan_path(
+    origin: &MatrixPoint,
+    destination: &MatrixPoint,
+    departure: &str,
+    max_seconds: u32,
+    max_matching_distance: f64,
+) -> String {
+    format!(
+        "/api/v5/plan?fromPlace={},{}&toPlace={},{}&time={}&maxTravelTime={}&maxMatchingDistance={}&numItineraries=1",
+        origin.lat,
+        origin.lon,
+        destination.lat,
+        destination.lon,
+        urlencoding::encode(departure),
+        max_seconds.div_ceil(60),
+        max_matching_distance
+    )
+}
+
+/// Travel time from the sampled departure to the earliest arrival, so waiting
+/// at the first stop counts as it does in accessibility studies.
+fn earliest_arrival_seconds(response: &Value, departure: &str, max_seconds: u32) -> Option<u32> {
+    let departure = parse_time(departure).ok()?;
+    ["itineraries", "direct"]
+        .iter()
+        .filter_map(|key| response[*key].as_array())
+        .flatten()
+        .filter_map(|itinerary| itinerary["endTime"].as_str())
+        .filter_map(|end| DateTime::parse_from_rfc3339(end).ok())
+        .map(|end| (end - departure).num_seconds())
+        .filter(|&seconds| seconds >= 0 && seconds <= i64::from(max_seconds))
+        .min()
+        .map(|seconds| seconds as u32)
+}
+
+/// Computes the matrix through `api_get` (the IPC `api_get` command outside
+/// tests). Returns the matrix and the number of endpoint calls made.
+pub(crate) fn compute_matrix<F>(
+    request: &MatrixRequest,
+    mut api_get: F,
+) -> Result<(TravelTimeMatrix, usize), String>
+where
+    F: FnMut(&str) -> Result<Value, String>,
+{
+    validate(request)?;
+    let max_seconds = request
+        .max_travel_seconds
+        .unwrap_or(DEFAULT_MAX_TRAVEL_SECONDS);
+    let max_matching_distance = request
+        .max_matching_distance
+        .unwrap_or(DEFAULT_MAX_MATCHING_DISTANCE);
+    let samples = departure_samples(request)?;
+    let street = is_street_mode(&request.mode);
+
+    // Street routing does not depend on the time of day.
+    let departures: Vec<Option<String>> = if street {
+        vec![None]
+    } else if samples.is_empty() {
+        return Err(
+            "Transit matrix needs departure times. Next action: set departures or sampling."
+                .to_string(),
+        );
+    } else {
+        samples.into_iter().map(Some).collect()
+    };
+
+    let cells = departures.len() * request.origins.len() * request.destinations.len();
+    if !street && cells > MAX_TRANSIT_QUERIES {
+        return Err(format!(
+            "Transit matrix needs {} route searches ({} max). Next action: use fewer points or departure times.",
+            cells, MAX_TRANSIT_QUERIES
+        ));
+    }
+
+    let mut seconds = Vec::with_capacity(cells);
+    let mut queries = 0;
+    for departure in &departures {
+        for (index, origin) in request.origins.iter().enumerate() {
+            match departure {
+                None => {
+                    let path = one_to_many_path(
+                        origin,
+                        &request.destinations,
+                        &request.mode,
+                        max_seconds,
+                        max_matching_distance,
+                    );
+                    // Like a failed plan, a failed origin only empties its row.
+                    let response = api_get(&path).and_then(|response| match response {
+                        Value::Array(durations) => Ok(durations),
+                        _ => Err("unexpected response, expected an array".to_string()),
+                    });
+                    queries += 1;
+                    let durations = response.unwrap_or_else(|e| {
+                        logging::warning!(
+                            MATRIX,
+                            "matrix: one-to-many from {} failed: {}",
+                            origin.id,
+                            e
+                        );
+                        Vec::new()
+                    });
+                    for destination in 0..request.destinations.len() {
+                        seconds.push(
+                            durations
+                                .get(destination)
+                                .and_then(|d| d["duration"].as_f64())
+                                .map(|d| d.round() as u32),
+                        );
+                    }
+                }
+                Some(departure) => {
+                    for destination in &request.destinations {
+                        let path = plan_path(
+                            origin,
+                            destination,
+                            departure,
+                            max_seconds,
+                            max_matching_distance,
+                        );
+                        // One unroutable pair must not abort the whole run.
+                        let value = match api_get(&path) {
+                            Ok(response) => {
+                                earliest_arrival_seconds(&response, departure, max_seconds)
+                            }
+                            Err(e) => {
+                                logging::warning!(
+                                    MATRIX,
+                                    "matrix: plan {} -> {} failed: {}",
+                                    origin.id, destination.id, e
+                                );
+                                None
+                            }
+                        };
+                        queries += 1;
+                        seconds.push(value);
+                    }
+                }
+            }
+            logging::debug!(
+                MATRIX,
+                "matrix: origin {}/{} done{}",
+                index + 1,
+                request.origins.len(),
+                departure
+                    .as_ref()
+                    .map(|d| format!(" for {}", d))
+                    .unwrap_or_default()
+            );
+        }
+    }
+
+    Ok((
+        TravelTimeMatrix {
+            origins: request.origins.clone(),
+            destinations: request.destinations.clone(),
+            departures,
+            seconds,
+        },
+        queries,
+    ))
+}
+
+impl TravelTimeMatrix {
+    /// Long-format rows: origin, destination, departure, travel seconds.
+    pub(crate) fn rows(&self) -> impl Iterator<Item = (&str, &str, &str, Option<u32>)> + '_ {
+        let per_departure = self.origins.len() * self.destinations.len();
+        self.seconds.iter().enumerate().map(move |(i, seconds)| {
+            let departure = self.departures[i / per_departure].as_deref().unwrap_or("");
+            let origin = &self.origins[(i % per_departure) / self.destinations.len()];
+            let destination = &self.destinations[i % self.destinations.len()];
+            (
+                origin.id.as_str(),
+                destination.id.as_str(),
+                departure,
+                *seconds,
+            )
+        })
+    }
+
+    fn to_csv(&self) -> String {
+        let quote = |field: &str| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        };
+        let mut out = String::from("origin_id,destination_id,departure,travel_seconds\r\n");
+        for (origin, destination, departure, seconds) in self.rows() {
+            out.push_str(&format!(
+                "{},{},{},{}\r\n",
+                quote(origin),
+                quote(destination),
+                departure,
+                seconds.map(|s| s.to_string()).unwrap_or_default()
+            ));
+        }
+        out
+    }
+}
+
+/// Computes a travel time matrix and writes it to `path`. Without an explicit
+/// format the file extension decides (`.csv`, `.json`, `.json.gz`).
+pub fn export_travel_time_matrix(
+    request: &MatrixRequest,
+    format: Option<MatrixFormat>,
+    path: &Path,
+) -> Result<MatrixSummary, String> {
+    let format = format
+        .or_else(|| MatrixFormat::from_path(path))
+        .ok_or_else(|| {
+            format!(
+                "Cannot tell matrix format from '{}'. Next action: use a .csv, .json or .json.gz file name.",
+                path.display()
+            )
+        })?;
+    let (matrix, queries) = compute_matrix(request, |path| {
+        native::api_get_sync(path).map_err(|e| e.to_string())
+    })?;
+
+    match format {
+        MatrixFormat::Csv => write_export(path, &matrix.to_csv())?,
+        MatrixFormat::ColumnarJson => columnar::write(&matrix, &request.mode, path)?,
+    }
+    Ok(MatrixSummary {
+        format,
+        cells: matrix.seconds.len(),
+        reachable: matrix.seconds.iter().filter(|s| s.is_some()).count(),
+        queries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{columnar, compute_matrix, MatrixPoint, MatrixRequest, TimeSampling};
+    use serde_json::json;
+
+    fn point(id: &str, lat: f64) -> MatrixPoint {
+        MatrixPoint {
+            id: id.to_string(),
+            lat,
+            lon: 18.0,
+        }
+    }
+
+    fn request(mode: &str) -> MatrixRequest {
+        MatrixRequest {
+            origins: vec![point("a", 59.0), point("b", 59.1)],
+            destinations: vec![point("x", 59.2), point("y", 59.3), point("z", 59.4)],
+            mode: mode.to_string(),
+            departures: Vec::new(),
+            sampling: None,
+            max_travel_seconds: Some(1800),
+            max_matching_distance: None,
+        }
+    }
+
+    #[test]
+    fn street_matrix_uses_one_request_per_origin() {
+        let mut paths = Vec::new();
+        let mut req = request("BIKE");
+        req.origins.push(point("c", 59.5));
+        let (matrix, queries) = compute_matrix(&req, |path| {
+            paths.push(path.to_string());
+            if path.contains("one=59.5") {
+                return Err("origin not on the street network".to_string());
+            }
+            Ok(json!([{ "duration": 600.0 }, {}, { "duration": 1200.4 }]))
+        })
+        .expect("matrix");
+
+        assert_eq!(queries, 3);
+        assert!(paths[0].starts_with("/api/v1/one-to-many?one=59%3B18&many="));
+        assert!(paths[0].contains("mode=BIKE&max=1800"));
+        assert_eq!(matrix.departures, vec![None]);
+        assert_eq!(
+            matrix.seconds,
+            vec![
+                Some(600),
+                None,
+                Some(1200),
+                Some(600),
+                None,
+                Some(1200),
+                None,
+                None,
+                None
+            ]
+        );
+        let rows: Vec<_> = matrix.rows().collect();
+        assert_eq!(rows[4], ("b", "y", "", None));
+    }
+
+    #[test]
+    fn transit_matrix_samples_departures() {
+        let mut request = request("TRANSIT");
+        request.origins.truncate(1);
+        request.destinations.truncate(1);
+        request.max_travel_seconds = Some(3600);
+        request.sampling = Some(TimeSampling {
+            from: "2026-03-02T07:00:00Z".to_string(),
+            to: "2026-03-02T07:30:00Z".to_string(),
+            every_minutes: 15,
+        });
+
+        let (matrix, queries) = compute_matrix(&request, |path| {
+            if path.contains("07%3A30") {
+                return Err("no route".to_string());
+            }
+            Ok(json!({
+                "itineraries": [{ "endTime": "2026-03-02T07:40:00Z" }],
+                "direct": [{ "endTime": "2026-03-02T07:35:00Z" }],
+            }))
+        })
+        .expect("matrix");
+
+        assert_eq!(queries, 3);
+        assert_eq!(matrix.departures.len(), 3);
+        // 07:00 -> direct 07:35, 07:15 -> 07:35, 07:30 failed.
+        assert_eq!(matrix.seconds, vec![Some(2100), Some(1200), None]);
+
+        let doc = columnar::to_value(&matrix, "TRANSIT");
+        assert_eq!(doc["columns"]["travel_seconds"], json!([2100, 1200, null]));
+        assert_eq!(doc["columns"]["departure"][1], "2026-03-02T07:15:00Z");
+    }
+}
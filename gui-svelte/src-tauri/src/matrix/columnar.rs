@@ -0,0 +1,50 @@
+use super::TravelTimeMatrix;
+use crate::export::{write_export, write_export_bytes};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::Path;
+
+/// One array per column, in the same row order as the CSV output, so pandas
+/// or polars can build a frame directly from `columns`.
+pub(super) fn to_value(matrix: &TravelTimeMatrix, mode: &str) -> Value {
+    let mut origin_id = Vec::with_capacity(matrix.seconds.len());
+    let mut destination_id = Vec::with_capacity(matrix.seconds.len());
+    let mut departure = Vec::with_capacity(matrix.seconds.len());
+    let mut travel_seconds = Vec::with_capacity(matrix.seconds.len());
+    for (origin, destination, time, seconds) in matrix.rows() {
+        origin_id.push(origin);
+        destination_id.push(destination);
+        departure.push(if time.is_empty() { None } else { Some(time) });
+        travel_seconds.push(seconds);
+    }
+    json!({
+        "mode": mode,
+        "rows": matrix.seconds.len(),
+        "columns": {
+            "origin_id": origin_id,
+            "destination_id": destination_id,
+            "departure": departure,
+            "travel_seconds": travel_seconds,
+        },
+    })
+}
+
+pub(super) fn write(matrix: &TravelTimeMatrix, mode: &str, path: &Path) -> Result<(), String> {
+    let document = to_value(matrix, mode).to_string();
+    let gzip = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.to_ascii_lowercase().ends_with(".gz"));
+    if !gzip {
+        return write_export(path, &document);
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let bytes = encoder
+        .write_all(document.as_bytes())
+        .and_then(|_| encoder.finish())
+        .map_err(|e| format!("Failed to compress '{}': {}", path.display(), e))?;
+    write_export_bytes(path, &bytes)
+}
@@ -0,0 +1,384 @@
+//! Request counters and latency histograms for the motis:// protocol layer
+//! and the IPC backend, the GUI counterpart of the server's
+//! `metrics_registry`.
+//!
+//! Read through the `get_metrics` command (JSON) or `motis://localhost/metrics`
+//! (Prometheus text format). Counters live for the lifetime of the process.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds (seconds) shared by all latency histograms; tiles and
+/// geocoding land in the low buckets, transit routing in the high ones.
+const LATENCY_BUCKETS: [f64; 14] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IpcPhase {
+    /// Waiting for the backend lock (another request in flight).
+    QueueWait,
+    /// Command written until response line read.
+    Backend,
+    /// Parsing the response JSON.
+    Decode,
+}
+
+impl IpcPhase {
+    fn label(self) -> &'static str {
+        match self {
+            IpcPhase::QueueWait => "queue_wait",
+            IpcPhase::Backend => "backend",
+            IpcPhase::Decode => "decode",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// Per-bucket (non-cumulative) counts; the last slot is `+Inf`.
+    counts: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        let index = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| seconds <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.counts[index] += 1;
+        self.sum += seconds;
+    }
+
+    fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = LATENCY_BUCKETS
+            .iter()
+            .zip(&self.counts)
+            .map(|(&le, &count)| {
+                cumulative += count;
+                BucketSnapshot {
+                    le,
+                    count: cumulative,
+                }
+            })
+            .collect();
+        HistogramSnapshot {
+            count: self.count(),
+            sum_seconds: self.sum,
+            buckets,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct RouteStats {
+    requests: u64,
+    errors: BTreeMap<String, u64>,
+    latency: Histogram,
+}
+
+#[derive(Debug, Default)]
+pub struct Registry {
+    /// Keyed by route kind and, for passthrough and debug routes, the API
+    /// path. Other kinds (tiles, glyphs, unknown paths) are not split by
+    /// path to keep the label set bounded.
+    routes: BTreeMap<(&'static str, String), RouteStats>,
+    ipc: BTreeMap<IpcPhase, Histogram>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketSnapshot {
+    /// Upper bound in seconds.
+    pub le: f64,
+    /// Observations at or below `le` (cumulative, as in Prometheus).
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramSnapshot {
+    pub count: u64,
+    pub sum_seconds: f64,
+    pub buckets: Vec<BucketSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteMetrics {
+    pub route: String,
+    /// API path for passthrough and debug routes, `None` for the other
+    /// kinds.
+    pub path: Option<String>,
+    pub requests: u64,
+    /// Error counts by stage (`initialization`, `endpoint`, `ipc`).
+    pub errors: BTreeMap<String, u64>,
+    pub latency: HistogramSnapshot,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IpcMetrics {
+    pub phase: String,
+    pub latency: HistogramSnapshot,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    pub routes: Vec<RouteMetrics>,
+    pub ipc: Vec<IpcMetrics>,
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_histogram(out: &mut String, name: &str, labels: &str, histogram: &Histogram) {
+    let separator = if labels.is_empty() { "" } else { "," };
+    let mut cumulative = 0;
+    for (bound, count) in LATENCY_BUCKETS.iter().zip(&histogram.counts) {
+        cumulative += count;
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {cumulative}"
+        );
+    }
+    let total = histogram.count();
+    let _ = writeln!(
+        out,
+        "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {total}"
+    );
+    let _ = writeln!(out, "{name}_sum{{{labels}}} {}", histogram.sum);
+    let _ = writeln!(out, "{name}_count{{{labels}}} {total}");
+}
+
+impl Registry {
+    pub fn observe_request(
+        &mut self,
+        route: &'static str,
+        path: Option<&str>,
+        elapsed: Duration,
+        error_stage: Option<&str>,
+    ) {
+        let stats = self
+            .routes
+            .entry((route, path.unwrap_or_default().to_string()))
+            .or_default();
+        stats.requests += 1;
+        stats.latency.observe(elapsed);
+        if let Some(stage) = error_stage {
+            *stats.errors.entry(stage.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn observe_ipc(&mut self, phase: IpcPhase, elapsed: Duration) {
+        self.ipc.entry(phase).or_default().observe(elapsed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            routes: self
+                .routes
+                .iter()
+                .map(|((route, path), stats)| RouteMetrics {
+                    route: route.to_string(),
+                    path: (!path.is_empty()).then(|| path.clone()),
+                    requests: stats.requests,
+                    errors: stats.errors.clone(),
+                    latency: stats.latency.snapshot(),
+                })
+                .collect(),
+            ipc: self
+                .ipc
+                .iter()
+                .map(|(phase, histogram)| IpcMetrics {
+                    phase: phase.label().to_string(),
+                    latency: histogram.snapshot(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn prometheus_text(&self) -> String {
+        let route_labels = |route: &str, path: &str| {
+            if path.is_empty() {
+                format!("route=\"{}\"", route)
+            } else {
+                format!("route=\"{}\",path=\"{}\"", route, escape_label(path))
+            }
+        };
+        let mut out = String::new();
+
+        out.push_str("# HELP motis_gui_requests_total Number of motis:// requests\n");
+        out.push_str("# TYPE motis_gui_requests_total counter\n");
+        for ((route, path), stats) in &self.routes {
+            let _ = writeln!(
+                out,
+                "motis_gui_requests_total{{{}}} {}",
+                route_labels(route, path),
+                stats.requests
+            );
+        }
+
+        out.push_str("# HELP motis_gui_request_errors_total Failed motis:// requests by stage\n");
+        out.push_str("# TYPE motis_gui_request_errors_total counter\n");
+        for ((route, path), stats) in &self.routes {
+            for (stage, count) in &stats.errors {
+                let _ = writeln!(
+                    out,
+                    "motis_gui_request_errors_total{{{},stage=\"{}\"}} {}",
+                    route_labels(route, path),
+                    escape_label(stage),
+                    count
+                );
+            }
+        }
+
+        out.push_str("# HELP motis_gui_request_duration_seconds motis:// request latency\n");
+        out.push_str("# TYPE motis_gui_request_duration_seconds histogram\n");
+        for ((route, path), stats) in &self.routes {
+            write_histogram(
+                &mut out,
+                "motis_gui_request_duration_seconds",
+                &route_labels(route, path),
+                &stats.latency,
+            );
+        }
+
+        out.push_str("# HELP motis_gui_ipc_duration_seconds Backend command timings by phase\n");
+        out.push_str("# TYPE motis_gui_ipc_duration_seconds histogram\n");
+        for (phase, histogram) in &self.ipc {
+            write_histogram(
+                &mut out,
+                "motis_gui_ipc_duration_seconds",
+                &format!("phase=\"{}\"", phase.label()),
+                histogram,
+            );
+        }
+        out
+    }
+}
+
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::default()));
+
+pub fn observe_request(
+    route: &'static str,
+    path: Option<&str>,
+    elapsed: Duration,
+    error_stage: Option<&str>,
+) {
+    if let Ok(mut registry) = REGISTRY.lock() {
+        registry.observe_request(route, path, elapsed, error_stage);
+    }
+}
+
+pub fn observe_ipc(phase: IpcPhase, elapsed: Duration) {
+    if let Ok(mut registry) = REGISTRY.lock() {
+        registry.observe_ipc(phase, elapsed);
+    }
+}
+
+pub fn snapshot() -> MetricsSnapshot {
+    REGISTRY
+        .lock()
+        .map(|registry| registry.snapshot())
+        .unwrap_or(MetricsSnapshot {
+            routes: Vec::new(),
+            ipc: Vec::new(),
+        })
+}
+
+pub fn prometheus_text() -> String {
+    REGISTRY
+        .lock()
+        .map(|registry| registry.prometheus_text())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IpcPhase, Registry};
+    use std::time::Duration;
+
+    fn registry() -> Registry {
+        let mut registry = Registry::default();
+        let ms = Duration::from_millis;
+        registry.observe_request("passthrough", Some("/api/v5/plan"), ms(300), None);
+        registry.observe_request("passthrough", Some("/api/v5/plan"), ms(40), Some("ipc"));
+        registry.observe_request("tiles", None, ms(3), None);
+        registry.observe_request("tiles", None, ms(2), Some("initialization"));
+        registry.observe_ipc(IpcPhase::QueueWait, ms(0));
+        registry.observe_ipc(IpcPhase::Backend, ms(280));
+        registry.observe_ipc(IpcPhase::Decode, ms(12));
+        registry
+    }
+
+    #[test]
+    fn snapshot_groups_by_route_and_passthrough_path() {
+        let snapshot = registry().snapshot();
+        assert_eq!(snapshot.routes.len(), 2);
+
+        let plan = &snapshot.routes[0];
+        assert_eq!(plan.route, "passthrough");
+        assert_eq!(plan.path.as_deref(), Some("/api/v5/plan"));
+        assert_eq!(plan.requests, 2);
+        assert_eq!(plan.errors.get("ipc"), Some(&1));
+        assert_eq!(plan.latency.count, 2);
+        assert!((plan.latency.sum_seconds - 0.34).abs() < 1e-9);
+        // 40 ms falls in the 0.05 bucket, 300 ms in 0.5; buckets are cumulative.
+        let bucket = |le: f64| {
+            plan.latency
+                .buckets
+                .iter()
+                .find(|b| b.le == le)
+                .map(|b| b.count)
+        };
+        assert_eq!(bucket(0.025), Some(0));
+        assert_eq!(bucket(0.05), Some(1));
+        assert_eq!(bucket(0.25), Some(1));
+        assert_eq!(bucket(0.5), Some(2));
+        assert_eq!(bucket(60.0), Some(2));
+
+        let tiles = &snapshot.routes[1];
+        assert_eq!(tiles.route, "tiles");
+        assert_eq!(tiles.path, None);
+        assert_eq!(tiles.errors.get("initialization"), Some(&1));
+
+        let phases: Vec<&str> = snapshot.ipc.iter().map(|m| m.phase.as_str()).collect();
+        assert_eq!(phases, ["queue_wait", "backend", "decode"]);
+    }
+
+    #[test]
+    fn prometheus_text_has_counters_and_histograms() {
+        let text = registry().prometheus_text();
+        for expected in [
+            "# TYPE motis_gui_requests_total counter",
+            "motis_gui_requests_total{route=\"passthrough\",path=\"/api/v5/plan\"} 2",
+            "motis_gui_requests_total{route=\"tiles\"} 2",
+            "motis_gui_request_errors_total{route=\"passthrough\",path=\"/api/v5/plan\",stage=\"ipc\"} 1",
+            "# TYPE motis_gui_request_duration_seconds histogram",
+            "motis_gui_request_duration_seconds_bucket{route=\"tiles\",le=\"0.005\"} 2",
+            "motis_gui_request_duration_seconds_bucket{route=\"tiles\",le=\"+Inf\"} 2",
+            "motis_gui_request_duration_seconds_count{route=\"tiles\"} 2",
+            "motis_gui_ipc_duration_seconds_bucket{phase=\"backend\",le=\"0.25\"} 0",
+            "motis_gui_ipc_duration_seconds_bucket{phase=\"backend\",le=\"0.5\"} 1",
+            "motis_gui_ipc_duration_seconds_count{phase=\"decode\"} 1",
+        ] {
+            assert!(
+                text.lines().any(|line| line == expected),
+                "missing line {expected:?} in:\n{text}"
+            );
+        }
+    }
+}
@@ -1,27 +1,56 @@
 mod commands;
+mod config;
+mod dataset;
+mod embedded;
 mod ipc;
 mod types;
 
 pub use commands::{
-    api_get_sync, geocode, geocode_sync, get_glyph_sync, get_tile_sync, plan_route,
-    plan_route_sync, reverse_geocode, reverse_geocode_sync,
+    api_get_sync, api_post_sync, dataset_info_sync, geocode, geocode_sync, get_glyph_sync,
+    get_tile_sync, load_elevators_sync, plan_route, plan_route_sync, reverse_geocode,
+    reverse_geocode_sync, set_elevator_status_sync, street_route_sync,
 };
+pub use config::{
+    add_config_dataset, check_import_config, read_config, set_config_feature, write_config,
+    ConfigDocument, ConfigFeature, ConfigIssue, DatasetConfig, ElevatorsConfig, FeatureSetting,
+    GbfsConfig, GbfsFeedConfig, IssueSeverity, MotisConfig, RtFeedConfig, StreetRoutingConfig,
+    TilesConfig, TimetableConfig,
+};
+pub use dataset::{dataset_info, warn_if_outside_service_range};
 pub use ipc::{
-    auto_init, destroy, get_mode, get_startup_diagnostics, init_ipc, is_ipc_initialized,
-    try_auto_init, BackendMode,
+    auto_init, destroy, get_mode, get_startup_diagnostics, init_ipc, init_replay,
+    is_ipc_initialized, set_ipc_response_timeout, start_recording, stop_recording, try_auto_init,
+    BackendMode,
+};
+pub use ipc::paths::{verify_data_manifest, DamagedFile, ManifestReport, ManifestVerifyMode};
+pub use types::{
+    Agency, Area, BoundingBox, DatasetInfo, GeocodeOptions, LatLon, LocationResult, Match,
+    MatchArea, RouteLeg, RouteResult, Token,
 };
-pub use types::{Area, LatLon, LocationResult, Match, MatchArea, RouteLeg, RouteResult, Token};
 
 #[cfg(test)]
 mod tests {
-    use super::commands::build_geocode_command;
+    use super::commands::{
+        build_geocode_command, build_street_route_body, build_update_elevator_body,
+    };
+    use super::config::{
+        add_dataset, locate_key, parse_config, set_feature, validate_config, ConfigFeature,
+        IssueSeverity,
+    };
+    use super::dataset::service_range_warning;
+    use super::embedded::EmbeddedBackend;
     use super::ipc::{
-        init_ipc, recover_ipc_backend, send_ipc_json_command, validate_ipc_command,
-        MAX_IPC_COMMAND_BYTES,
+        for_each_stderr_line, init_ipc, read_response_line, recover_ipc_backend,
+        send_ipc_json_command, validate_ipc_command, with_trace_id, MAX_IPC_COMMAND_BYTES,
     };
     #[cfg(unix)]
     use super::ipc::paths::create_private_temp_dir;
-    use super::destroy;
+    use super::ipc::record::{ReplayBackend, SessionRecorder};
+    use super::{
+        destroy, verify_data_manifest, DatasetInfo, GeocodeOptions, LatLon, LocationResult,
+        ManifestVerifyMode, Match,
+    };
+    use chrono::NaiveDate;
     #[cfg(unix)]
     use std::fs;
     use std::io;
@@ -85,15 +114,352 @@ fi
         assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
     }
 
+    #[test]
+    fn trace_id_is_added_to_json_commands() {
+        let cmd = with_trace_id(r#"{"cmd":"api_get","path":"/api/v5/plan"}"#, "0123abcd");
+        let value: serde_json::Value = serde_json::from_str(&cmd).expect("json command");
+        assert_eq!(value["cmd"], "api_get");
+        assert_eq!(value["path"], "/api/v5/plan");
+        assert_eq!(value["trace_id"], "0123abcd");
+        validate_ipc_command(&cmd).expect("still one line");
+
+        assert_eq!(with_trace_id("not json", "0123abcd"), "not json");
+    }
+
+    #[test]
+    fn street_route_body_matches_motis_format() {
+        let from = LatLon { lat: 59.3303, lon: 18.0586 };
+        let to = LatLon { lat: 59.3326, lon: 18.0649 };
+        let body = build_street_route_body(&from, &to, "foot", None);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "start": { "lat": 59.3303, "lng": 18.0586 },
+                "destination": { "lat": 59.3326, "lng": 18.0649 },
+                "profile": "foot"
+            })
+        );
+        assert_eq!(build_street_route_body(&from, &to, "bike", Some(7200))["max"], 7200);
+    }
+
+    #[test]
+    fn update_elevator_body_matches_motis_format() {
+        let windows = [("2026-10-20T06:00:00Z".to_string(), "2026-10-20T18:00:00Z".to_string())];
+        let body = build_update_elevator_body(10561326, false, &windows);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "id": 10561326,
+                "status": "INACTIVE",
+                "outOfService": [["2026-10-20T06:00:00Z", "2026-10-20T18:00:00Z"]]
+            })
+        );
+        assert_eq!(build_update_elevator_body(7, true, &[])["status"], "ACTIVE");
+    }
+
+    #[test]
+    fn response_reader_enforces_size_limit() {
+        let mut ok = io::Cursor::new(b"{\"status\":\"ok\"}\nrest".to_vec());
+        assert_eq!(read_response_line(&mut ok, 64).unwrap(), "{\"status\":\"ok\"}\n");
+
+        let mut oversized = io::Cursor::new(vec![b'x'; 65]);
+        let err = read_response_line(&mut oversized, 64).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut eof = io::Cursor::new(Vec::new());
+        assert_eq!(read_response_line(&mut eof, 64).unwrap(), "");
+    }
+
+    #[test]
+    fn stderr_lines_are_forwarded_individually() {
+        let stderr =
+            io::Cursor::new(b"loading tt\r\n\n[trace=a1] api_get error: \xff\npartial".to_vec());
+        let mut lines = Vec::new();
+        for_each_stderr_line(stderr, |line| lines.push(line.to_string()));
+        assert_eq!(
+            lines,
+            vec!["loading tt", "[trace=a1] api_get error: \u{fffd}", "partial"]
+        );
+    }
+
     #[test]
     fn geocode_command_serializes_control_chars_safely() {
         let query = "line1\nline2\\\"quoted\"";
-        let cmd = build_geocode_command(query);
+        let cmd = build_geocode_command(query, &GeocodeOptions::default());
         assert!(!cmd.contains('\n'));
 
         let parsed: serde_json::Value = serde_json::from_str(&cmd).expect("parse geocode cmd");
         assert_eq!(parsed["cmd"], "geocode");
         assert_eq!(parsed["query"], query);
+        assert!(parsed.get("options").is_none());
+    }
+
+    #[test]
+    fn geocode_options_are_sent_and_validated() {
+        let options = GeocodeOptions {
+            type_: Some("STOP".to_string()),
+            modes: vec!["BUS".to_string(), "TRAM".to_string()],
+            place: Some(LatLon { lat: 59.366, lon: 18.134 }),
+            place_bias: Some(2.0),
+            language: vec!["sv".to_string()],
+            limit: Some(5),
+        };
+        let parsed: serde_json::Value =
+            serde_json::from_str(&build_geocode_command("lidingö", &options)).expect("parse");
+        assert_eq!(parsed["options"]["type"], "STOP");
+        assert_eq!(parsed["options"]["modes"], serde_json::json!(["BUS", "TRAM"]));
+        assert_eq!(parsed["options"]["place"]["lon"], 18.134);
+        assert_eq!(parsed["options"]["place_bias"], 2.0);
+        assert_eq!(parsed["options"]["limit"], 5);
+        assert!(options.validate().is_ok());
+
+        let bad_type = GeocodeOptions {
+            type_: Some("CITY".to_string()),
+            ..GeocodeOptions::default()
+        };
+        assert!(bad_type.validate().is_err());
+        let bad_bias = GeocodeOptions {
+            place_bias: Some(f64::NAN),
+            ..GeocodeOptions::default()
+        };
+        assert!(bad_bias.validate().is_err());
+    }
+
+    #[test]
+    fn geocode_result_carries_timezone_and_level() {
+        let loc: LocationResult = serde_json::from_value(serde_json::json!({
+            "name": "T-Centralen",
+            "place_id": "sweden_9001",
+            "lat": 59.331,
+            "lon": 18.059,
+            "score": 12.5,
+            "type": "STOP",
+            "tz": "Europe/Stockholm",
+            "level": -2.0
+        }))
+        .expect("parse ipc location");
+
+        let matched = Match::from_location_result(&loc);
+        assert_eq!(matched.tz.as_deref(), Some("Europe/Stockholm"));
+        assert_eq!(matched.level, Some(-2.0));
+
+        let json = serde_json::to_value(&matched).expect("serialize match");
+        assert_eq!(json["tz"], "Europe/Stockholm");
+        assert_eq!(json["level"], -2.0);
+    }
+
+    fn dataset_info_fixture() -> DatasetInfo {
+        serde_json::from_value(serde_json::json!({
+            "feeds": ["sweden"],
+            "service_from": "2026-01-01",
+            "service_to": "2026-06-30",
+            "agencies": [{"id": "SL", "name": "Storstockholms Lokaltrafik"}],
+            "stop_count": 1200,
+            "route_count": 80,
+            "osm_bbox": {
+                "min": {"lat": 59.0, "lon": 17.5},
+                "max": {"lat": 60.0, "lon": 19.0}
+            },
+            "imported_at": "2025-12-31T12:00:00Z"
+        }))
+        .expect("parse dataset info")
+    }
+
+    #[test]
+    fn dataset_info_parses_ipc_payload() {
+        let info = dataset_info_fixture();
+        assert_eq!(info.feeds, vec!["sweden"]);
+        assert_eq!(info.agencies[0].id, "SL");
+        assert_eq!(info.stop_count, 1200);
+        assert_eq!(info.osm_bbox.expect("bbox").max.lon, 19.0);
+        assert!(info.validity_warning.is_none());
+    }
+
+    #[test]
+    fn service_range_warning_only_outside_range() {
+        let info = dataset_info_fixture();
+        let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").expect("date");
+
+        assert!(service_range_warning(&info, day("2026-03-15")).is_none());
+        assert!(service_range_warning(&info, day("2026-06-30")).is_none());
+
+        let expired = service_range_warning(&info, day("2026-07-20")).expect("expired");
+        assert!(expired.contains("expired on 2026-06-30"));
+
+        let early = service_range_warning(&info, day("2025-12-31")).expect("not started");
+        assert!(early.contains("starts on 2026-01-01"));
+    }
+
+    #[test]
+    fn service_range_warning_flags_empty_timetable() {
+        let mut info = dataset_info_fixture();
+        info.service_from = None;
+        info.service_to = None;
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).expect("date");
+        assert!(service_range_warning(&info, today).is_some());
+
+        info.feeds.clear();
+        assert!(service_range_warning(&info, today).is_none());
+    }
+
+    const IMPORT_CONFIG: &str = r#"osm: "/nonexistent/city.osm.pbf"
+timetable:
+  first_day: TODAY
+  num_days: 365
+  datasets:
+    sweden:
+      path: "/nonexistent/gtfs.zip"
+      script: "/nonexistent/sweden-route-fix.lua"
+tiles:
+  profile: /nonexistent/tiles-profile.lua
+  db_size: 274877906944
+  flush_threshold: 100000
+street_routing: true
+geocoding: true
+reverse_geocoding: true
+server:
+  port: 8080
+"#;
+
+    #[test]
+    fn config_round_trip_keeps_unknown_keys() {
+        let config = parse_config(IMPORT_CONFIG).expect("parse import config");
+        assert_eq!(config.osm.as_deref(), Some("/nonexistent/city.osm.pbf"));
+        let timetable = config.timetable.as_ref().expect("timetable");
+        assert_eq!(timetable.num_days, Some(365));
+        assert!(timetable.datasets.contains_key("sweden"));
+        assert!(config.extra.contains_key("server"));
+
+        let text = serde_yaml::to_string(&config).expect("encode config");
+        assert_eq!(parse_config(&text).expect("parse encoded config"), config);
+    }
+
+    #[test]
+    fn config_validation_reports_line_numbers() {
+        let text = IMPORT_CONFIG
+            .replace("first_day: TODAY", "first_day: tomorrow")
+            .replace("osm: \"/nonexistent/city.osm.pbf\"\n", "");
+        let config = parse_config(&text).expect("parse config");
+        let issues = validate_config(&config, &text, std::path::Path::new("/"));
+
+        let first_day = issues
+            .iter()
+            .find(|i| i.key == "timetable.first_day")
+            .expect("first_day issue");
+        assert_eq!(first_day.severity, IssueSeverity::Error);
+        assert_eq!(first_day.line, Some(2));
+
+        let tiles = issues.iter().find(|i| i.key == "tiles").expect("tiles issue");
+        assert_eq!(tiles.severity, IssueSeverity::Error);
+        assert_eq!(tiles.line, Some(8));
+
+        let gtfs = issues
+            .iter()
+            .find(|i| i.key == "timetable.datasets.sweden.path")
+            .expect("missing gtfs warning");
+        assert_eq!(gtfs.severity, IssueSeverity::Warning);
+        assert_eq!(gtfs.line, Some(6));
+
+        let broken = parse_config("timetable:\n  num_days: many\n").expect_err("type error");
+        assert_eq!(broken.line, Some(2));
+        assert_eq!(locate_key(IMPORT_CONFIG, &["tiles", "profile"]), Some(10));
+    }
+
+    #[test]
+    fn config_edits_toggle_features_and_add_datasets() {
+        let mut config = parse_config(IMPORT_CONFIG).expect("parse config");
+        set_feature(&mut config, ConfigFeature::Geocoding, false);
+        assert_eq!(config.geocoding, Some(false));
+        set_feature(&mut config, ConfigFeature::StreetRouting, false);
+        assert!(!config.street_routing.as_ref().unwrap().is_enabled());
+
+        add_dataset(&mut config, "norway", "/data/norway.zip", None).expect("add dataset");
+        assert!(add_dataset(&mut config, "norway", "/data/other.zip", None).is_err());
+        assert!(add_dataset(&mut config, "bad_id", "/data/other.zip", None).is_err());
+        let datasets = &config.timetable.as_ref().unwrap().datasets;
+        assert_eq!(datasets["norway"].path, "/data/norway.zip");
+    }
+
+    #[cfg(unix)]
+    fn write_executable(path: &Path, script: &str) {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::write(path, script).expect("write script");
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).expect("chmod script");
+    }
+
+    #[cfg(unix)]
+    fn run_import_script(bundle: &Path, args: &[&str]) -> std::process::Output {
+        std::process::Command::new("bash")
+            .arg(bundle.join("motis-import.sh"))
+            .args(args)
+            .env_remove("MOTIS_GUI_BIN")
+            .output()
+            .expect("run motis-import.sh")
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn import_script_keeps_config_edits_across_reimport() {
+        let template = Path::new(env!("CARGO_MANIFEST_DIR")).join("../usb-template");
+        let bundle = unique_test_dir("motis-import-roundtrip");
+        fs::create_dir_all(&bundle).expect("create bundle dir");
+        for file in ["motis-import.sh", "sweden-route-fix.lua"] {
+            fs::copy(template.join(file), bundle.join(file)).expect("copy template file");
+        }
+        for file in ["gtfs.zip", "norway.zip", "city.osm.pbf", "tiles-profile.lua"] {
+            fs::write(bundle.join(file), b"").expect("write input file");
+        }
+        // `motis import` leaves its outputs in the working directory.
+        write_executable(&bundle.join("motis"), "#!/bin/sh\ntouch tags.bin\n");
+        // Stands in for the app binary; the check itself is called below.
+        write_executable(
+            &bundle.join("motis-gui-svelte"),
+            "#!/bin/sh\necho \"$@\" >> \"$(dirname \"$0\")/checks.txt\"\n",
+        );
+
+        let gtfs = bundle.join("gtfs.zip").display().to_string();
+        let osm = bundle.join("city.osm.pbf").display().to_string();
+        let output = run_import_script(&bundle, &[gtfs.as_str(), osm.as_str()]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+
+        let data = bundle.join("data");
+        let data_path = data.to_str().expect("utf-8 path");
+        super::add_config_dataset(
+            data_path,
+            "norway",
+            bundle.join("norway.zip").to_str().expect("utf-8 path"),
+            None,
+        )
+        .expect("add dataset");
+        super::set_config_feature(data_path, ConfigFeature::Geocoding, false)
+            .expect("toggle geocoding");
+
+        // Arguments next to an existing config would be ignored; refuse them.
+        let output = run_import_script(&bundle, &[gtfs.as_str(), osm.as_str()]);
+        assert!(!output.status.success());
+
+        let output = run_import_script(&bundle, &[]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+        let config = super::read_config(data_path).expect("read config").config;
+        let datasets = &config.timetable.as_ref().expect("timetable").datasets;
+        assert!(datasets.contains_key("sweden"));
+        assert!(datasets.contains_key("norway"));
+        assert_eq!(config.geocoding, Some(false));
+        assert!(data.join("tags.bin").is_file());
+
+        let checks = fs::read_to_string(bundle.join("checks.txt")).expect("check calls");
+        assert_eq!(checks.lines().count(), 2);
+        assert!(checks.starts_with("--check-import-config "));
+        let bundle_path = bundle.to_str().expect("utf-8 path");
+        super::check_import_config(bundle_path).expect("imported config is complete");
+
+        fs::remove_file(bundle.join("norway.zip")).expect("remove dataset");
+        let err = super::check_import_config(bundle_path).expect_err("missing dataset");
+        assert!(err.contains("config.yml:"), "{err}");
+        assert!(err.contains("norway.zip"), "{err}");
+
+        let _ = fs::remove_dir_all(&bundle);
     }
 
     #[cfg(unix)]
@@ -107,6 +473,142 @@ fi
         let _ = std::fs::remove_dir_all(dir);
     }
 
+    #[cfg(unix)]
+    fn write_manifest_fixture(data_dir: &Path) {
+        // sha256("hello")
+        fs::write(data_dir.join("tt.bin"), "hello").expect("write tt.bin");
+        fs::write(
+            data_dir.join("manifest.txt"),
+            "# motis-data-manifest v1\n\
+             2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824 5 tt.bin\n",
+        )
+        .expect("write manifest");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn data_manifest_is_optional() {
+        let data_dir = unique_test_dir("motis-manifest-missing");
+        fs::create_dir_all(&data_dir).expect("create test data dir");
+
+        let report = verify_data_manifest(data_dir.to_str().unwrap(), ManifestVerifyMode::Full)
+            .expect("verify without manifest");
+        assert!(!report.manifest_found);
+        assert!(report.is_ok());
+
+        let _ = fs::remove_dir_all(data_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn data_manifest_quick_check_detects_truncated_and_missing_files() {
+        let data_dir = unique_test_dir("motis-manifest-quick");
+        fs::create_dir_all(&data_dir).expect("create test data dir");
+        write_manifest_fixture(&data_dir);
+        let data_path = data_dir.to_str().unwrap();
+
+        let report = verify_data_manifest(data_path, ManifestVerifyMode::Quick).expect("verify");
+        assert!(report.is_ok());
+        assert_eq!(report.checked_files, 1);
+
+        fs::write(data_dir.join("tt.bin"), "hel").expect("truncate tt.bin");
+        let report = verify_data_manifest(data_path, ManifestVerifyMode::Quick).expect("verify");
+        assert_eq!(report.damaged.len(), 1);
+        assert_eq!(report.damaged[0].path, "tt.bin");
+        assert!(report.describe_damage(data_path).contains("Next action"));
+
+        fs::remove_file(data_dir.join("tt.bin")).expect("remove tt.bin");
+        let report = verify_data_manifest(data_path, ManifestVerifyMode::Quick).expect("verify");
+        assert!(report.damaged[0].problem.contains("missing"));
+
+        let _ = fs::remove_dir_all(data_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn data_manifest_full_check_detects_same_size_corruption() {
+        let data_dir = unique_test_dir("motis-manifest-full");
+        fs::create_dir_all(&data_dir).expect("create test data dir");
+        write_manifest_fixture(&data_dir);
+        fs::write(data_dir.join("tt.bin"), "jello").expect("corrupt tt.bin");
+        let data_path = data_dir.to_str().unwrap();
+
+        let quick = verify_data_manifest(data_path, ManifestVerifyMode::Quick).expect("verify");
+        assert!(quick.is_ok(), "quick check only compares sizes");
+
+        let full = verify_data_manifest(data_path, ManifestVerifyMode::Full).expect("verify");
+        assert_eq!(full.damaged.len(), 1);
+        assert!(full.damaged[0].problem.contains("checksum"));
+
+        let _ = fs::remove_dir_all(data_dir);
+    }
+
+    #[test]
+    fn embedded_backend_reports_missing_library() {
+        let err = EmbeddedBackend::load("/nonexistent/libmotis-native-embedded.so", "/tmp")
+            .err()
+            .expect("missing library must fail");
+        assert!(err.contains("Failed to load"), "unexpected error: {err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn recorded_session_replays_deterministically() {
+        let root = unique_test_dir("motis-ipc-record");
+        fs::create_dir_all(&root).expect("create test dir");
+        let recording = root.join("session.jsonl");
+        let recording_path = recording.to_str().expect("recording path");
+
+        let mut recorder = SessionRecorder::create(recording_path).expect("create recorder");
+        let first = r#"{"status":"ok","data":[{"name":"Centralen"}]}"#;
+        let second = r#"{"status":"ok","data":[]}"#;
+        recorder
+            .record(r#"{"cmd":"geocode","query":"cen"}"#, first)
+            .expect("record first");
+        recorder
+            .record(r#"{"query":"cen","cmd":"geocode"}"#, second)
+            .expect("record second");
+        recorder
+            .record(r#"{"cmd":"dataset_info"}"#, "not json\n")
+            .expect("record malformed");
+        drop(recorder);
+
+        let parse =
+            |line: &str| serde_json::from_str::<serde_json::Value>(line).expect("JSON response");
+        let mut replay = ReplayBackend::load(recording_path).expect("load recording");
+        let cmd = r#"{"cmd":"geocode","query":"cen"}"#;
+        assert_eq!(parse(&replay.send_command(cmd)), parse(first));
+        assert_eq!(parse(&replay.send_command(cmd)), parse(second));
+        assert_eq!(
+            parse(&replay.send_command(cmd)),
+            parse(second),
+            "last response repeats"
+        );
+        assert_eq!(replay.send_command(r#"{"cmd":"dataset_info"}"#), "not json");
+
+        let unknown = parse(&replay.send_command(r#"{"cmd":"plan_route"}"#));
+        assert_eq!(unknown["status"], "error");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replay_rejects_malformed_recordings_with_line_number() {
+        let root = unique_test_dir("motis-ipc-replay-invalid");
+        fs::create_dir_all(&root).expect("create test dir");
+        let recording = root.join("session.jsonl");
+        fs::write(&recording, "{\"cmd\":{},\"response\":{}}\n{\"cmd\":{}}\n")
+            .expect("write recording");
+
+        let err = ReplayBackend::load(recording.to_str().unwrap())
+            .err()
+            .expect("missing response must fail");
+        assert!(err.contains(":2:"), "unexpected error: {err}");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
     #[test]
     fn destroy_is_idempotent_without_backend() {
         destroy();
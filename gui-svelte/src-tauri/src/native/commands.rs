@@ -1,12 +1,18 @@
-use super::ipc::send_ipc_json_command;
-use super::types::{LocationResult, Match, RouteResult};
+use super::ipc::{send_ipc_json_command, with_typed_embedded};
+use super::types::{DatasetInfo, GeocodeOptions, LatLon, LocationResult, Match, RouteResult};
+use crate::logging::{self, IPC};
 
-pub(crate) fn build_geocode_command(query: &str) -> String {
-    serde_json::json!({
+/// Options are omitted when empty so plain lookups keep the old command
+/// shape (and match existing session recordings).
+pub(crate) fn build_geocode_command(query: &str, options: &GeocodeOptions) -> String {
+    let mut cmd = serde_json::json!({
         "cmd": "geocode",
         "query": query,
-    })
-    .to_string()
+    });
+    if !options.is_empty() {
+        cmd["options"] = serde_json::to_value(options).unwrap_or_default();
+    }
+    cmd.to_string()
 }
 
 fn build_plan_route_command(from_lat: f64, from_lon: f64, to_lat: f64, to_lon: f64) -> String {
@@ -29,14 +35,55 @@ fn build_reverse_geocode_command(lat: f64, lon: f64) -> String {
     .to_string()
 }
 
-pub async fn geocode(query: &str) -> Result<Vec<Match>, Box<dyn std::error::Error>> {
-    eprintln!("[MOTIS-GUI] geocode() called with query: '{}'", query);
+/// Typed C ABI when embedded, JSON command otherwise.
+fn geocode_locations(
+    query: &str,
+    options: &GeocodeOptions,
+) -> Result<Vec<LocationResult>, Box<dyn std::error::Error>> {
+    if let Some(result) = with_typed_embedded(|backend| backend.geocode(query, options)) {
+        return result;
+    }
+    let cmd = build_geocode_command(query, options);
+    logging::trace!(IPC, "Sending command: {}", cmd);
+    let data = send_ipc_json_command(&cmd)?;
+    Ok(serde_json::from_value(data)?)
+}
 
-    let cmd = build_geocode_command(query);
-    eprintln!("[MOTIS-GUI] Sending command: {}", cmd);
+fn plan_routes(
+    from_lat: f64,
+    from_lon: f64,
+    to_lat: f64,
+    to_lon: f64,
+) -> Result<Vec<RouteResult>, Box<dyn std::error::Error>> {
+    let from = LatLon { lat: from_lat, lon: from_lon };
+    let to = LatLon { lat: to_lat, lon: to_lon };
+    if let Some(result) = with_typed_embedded(|backend| backend.plan_route(&from, &to, None)) {
+        return result;
+    }
+    let cmd = build_plan_route_command(from_lat, from_lon, to_lat, to_lon);
+    logging::trace!(IPC, "Sending command: {}", cmd);
     let data = send_ipc_json_command(&cmd)?;
-    let locations: Vec<LocationResult> = serde_json::from_value(data)?;
-    eprintln!("[MOTIS-GUI] Found {} locations", locations.len());
+    Ok(serde_json::from_value(data)?)
+}
+
+/// The typed C ABI returns raw bytes; callers get the same base64 string as
+/// from motis-ipc.
+fn encode_typed_bytes(
+    result: Result<Option<Vec<u8>>, Box<dyn std::error::Error>>,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    Ok(result?
+        .map(|bytes| base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes)))
+}
+
+pub async fn geocode(
+    query: &str,
+    options: &GeocodeOptions,
+) -> Result<Vec<Match>, Box<dyn std::error::Error>> {
+    logging::debug!(IPC, "geocode() called with query: {}", logging::sensitive(query));
+
+    options.validate()?;
+    let locations = geocode_locations(query, options)?;
+    logging::debug!(IPC, "Found {} locations", locations.len());
     let matches: Vec<Match> = locations.iter().map(Match::from_location_result).collect();
     Ok(matches)
 }
@@ -47,16 +94,14 @@ pub async fn plan_route(
     to_lat: f64,
     to_lon: f64,
 ) -> Result<Vec<RouteResult>, Box<dyn std::error::Error>> {
-    eprintln!(
-        "[MOTIS-GUI] plan_route() called: ({}, {}) to ({}, {})",
+    logging::debug!(
+        IPC,
+        "plan_route() called: ({}, {}) to ({}, {})",
         from_lat, from_lon, to_lat, to_lon
     );
 
-    let cmd = build_plan_route_command(from_lat, from_lon, to_lat, to_lon);
-    eprintln!("[MOTIS-GUI] Sending command: {}", cmd);
-    let data = send_ipc_json_command(&cmd)?;
-    let routes: Vec<RouteResult> = serde_json::from_value(data)?;
-    eprintln!("[MOTIS-GUI] Found {} routes", routes.len());
+    let routes = plan_routes(from_lat, from_lon, to_lat, to_lon)?;
+    logging::debug!(IPC, "Found {} routes", routes.len());
     Ok(routes)
 }
 
@@ -74,10 +119,12 @@ pub async fn reverse_geocode(
     }
 }
 
-pub fn geocode_sync(query: &str) -> Result<Vec<Match>, Box<dyn std::error::Error>> {
-    let cmd = build_geocode_command(query);
-    let data = send_ipc_json_command(&cmd)?;
-    let locations: Vec<LocationResult> = serde_json::from_value(data)?;
+pub fn geocode_sync(
+    query: &str,
+    options: &GeocodeOptions,
+) -> Result<Vec<Match>, Box<dyn std::error::Error>> {
+    options.validate()?;
+    let locations = geocode_locations(query, options)?;
     let matches: Vec<Match> = locations.iter().map(Match::from_location_result).collect();
     Ok(matches)
 }
@@ -88,10 +135,7 @@ pub fn plan_route_sync(
     to_lat: f64,
     to_lon: f64,
 ) -> Result<Vec<RouteResult>, Box<dyn std::error::Error>> {
-    let cmd = build_plan_route_command(from_lat, from_lon, to_lat, to_lon);
-    let data = send_ipc_json_command(&cmd)?;
-    let routes: Vec<RouteResult> = serde_json::from_value(data)?;
-    Ok(routes)
+    plan_routes(from_lat, from_lon, to_lat, to_lon)
 }
 
 pub fn reverse_geocode_sync(
@@ -113,6 +157,9 @@ pub fn get_tile_sync(
     x: i32,
     y: i32,
 ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    if let Some(result) = with_typed_embedded(|backend| backend.get_tile(z, x, y)) {
+        return encode_typed_bytes(result);
+    }
     let cmd = serde_json::json!({
         "cmd": "get_tile",
         "z": z,
@@ -131,6 +178,9 @@ pub fn get_tile_sync(
 }
 
 pub fn get_glyph_sync(path: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    if let Some(result) = with_typed_embedded(|backend| backend.get_glyph(path)) {
+        return encode_typed_bytes(result);
+    }
     let cmd = serde_json::json!({
         "cmd": "get_glyph",
         "path": path
@@ -148,6 +198,12 @@ pub fn get_glyph_sync(path: &str) -> Result<Option<String>, Box<dyn std::error::
 }
 
 pub fn api_get_sync(path_and_query: &str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    if let Some(result) = with_typed_embedded(|backend| backend.api_get(path_and_query)) {
+        let payload = result?;
+        return serde_json::from_str(&payload).map_err(|_| {
+            format!("Endpoint did not return valid JSON: {}", path_and_query).into()
+        });
+    }
     let cmd = serde_json::json!({
         "cmd": "api_get",
         "path": path_and_query
@@ -156,3 +212,109 @@ pub fn api_get_sync(path_and_query: &str) -> Result<serde_json::Value, Box<dyn s
 
     send_ipc_json_command(&cmd)
 }
+
+/// Calls a MOTIS POST endpoint (e.g. `/api/update_elevator`) with a JSON body.
+pub fn api_post_sync(
+    path: &str,
+    body: &serde_json::Value,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let cmd = serde_json::json!({
+        "cmd": "api_post",
+        "path": path,
+        "body": body
+    })
+    .to_string();
+
+    send_ipc_json_command(&cmd)
+}
+
+/// Body for `/api/route`. `profile` is an OSR search profile such as `foot`,
+/// `wheelchair`, `bike` or `car`; `max_seconds` caps the search (MOTIS
+/// default: 3600).
+pub(crate) fn build_street_route_body(
+    from: &LatLon,
+    to: &LatLon,
+    profile: &str,
+    max_seconds: Option<u32>,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "start": { "lat": from.lat, "lng": from.lon },
+        "destination": { "lat": to.lat, "lng": to.lon },
+        "profile": profile
+    });
+    if let Some(max) = max_seconds {
+        body["max"] = max.into();
+    }
+    body
+}
+
+/// Street-only route without transit. Returns a GeoJSON FeatureCollection of
+/// line segments (with OSM way and level) and `metadata.duration` (seconds)
+/// and `metadata.distance` (meters).
+pub fn street_route_sync(
+    from: &LatLon,
+    to: &LatLon,
+    profile: &str,
+    max_seconds: Option<u32>,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let body = build_street_route_body(from, to, profile, max_seconds);
+    let data = api_post_sync("/api/route", &body)?;
+    if let Some(error) = data["error"].as_str() {
+        return Err(format!(
+            "Street routing failed: {}. Next action: move the points closer to a road or path, or try another profile.",
+            error
+        )
+        .into());
+    }
+    Ok(data)
+}
+
+/// Body for `/api/update_elevator`; `out_of_service` holds ISO 8601
+/// `[from, to]` maintenance windows.
+pub(crate) fn build_update_elevator_body(
+    id: i64,
+    active: bool,
+    out_of_service: &[(String, String)],
+) -> serde_json::Value {
+    serde_json::json!({
+        "id": id,
+        "status": if active { "ACTIVE" } else { "INACTIVE" },
+        "outOfService": out_of_service
+            .iter()
+            .map(|(from, to)| [from, to])
+            .collect::<Vec<_>>()
+    })
+}
+
+/// Marks one elevator as working or out of service; footpaths at nearby
+/// stations are recomputed by the backend.
+pub fn set_elevator_status_sync(
+    id: i64,
+    active: bool,
+    out_of_service: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let body = build_update_elevator_body(id, active, out_of_service);
+    api_post_sync("/api/update_elevator", &body)?;
+    Ok(())
+}
+
+/// Replaces the backend's elevator status with a FASTA JSON export and
+/// returns the number of elevators loaded.
+pub fn load_elevators_sync(fasta_path: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    let cmd = serde_json::json!({
+        "cmd": "load_elevators",
+        "path": fasta_path
+    })
+    .to_string();
+
+    let data = send_ipc_json_command(&cmd)?;
+    let count = data["elevators"].as_u64().ok_or("Invalid load_elevators response")?;
+    Ok(count as usize)
+}
+
+pub fn dataset_info_sync() -> Result<DatasetInfo, Box<dyn std::error::Error>> {
+    let cmd = serde_json::json!({ "cmd": "dataset_info" }).to_string();
+    let data = send_ipc_json_command(&cmd)?;
+    let info: DatasetInfo = serde_json::from_value(data)?;
+    Ok(info)
+}
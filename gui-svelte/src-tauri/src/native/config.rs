@@ -0,0 +1,588 @@
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Keys the model does not know about are kept in `extra` so a read/write
+/// round trip never drops settings written by newer MOTIS versions.
+type ExtraKeys = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MotisConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub osm: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timetable: Option<TimetableConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tiles: Option<TilesConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gbfs: Option<GbfsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevators: Option<FeatureSetting<ElevatorsConfig>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub street_routing: Option<FeatureSetting<StreetRoutingConfig>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub osr_footpath: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geocoding: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverse_geocoding: Option<bool>,
+    #[serde(flatten)]
+    pub extra: ExtraKeys,
+}
+
+/// MOTIS accepts `street_routing: true` as well as a settings block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FeatureSetting<T> {
+    Flag(bool),
+    Settings(T),
+}
+
+impl<T> FeatureSetting<T> {
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            FeatureSetting::Flag(enabled) => *enabled,
+            FeatureSetting::Settings(_) => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimetableConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_day: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_days: Option<u32>,
+    #[serde(default)]
+    pub datasets: BTreeMap<String, DatasetConfig>,
+    #[serde(flatten)]
+    pub extra: ExtraKeys,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatasetConfig {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rt: Option<Vec<RtFeedConfig>>,
+    #[serde(flatten)]
+    pub extra: ExtraKeys,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RtFeedConfig {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(flatten)]
+    pub extra: ExtraKeys,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TilesConfig {
+    pub profile: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coastline: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flush_threshold: Option<u64>,
+    #[serde(flatten)]
+    pub extra: ExtraKeys,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GbfsConfig {
+    #[serde(default)]
+    pub feeds: BTreeMap<String, GbfsFeedConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_interval: Option<u32>,
+    #[serde(flatten)]
+    pub extra: ExtraKeys,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GbfsFeedConfig {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+    #[serde(flatten)]
+    pub extra: ExtraKeys,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ElevatorsConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub init: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_timeout: Option<u32>,
+    #[serde(flatten)]
+    pub extra: ExtraKeys,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreetRoutingConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation_data_dir: Option<String>,
+    #[serde(flatten)]
+    pub extra: ExtraKeys,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueSeverity {
+    /// MOTIS refuses to import or start with this config.
+    Error,
+    /// Only matters for a re-import (e.g. source files not on this machine).
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigIssue {
+    pub severity: IssueSeverity,
+    /// Dotted key path, e.g. `timetable.datasets.sweden.path`.
+    pub key: String,
+    /// 1-based line in `config.yml`, when the key could be located.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigDocument {
+    pub path: String,
+    pub config: MotisConfig,
+    pub issues: Vec<ConfigIssue>,
+}
+
+/// Features that can be switched on/off from the GUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFeature {
+    StreetRouting,
+    Geocoding,
+    ReverseGeocoding,
+    OsrFootpath,
+    Elevators,
+}
+
+pub(crate) fn config_path(data_path: &str) -> PathBuf {
+    Path::new(data_path).join("config.yml")
+}
+
+/// Finds the line of a nested block-style key (`a:` → `  b:` → ...).
+/// Flow style and anchors are not followed; callers fall back to `None`.
+pub(crate) fn locate_key(text: &str, key_path: &[&str]) -> Option<usize> {
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let trimmed = raw.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let mut indent = raw.len() - trimmed.len();
+        let mut entry = trimmed;
+        while let Some(rest) = entry.strip_prefix("- ") {
+            indent += 2;
+            entry = rest.trim_start();
+        }
+        let Some((key, _)) = entry.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().trim_matches(|c| c == '"' || c == '\'');
+
+        while stack.last().is_some_and(|(i, _)| *i >= indent) {
+            stack.pop();
+        }
+        stack.push((indent, key.to_string()));
+
+        if stack.len() == key_path.len() && stack.iter().zip(key_path).all(|((_, a), b)| a == b) {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+pub(crate) fn parse_config(text: &str) -> Result<MotisConfig, ConfigIssue> {
+    if text.trim().is_empty() {
+        return Ok(MotisConfig::default());
+    }
+    serde_yaml::from_str(text).map_err(|e| ConfigIssue {
+        severity: IssueSeverity::Error,
+        key: String::new(),
+        line: e.location().map(|l| l.line()),
+        message: format!("config.yml is not valid: {}", e),
+    })
+}
+
+fn is_valid_first_day(value: &str) -> bool {
+    value == "TODAY" || chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok()
+}
+
+/// Resolves a config path the way `motis import` does when started from the
+/// directory holding `config.yml`.
+fn resolve(base_dir: &Path, value: &str) -> PathBuf {
+    let path = Path::new(value);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    }
+}
+
+/// Mirrors `config::verify()` and `config::verify_input_files_exist()` from
+/// the C++ side. Missing input files are warnings: a finished data bundle no
+/// longer needs the GTFS/OSM sources, only a re-import does.
+pub(crate) fn validate_config(
+    config: &MotisConfig,
+    text: &str,
+    base_dir: &Path,
+) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let mut push = |severity, key_path: &[&str], message: String| {
+        issues.push(ConfigIssue {
+            severity,
+            key: key_path.join("."),
+            line: locate_key(text, key_path),
+            message,
+        });
+    };
+
+    let street_routing = config
+        .street_routing
+        .as_ref()
+        .is_some_and(FeatureSetting::is_enabled);
+    let elevators = config
+        .elevators
+        .as_ref()
+        .is_some_and(FeatureSetting::is_enabled);
+
+    if config.tiles.is_some() && config.osm.is_none() {
+        push(
+            IssueSeverity::Error,
+            &["tiles"],
+            "feature TILES requires OpenStreetMap data (osm)".to_string(),
+        );
+    }
+    if street_routing && config.osm.is_none() {
+        push(
+            IssueSeverity::Error,
+            &["street_routing"],
+            "feature STREET_ROUTING requires OpenStreetMap data (osm)".to_string(),
+        );
+    }
+    if config.osr_footpath == Some(true) && !(street_routing && config.timetable.is_some()) {
+        push(
+            IssueSeverity::Error,
+            &["osr_footpath"],
+            "feature OSR_FOOTPATH requires STREET_ROUTING and TIMETABLE".to_string(),
+        );
+    }
+    if elevators && !(street_routing && config.timetable.is_some()) {
+        push(
+            IssueSeverity::Error,
+            &["elevators"],
+            "feature ELEVATORS requires STREET_ROUTING and TIMETABLE".to_string(),
+        );
+    }
+    if elevators && config.osr_footpath != Some(true) {
+        push(
+            IssueSeverity::Error,
+            &["elevators"],
+            "feature ELEVATORS requires OSR_FOOTPATH".to_string(),
+        );
+    }
+    if matches!(config.elevators, Some(FeatureSetting::Flag(true))) {
+        push(
+            IssueSeverity::Error,
+            &["elevators"],
+            "elevators: true is not supported, use a settings block (url/init)".to_string(),
+        );
+    }
+    if config.gbfs.as_ref().is_some_and(|g| !g.feeds.is_empty()) && !street_routing {
+        push(
+            IssueSeverity::Error,
+            &["gbfs"],
+            "feature GBFS requires STREET_ROUTING".to_string(),
+        );
+    }
+
+    if let Some(osm) = &config.osm {
+        if !resolve(base_dir, osm).is_file() {
+            push(
+                IssueSeverity::Warning,
+                &["osm"],
+                format!("OpenStreetMap file does not exist: {}", osm),
+            );
+        }
+    }
+
+    if let Some(tiles) = &config.tiles {
+        if !resolve(base_dir, &tiles.profile).is_file() {
+            push(
+                IssueSeverity::Warning,
+                &["tiles", "profile"],
+                format!("tiles profile does not exist: {}", tiles.profile),
+            );
+        }
+        if let Some(coastline) = &tiles.coastline {
+            if !resolve(base_dir, coastline).is_file() {
+                push(
+                    IssueSeverity::Warning,
+                    &["tiles", "coastline"],
+                    format!("coastline file does not exist: {}", coastline),
+                );
+            }
+        }
+    }
+
+    if let Some(timetable) = &config.timetable {
+        if timetable.datasets.is_empty() {
+            push(
+                IssueSeverity::Error,
+                &["timetable", "datasets"],
+                "feature TIMETABLE requires at least one dataset".to_string(),
+            );
+        }
+        if timetable.num_days == Some(0) {
+            push(
+                IssueSeverity::Error,
+                &["timetable", "num_days"],
+                "num_days must be greater than 0".to_string(),
+            );
+        }
+        if let Some(first_day) = &timetable.first_day {
+            if !is_valid_first_day(first_day) {
+                push(
+                    IssueSeverity::Error,
+                    &["timetable", "first_day"],
+                    format!("first_day must be TODAY or YYYY-MM-DD, got '{}'", first_day),
+                );
+            }
+        }
+
+        for (id, dataset) in &timetable.datasets {
+            let key = |field: &'static str| ["timetable", "datasets", id.as_str(), field];
+            if id.contains('_') {
+                push(
+                    IssueSeverity::Error,
+                    &["timetable", "datasets", id.as_str()],
+                    format!("dataset identifier '{}' may not contain '_'", id),
+                );
+            }
+            if !resolve(base_dir, &dataset.path).exists() {
+                push(
+                    IssueSeverity::Warning,
+                    &key("path"),
+                    format!("timetable dataset {} does not exist: {}", id, dataset.path),
+                );
+            }
+            if let Some(script) = &dataset.script {
+                if !resolve(base_dir, script).is_file() {
+                    push(
+                        IssueSeverity::Warning,
+                        &key("script"),
+                        format!("user script for {} not found at path: {}", id, script),
+                    );
+                }
+            }
+            for feed in dataset.rt.iter().flatten() {
+                if !(feed.url.starts_with("http://") || feed.url.starts_with("https://")) {
+                    push(
+                        IssueSeverity::Error,
+                        &key("rt"),
+                        format!("{} is not a valid url", feed.url),
+                    );
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+pub(crate) fn has_errors(issues: &[ConfigIssue]) -> bool {
+    issues.iter().any(|i| i.severity == IssueSeverity::Error)
+}
+
+/// Reads and validates `<data_path>/config.yml`. A YAML syntax error is
+/// returned as the single issue of an otherwise empty document.
+pub fn read_config(data_path: &str) -> Result<ConfigDocument, String> {
+    let path = config_path(data_path);
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    let (config, issues) = match parse_config(&text) {
+        Ok(config) => {
+            let issues = validate_config(&config, &text, base_dir);
+            (config, issues)
+        }
+        Err(issue) => (MotisConfig::default(), vec![issue]),
+    };
+    Ok(ConfigDocument {
+        path: path.display().to_string(),
+        config,
+        issues,
+    })
+}
+
+/// Pre-import check behind `--check-import-config`, run by `motis-import.sh`
+/// on the config it is about to import. Unlike in the GUI, missing source
+/// files fail here too: the import reads them.
+pub fn check_import_config(dir: &str) -> Result<(), String> {
+    let document = read_config(dir)?;
+    if document.issues.is_empty() {
+        return Ok(());
+    }
+    Err(document
+        .issues
+        .iter()
+        .map(|issue| {
+            let location = match issue.line {
+                Some(line) => format!("{}:{}", document.path, line),
+                None => document.path.clone(),
+            };
+            format!("{}: {}", location, issue.message)
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Validates and writes `config` to `<data_path>/config.yml` (tmp + rename).
+/// Comments in the original file are not preserved.
+pub fn write_config(data_path: &str, config: &MotisConfig) -> Result<Vec<ConfigIssue>, String> {
+    let path = config_path(data_path);
+    let text =
+        serde_yaml::to_string(config).map_err(|e| format!("Failed to encode config: {}", e))?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    let issues = validate_config(config, &text, base_dir);
+    if has_errors(&issues) {
+        let messages = issues
+            .iter()
+            .filter(|i| i.severity == IssueSeverity::Error)
+            .map(|i| i.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        return Err(format!("config.yml not written: {}", messages));
+    }
+
+    let tmp = path.with_extension("yml.tmp");
+    std::fs::write(&tmp, &text)
+        .map_err(|e| format!("Failed to write '{}': {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        format!("Failed to replace '{}': {}", path.display(), e)
+    })?;
+    Ok(issues)
+}
+
+pub(crate) fn set_feature(config: &mut MotisConfig, feature: ConfigFeature, enabled: bool) {
+    match feature {
+        ConfigFeature::StreetRouting => {
+            // Keep an existing settings block when the feature stays on.
+            if !enabled
+                || !config
+                    .street_routing
+                    .as_ref()
+                    .is_some_and(FeatureSetting::is_enabled)
+            {
+                config.street_routing = Some(FeatureSetting::Flag(enabled));
+            }
+        }
+        ConfigFeature::Geocoding => config.geocoding = Some(enabled),
+        ConfigFeature::ReverseGeocoding => config.reverse_geocoding = Some(enabled),
+        ConfigFeature::OsrFootpath => config.osr_footpath = Some(enabled),
+        ConfigFeature::Elevators => {
+            if !enabled {
+                config.elevators = Some(FeatureSetting::Flag(false));
+            } else if !config
+                .elevators
+                .as_ref()
+                .is_some_and(FeatureSetting::is_enabled)
+            {
+                config.elevators = Some(FeatureSetting::Settings(ElevatorsConfig::default()));
+            }
+        }
+    }
+}
+
+pub(crate) fn add_dataset(
+    config: &mut MotisConfig,
+    id: &str,
+    path: &str,
+    script: Option<String>,
+) -> Result<(), String> {
+    let id = id.trim();
+    if id.is_empty() || id.contains('_') || id.contains(char::is_whitespace) {
+        return Err(format!(
+            "Invalid dataset identifier '{}': use letters, digits and '-' only",
+            id
+        ));
+    }
+    let timetable = config.timetable.get_or_insert_with(|| TimetableConfig {
+        first_day: Some("TODAY".to_string()),
+        num_days: Some(365),
+        ..TimetableConfig::default()
+    });
+    if timetable.datasets.contains_key(id) {
+        return Err(format!("Dataset '{}' already exists", id));
+    }
+    timetable.datasets.insert(
+        id.to_string(),
+        DatasetConfig {
+            path: path.to_string(),
+            script,
+            ..DatasetConfig::default()
+        },
+    );
+    Ok(())
+}
+
+fn edit_config(
+    data_path: &str,
+    edit: impl FnOnce(&mut MotisConfig) -> Result<(), String>,
+) -> Result<ConfigDocument, String> {
+    let path = config_path(data_path);
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let mut config = parse_config(&text).map_err(|issue| match issue.line {
+        Some(line) => format!("line {}: {}", line, issue.message),
+        None => issue.message,
+    })?;
+    edit(&mut config)?;
+    let issues = write_config(data_path, &config)?;
+    Ok(ConfigDocument {
+        path: path.display().to_string(),
+        config,
+        issues,
+    })
+}
+
+pub fn set_config_feature(
+    data_path: &str,
+    feature: ConfigFeature,
+    enabled: bool,
+) -> Result<ConfigDocument, String> {
+    edit_config(data_path, |config| {
+        set_feature(config, feature, enabled);
+        Ok(())
+    })
+}
+
+/// Adds a GTFS dataset. `motis-import.sh` imports from `data/config.yml`
+/// when it exists, so the dataset is picked up by the next import.
+pub fn add_config_dataset(
+    data_path: &str,
+    id: &str,
+    path: &str,
+    script: Option<String>,
+) -> Result<ConfigDocument, String> {
+    edit_config(data_path, |config| add_dataset(config, id, path, script))
+}
@@ -0,0 +1,72 @@
+use chrono::NaiveDate;
+
+use super::commands::dataset_info_sync;
+use super::types::DatasetInfo;
+use crate::logging::{self, IPC};
+
+fn parse_service_day(value: Option<&str>) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value?, "%Y-%m-%d").ok()
+}
+
+pub(crate) fn service_range_warning(info: &DatasetInfo, today: NaiveDate) -> Option<String> {
+    let (Some(from), Some(to)) = (
+        parse_service_day(info.service_from.as_deref()),
+        parse_service_day(info.service_to.as_deref()),
+    ) else {
+        if info.feeds.is_empty() {
+            return None;
+        }
+        return Some(
+            "Timetable contains no service days. Next action: re-import a valid GTFS feed (motis-import.sh)."
+                .to_string(),
+        );
+    };
+
+    if today < from {
+        return Some(format!(
+            "Timetable service starts on {} (today is {}). Plans before that date come back empty.",
+            from, today
+        ));
+    }
+    if today > to {
+        return Some(format!(
+            "Timetable expired on {} (today is {}). Plans come back empty. Next action: re-import a current GTFS feed (motis-import.sh).",
+            to, today
+        ));
+    }
+    None
+}
+
+fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+pub async fn dataset_info() -> Result<DatasetInfo, Box<dyn std::error::Error>> {
+    let mut info = dataset_info_sync()?;
+    info.validity_warning = service_range_warning(&info, today());
+    Ok(info)
+}
+
+/// Queries dataset metadata once the backend is up and logs a warning when
+/// today's date is outside the timetable service range.
+pub fn warn_if_outside_service_range() {
+    match dataset_info_sync() {
+        Ok(info) => {
+            logging::info!(
+                IPC,
+                "Dataset: feeds={:?}, service={}..{}, stops={}, routes={}",
+                info.feeds,
+                info.service_from.as_deref().unwrap_or("?"),
+                info.service_to.as_deref().unwrap_or("?"),
+                info.stop_count,
+                info.route_count
+            );
+            if let Some(warning) = service_range_warning(&info, today()) {
+                logging::warning!(IPC, "{}", warning);
+            }
+        }
+        Err(e) => {
+            logging::warning!(IPC, "Dataset info unavailable: {}", e);
+        }
+    }
+}
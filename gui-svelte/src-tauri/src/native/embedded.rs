@@ -0,0 +1,566 @@
+use libloading::Library;
+use std::ffi::{c_char, c_double, c_int, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+
+use super::types::{GeocodeOptions, LatLon, LocationResult, MatchArea, RouteLeg, RouteResult};
+
+/// Shared library built with `-DMOTIS_NATIVE_EMBEDDED=ON` (see native/c_api.h).
+pub(crate) const EMBEDDED_LIBRARY_NAME: &str = "libmotis-native-embedded.so";
+
+type InitFn = unsafe extern "C" fn(*const c_char, *mut *mut c_char) -> *mut c_void;
+type DestroyFn = unsafe extern "C" fn(*mut c_void);
+type CommandFn = unsafe extern "C" fn(*mut c_void, *const c_char) -> *mut c_char;
+type FreeStringFn = unsafe extern "C" fn(*mut c_char);
+type PlanRouteFn = unsafe extern "C" fn(
+    *mut c_void,
+    c_double,
+    c_double,
+    c_double,
+    c_double,
+    *const c_char,
+    *mut CRoutes,
+    *mut *mut c_char,
+) -> c_int;
+type FreeRoutesFn = unsafe extern "C" fn(*mut CRoutes);
+type GeocodeFn = unsafe extern "C" fn(
+    *mut c_void,
+    *const c_char,
+    *const CGeocodeOptions,
+    *mut CLocations,
+    *mut *mut c_char,
+) -> c_int;
+type FreeLocationsFn = unsafe extern "C" fn(*mut CLocations);
+type GetTileFn = unsafe extern "C" fn(
+    *mut c_void,
+    c_int,
+    c_int,
+    c_int,
+    *mut *mut u8,
+    *mut usize,
+    *mut *mut c_char,
+) -> c_int;
+type GetGlyphFn = unsafe extern "C" fn(
+    *mut c_void,
+    *const c_char,
+    *mut *mut u8,
+    *mut usize,
+    *mut *mut c_char,
+) -> c_int;
+type FreeBytesFn = unsafe extern "C" fn(*mut u8);
+type SetTraceIdFn = unsafe extern "C" fn(*const c_char);
+type ApiGetFn = unsafe extern "C" fn(*mut c_void, *const c_char, *mut *mut c_char) -> *mut c_char;
+
+// Mirrors of the structs in native/c_api.h.
+
+#[repr(C)]
+struct CLeg {
+    mode: *const c_char,
+    from_name: *const c_char,
+    to_name: *const c_char,
+    from_lat: c_double,
+    from_lon: c_double,
+    to_lat: c_double,
+    to_lon: c_double,
+    duration_seconds: c_int,
+    distance_meters: c_int,
+    route_short_name: *const c_char,
+    headsign: *const c_char,
+}
+
+#[repr(C)]
+struct CRoute {
+    duration_seconds: c_int,
+    transfers: c_int,
+    n_legs: usize,
+    legs: *const CLeg,
+}
+
+#[repr(C)]
+struct CRoutes {
+    n_routes: usize,
+    routes: *const CRoute,
+    storage: *mut c_void,
+}
+
+#[repr(C)]
+struct CGeocodeOptions {
+    type_: *const c_char,
+    modes: *const *const c_char,
+    n_modes: usize,
+    has_place: c_int,
+    place_lat: c_double,
+    place_lon: c_double,
+    has_place_bias: c_int,
+    place_bias: c_double,
+    language: *const *const c_char,
+    n_language: usize,
+    limit: usize,
+}
+
+#[repr(C)]
+struct CArea {
+    name: *const c_char,
+    admin_level: c_int,
+    matched: c_int,
+    unique: c_int,
+    is_default: c_int,
+}
+
+#[repr(C)]
+struct CLocation {
+    name: *const c_char,
+    place_id: *const c_char,
+    lat: c_double,
+    lon: c_double,
+    type_: *const c_char,
+    score: c_double,
+    category: *const c_char,
+    n_areas: usize,
+    areas: *const CArea,
+    n_tokens: usize,
+    tokens: *const c_int,
+    has_modes: c_int,
+    n_modes: usize,
+    modes: *const *const c_char,
+    has_importance: c_int,
+    importance: c_double,
+    street: *const c_char,
+    house_number: *const c_char,
+    country: *const c_char,
+    zip: *const c_char,
+    tz: *const c_char,
+    has_level: c_int,
+    level: c_double,
+}
+
+#[repr(C)]
+struct CLocations {
+    n_locations: usize,
+    locations: *const CLocation,
+    storage: *mut c_void,
+}
+
+/// Typed entry points of the C ABI (route planning, geocoding, tiles,
+/// glyphs, `api_get`) that skip the JSON round trip.
+struct TypedApi {
+    plan_route: PlanRouteFn,
+    free_routes: FreeRoutesFn,
+    geocode: GeocodeFn,
+    free_locations: FreeLocationsFn,
+    get_tile: GetTileFn,
+    get_glyph: GetGlyphFn,
+    free_bytes: FreeBytesFn,
+    api_get: ApiGetFn,
+    set_trace_id: SetTraceIdFn,
+}
+
+/// In-process MOTIS instance loaded through the C ABI. Hot paths use the
+/// typed entry points; everything else uses the same JSON request/response
+/// format as motis-ipc, without the process boundary.
+pub(crate) struct EmbeddedBackend {
+    handle: *mut c_void,
+    destroy: DestroyFn,
+    command: CommandFn,
+    free_string: FreeStringFn,
+    typed: TypedApi,
+    // Must outlive the function pointers above; dropped last.
+    _library: Library,
+}
+
+// The C API is not thread-safe per handle; callers serialize access through
+// the backend mutex, so moving the handle between threads is fine.
+unsafe impl Send for EmbeddedBackend {}
+
+/// Takes ownership of a string allocated by the C API.
+unsafe fn take_c_string(ptr: *mut c_char, free_string: FreeStringFn) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    let value = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+    free_string(ptr);
+    Some(value)
+}
+
+/// Copies a borrowed, possibly NULL C string.
+unsafe fn borrowed_string(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+unsafe fn borrowed_slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if ptr.is_null() || len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, len)
+    }
+}
+
+unsafe fn route_from_c(route: &CRoute) -> RouteResult {
+    RouteResult {
+        duration_seconds: route.duration_seconds,
+        transfers: route.transfers,
+        legs: borrowed_slice(route.legs, route.n_legs)
+            .iter()
+            .map(|leg| RouteLeg {
+                mode: borrowed_string(leg.mode).unwrap_or_default(),
+                from_name: borrowed_string(leg.from_name).unwrap_or_default(),
+                to_name: borrowed_string(leg.to_name).unwrap_or_default(),
+                from: LatLon {
+                    lat: leg.from_lat,
+                    lon: leg.from_lon,
+                },
+                to: LatLon {
+                    lat: leg.to_lat,
+                    lon: leg.to_lon,
+                },
+                duration_seconds: leg.duration_seconds,
+                distance_meters: leg.distance_meters,
+                route_short_name: borrowed_string(leg.route_short_name),
+                headsign: borrowed_string(leg.headsign),
+            })
+            .collect(),
+    }
+}
+
+unsafe fn location_from_c(loc: &CLocation) -> LocationResult {
+    LocationResult {
+        name: borrowed_string(loc.name).unwrap_or_default(),
+        place_id: borrowed_string(loc.place_id).unwrap_or_default(),
+        lat: loc.lat,
+        lon: loc.lon,
+        type_: borrowed_string(loc.type_),
+        areas: borrowed_slice(loc.areas, loc.n_areas)
+            .iter()
+            .map(|area| MatchArea {
+                name: borrowed_string(area.name).unwrap_or_default(),
+                admin_level: Some(area.admin_level),
+                matched: Some(area.matched != 0),
+                unique: Some(area.unique != 0),
+                is_default: Some(area.is_default != 0),
+            })
+            .collect(),
+        tokens: borrowed_slice(loc.tokens, loc.n_tokens * 2)
+            .chunks_exact(2)
+            .map(|pair| pair.to_vec())
+            .collect(),
+        score: loc.score,
+        category: borrowed_string(loc.category),
+        modes: (loc.has_modes != 0).then(|| {
+            borrowed_slice(loc.modes, loc.n_modes)
+                .iter()
+                .filter_map(|mode| borrowed_string(*mode))
+                .collect()
+        }),
+        importance: (loc.has_importance != 0).then_some(loc.importance),
+        street: borrowed_string(loc.street),
+        house_number: borrowed_string(loc.house_number),
+        country: borrowed_string(loc.country),
+        zip: borrowed_string(loc.zip),
+        tz: borrowed_string(loc.tz),
+        level: (loc.has_level != 0).then_some(loc.level),
+    }
+}
+
+fn c_strings(values: &[String], what: &str) -> Result<Vec<CString>, String> {
+    values
+        .iter()
+        .map(|v| CString::new(v.as_str()).map_err(|_| format!("{} contains a NUL byte", what)))
+        .collect()
+}
+
+impl EmbeddedBackend {
+    pub(crate) fn load(library_path: &str, data_path: &str) -> Result<Self, String> {
+        let data_path_c = CString::new(data_path)
+            .map_err(|_| format!("Data path contains a NUL byte: {:?}", data_path))?;
+
+        // SAFETY: loading runs the library's static initializers; the library
+        // is our own motis-native build, symbols are checked below.
+        let library = unsafe { Library::new(library_path) }
+            .map_err(|e| format!("Failed to load '{}': {}", library_path, e))?;
+
+        // SAFETY: signatures match native/c_api.h.
+        let (init, destroy, command, free_string, typed) = unsafe {
+            let symbol_error = |e: libloading::Error| {
+                format!("'{}' is missing C API symbols: {}", library_path, e)
+            };
+            (
+                *library
+                    .get::<InitFn>(b"motis_native_init\0")
+                    .map_err(symbol_error)?,
+                *library
+                    .get::<DestroyFn>(b"motis_native_destroy\0")
+                    .map_err(symbol_error)?,
+                *library
+                    .get::<CommandFn>(b"motis_native_command\0")
+                    .map_err(symbol_error)?,
+                *library
+                    .get::<FreeStringFn>(b"motis_native_free_string\0")
+                    .map_err(symbol_error)?,
+                TypedApi {
+                    plan_route: *library
+                        .get::<PlanRouteFn>(b"motis_native_plan_route\0")
+                        .map_err(symbol_error)?,
+                    free_routes: *library
+                        .get::<FreeRoutesFn>(b"motis_native_free_routes\0")
+                        .map_err(symbol_error)?,
+                    geocode: *library
+                        .get::<GeocodeFn>(b"motis_native_geocode\0")
+                        .map_err(symbol_error)?,
+                    free_locations: *library
+                        .get::<FreeLocationsFn>(b"motis_native_free_locations\0")
+                        .map_err(symbol_error)?,
+                    get_tile: *library
+                        .get::<GetTileFn>(b"motis_native_get_tile\0")
+                        .map_err(symbol_error)?,
+                    get_glyph: *library
+                        .get::<GetGlyphFn>(b"motis_native_get_glyph\0")
+                        .map_err(symbol_error)?,
+                    free_bytes: *library
+                        .get::<FreeBytesFn>(b"motis_native_free_bytes\0")
+                        .map_err(symbol_error)?,
+                    api_get: *library
+                        .get::<ApiGetFn>(b"motis_native_api_get\0")
+                        .map_err(symbol_error)?,
+                    set_trace_id: *library
+                        .get::<SetTraceIdFn>(b"motis_native_set_trace_id\0")
+                        .map_err(symbol_error)?,
+                },
+            )
+        };
+
+        let mut error: *mut c_char = std::ptr::null_mut();
+        // SAFETY: data_path_c outlives the call; error is an out-parameter
+        // owned by us afterwards.
+        let handle = unsafe { init(data_path_c.as_ptr(), &mut error) };
+        let error = unsafe { take_c_string(error, free_string) };
+        if handle.is_null() {
+            return Err(error.unwrap_or_else(|| "Failed to initialize MOTIS".to_string()));
+        }
+
+        Ok(Self {
+            handle,
+            destroy,
+            command,
+            free_string,
+            typed,
+            _library: library,
+        })
+    }
+
+    /// Takes the error message of a failed typed call.
+    fn typed_error(&self, error: *mut c_char, fallback: &str) -> String {
+        // SAFETY: error is NULL or was allocated by the C API for us.
+        unsafe { take_c_string(error, self.free_string) }.unwrap_or_else(|| fallback.to_string())
+    }
+
+    /// Trace id prefixed to MOTIS diagnostics written by the following typed
+    /// calls on this thread; `None` clears it.
+    pub(crate) fn set_trace_id(&mut self, trace_id: Option<&str>) {
+        // Trace ids are validated ASCII, so this only fails for foreign input.
+        let trace_c = trace_id.and_then(|id| CString::new(id).ok());
+        // SAFETY: the string outlives the call; the library copies it.
+        unsafe {
+            (self.typed.set_trace_id)(trace_c.as_ref().map_or(std::ptr::null(), |c| c.as_ptr()))
+        };
+    }
+
+    pub(crate) fn plan_route(
+        &mut self,
+        from: &LatLon,
+        to: &LatLon,
+        departure_time: Option<&str>,
+    ) -> Result<Vec<RouteResult>, String> {
+        let departure_c = departure_time
+            .map(CString::new)
+            .transpose()
+            .map_err(|_| "Departure time contains a NUL byte".to_string())?;
+        let mut out = CRoutes {
+            n_routes: 0,
+            routes: std::ptr::null(),
+            storage: std::ptr::null_mut(),
+        };
+        let mut error: *mut c_char = std::ptr::null_mut();
+        // SAFETY: handle is live until drop; `out` is released below with the
+        // matching free function after copying.
+        unsafe {
+            let status = (self.typed.plan_route)(
+                self.handle,
+                from.lat,
+                from.lon,
+                to.lat,
+                to.lon,
+                departure_c
+                    .as_ref()
+                    .map_or(std::ptr::null(), |c| c.as_ptr()),
+                &mut out,
+                &mut error,
+            );
+            if status != 0 {
+                return Err(self.typed_error(error, "Route planning failed"));
+            }
+            let routes = borrowed_slice(out.routes, out.n_routes)
+                .iter()
+                .map(|route| route_from_c(route))
+                .collect();
+            (self.typed.free_routes)(&mut out);
+            Ok(routes)
+        }
+    }
+
+    pub(crate) fn geocode(
+        &mut self,
+        query: &str,
+        options: &GeocodeOptions,
+    ) -> Result<Vec<LocationResult>, String> {
+        let query_c = CString::new(query).map_err(|_| "Query contains a NUL byte".to_string())?;
+        let type_c = options
+            .type_
+            .as_deref()
+            .map(CString::new)
+            .transpose()
+            .map_err(|_| "Geocode type contains a NUL byte".to_string())?;
+        let modes_c = c_strings(&options.modes, "Geocode mode")?;
+        let language_c = c_strings(&options.language, "Geocode language")?;
+        let modes_ptrs: Vec<*const c_char> = modes_c.iter().map(|m| m.as_ptr()).collect();
+        let language_ptrs: Vec<*const c_char> = language_c.iter().map(|l| l.as_ptr()).collect();
+        let c_options = CGeocodeOptions {
+            type_: type_c.as_ref().map_or(std::ptr::null(), |c| c.as_ptr()),
+            modes: modes_ptrs.as_ptr(),
+            n_modes: modes_ptrs.len(),
+            has_place: options.place.is_some() as c_int,
+            place_lat: options.place.as_ref().map_or(0.0, |p| p.lat),
+            place_lon: options.place.as_ref().map_or(0.0, |p| p.lon),
+            has_place_bias: options.place_bias.is_some() as c_int,
+            place_bias: options.place_bias.unwrap_or_default(),
+            language: language_ptrs.as_ptr(),
+            n_language: language_ptrs.len(),
+            limit: options.limit.unwrap_or(0),
+        };
+        let mut out = CLocations {
+            n_locations: 0,
+            locations: std::ptr::null(),
+            storage: std::ptr::null_mut(),
+        };
+        let mut error: *mut c_char = std::ptr::null_mut();
+        // SAFETY: every pointer in c_options borrows from locals that outlive
+        // the call; `out` is released with the matching free function.
+        unsafe {
+            let status = (self.typed.geocode)(
+                self.handle,
+                query_c.as_ptr(),
+                &c_options,
+                &mut out,
+                &mut error,
+            );
+            if status != 0 {
+                return Err(self.typed_error(error, "Geocoding failed"));
+            }
+            let locations = borrowed_slice(out.locations, out.n_locations)
+                .iter()
+                .map(|loc| location_from_c(loc))
+                .collect();
+            (self.typed.free_locations)(&mut out);
+            Ok(locations)
+        }
+    }
+
+    /// Copies bytes returned by get_tile/get_glyph (1 found, 0 not found).
+    fn take_bytes(
+        &self,
+        status: c_int,
+        data: *mut u8,
+        size: usize,
+        error: *mut c_char,
+    ) -> Result<Option<Vec<u8>>, String> {
+        match status {
+            0 => Ok(None),
+            1 => {
+                // SAFETY: on success data points to `size` bytes owned by us.
+                let bytes = unsafe { borrowed_slice(data, size) }.to_vec();
+                unsafe { (self.typed.free_bytes)(data) };
+                Ok(Some(bytes))
+            }
+            _ => Err(self.typed_error(error, "Native call failed")),
+        }
+    }
+
+    /// Raw MVT bytes of one tile, `None` if there is no tile.
+    pub(crate) fn get_tile(&mut self, z: i32, x: i32, y: i32) -> Result<Option<Vec<u8>>, String> {
+        let mut data: *mut u8 = std::ptr::null_mut();
+        let mut size = 0usize;
+        let mut error: *mut c_char = std::ptr::null_mut();
+        // SAFETY: handle is live until drop; out-parameters are ours.
+        let status = unsafe {
+            (self.typed.get_tile)(self.handle, z, x, y, &mut data, &mut size, &mut error)
+        };
+        self.take_bytes(status, data, size, error)
+    }
+
+    /// Raw PBF bytes of one glyph range, `None` if the font is unknown.
+    pub(crate) fn get_glyph(&mut self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        let path_c =
+            CString::new(path).map_err(|_| "Glyph path contains a NUL byte".to_string())?;
+        let mut data: *mut u8 = std::ptr::null_mut();
+        let mut size = 0usize;
+        let mut error: *mut c_char = std::ptr::null_mut();
+        // SAFETY: handle is live until drop; out-parameters are ours.
+        let status = unsafe {
+            (self.typed.get_glyph)(
+                self.handle,
+                path_c.as_ptr(),
+                &mut data,
+                &mut size,
+                &mut error,
+            )
+        };
+        self.take_bytes(status, data, size, error)
+    }
+
+    /// JSON payload of a supported MOTIS GET endpoint, as returned by MOTIS.
+    pub(crate) fn api_get(&mut self, path_and_query: &str) -> Result<String, String> {
+        let path_c =
+            CString::new(path_and_query).map_err(|_| "Path contains a NUL byte".to_string())?;
+        let mut error: *mut c_char = std::ptr::null_mut();
+        // SAFETY: handle is live until drop; the payload is owned by us.
+        unsafe {
+            let payload = (self.typed.api_get)(self.handle, path_c.as_ptr(), &mut error);
+            match take_c_string(payload, self.free_string) {
+                Some(payload) => Ok(payload),
+                None => Err(self.typed_error(error, "Native call failed")),
+            }
+        }
+    }
+
+    pub(crate) fn send_command(&mut self, cmd: &str) -> Result<String, String> {
+        let cmd_c = CString::new(cmd).map_err(|_| "Command contains a NUL byte".to_string())?;
+        // SAFETY: handle is live until drop; the response is owned by us.
+        unsafe {
+            let response = (self.command)(self.handle, cmd_c.as_ptr());
+            take_c_string(response, self.free_string)
+                .ok_or_else(|| "Embedded backend returned no response".to_string())
+        }
+    }
+}
+
+impl Drop for EmbeddedBackend {
+    fn drop(&mut self) {
+        // SAFETY: handle came from motis_native_init and is released once.
+        unsafe { (self.destroy)(self.handle) };
+    }
+}
+
+/// `MOTIS_EMBEDDED_LIB`, otherwise the library next to motis-ipc.
+pub(crate) fn find_embedded_library(exe_path: &str) -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("MOTIS_EMBEDDED_LIB") {
+        return Some(PathBuf::from(path));
+    }
+    let candidate = Path::new(exe_path).parent()?.join(EMBEDDED_LIBRARY_NAME);
+    candidate.exists().then_some(candidate)
+}
+
+/// `MOTIS_BACKEND=embedded` opts into the in-process backend. IPC remains the
+/// default because a crash in MOTIS then only takes down motis-ipc.
+pub(crate) fn embedded_requested() -> bool {
+    std::env::var("MOTIS_BACKEND")
+        .map(|v| v.eq_ignore_ascii_case("embedded"))
+        .unwrap_or(false)
+}
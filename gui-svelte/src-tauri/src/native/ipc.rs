@@ -1,23 +1,36 @@
 #[path = "ipc_paths.rs"]
 pub(crate) mod paths;
+#[path = "ipc_record.rs"]
+pub(crate) mod record;
 
+use crate::logging::{self, IPC};
+use crate::metrics::{self, IpcPhase};
 use once_cell::sync::Lazy;
 #[cfg(unix)]
 use std::io::ErrorKind;
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::path::Path;
-use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::sync::Mutex;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use super::config::{read_config, IssueSeverity};
+use super::embedded::{embedded_requested, find_embedded_library, EmbeddedBackend};
 use self::paths::{
     copy_to_tmp_and_make_executable, ensure_executable, validate_data_directory,
-    validate_ipc_executable_path,
+    validate_ipc_executable_path, verify_data_manifest, ManifestVerifyMode,
 };
+use self::record::{ReplayBackend, SessionRecorder};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BackendMode {
+    /// motis-ipc subprocess (crash-isolated, auto-recovering).
     Ipc,
+    /// MOTIS loaded in-process through the C ABI (`MOTIS_BACKEND=embedded`).
+    Embedded,
+    /// Recorded responses from a JSONL session file (`MOTIS_IPC_REPLAY`).
+    Replay,
 }
 
 #[derive(Debug, Clone)]
@@ -27,23 +40,53 @@ struct IpcLaunchConfig {
 }
 
 pub(crate) const MAX_IPC_COMMAND_BYTES: usize = 64 * 1024;
+pub(crate) const MAX_IPC_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
 const IPC_RECOVERY_MAX_ATTEMPTS: usize = 2;
 const IPC_RECOVERY_DELAYS_MS: [u64; IPC_RECOVERY_MAX_ATTEMPTS] = [250, 1000];
 
 static BACKEND_MODE: Lazy<Mutex<BackendMode>> = Lazy::new(|| Mutex::new(BackendMode::Ipc));
 static IPC_PROCESS: Lazy<Mutex<Option<IpcBackend>>> = Lazy::new(|| Mutex::new(None));
+static EMBEDDED_BACKEND: Lazy<Mutex<Option<EmbeddedBackend>>> = Lazy::new(|| Mutex::new(None));
+static REPLAY_BACKEND: Lazy<Mutex<Option<ReplayBackend>>> = Lazy::new(|| Mutex::new(None));
+static SESSION_RECORDER: Lazy<Mutex<Option<SessionRecorder>>> = Lazy::new(|| {
+    let recorder = std::env::var("MOTIS_IPC_RECORD")
+        .ok()
+        .and_then(|path| match SessionRecorder::create(&path) {
+            Ok(recorder) => {
+                logging::info!(IPC, "Recording IPC session to {}", path);
+                Some(recorder)
+            }
+            Err(e) => {
+                logging::warning!(IPC, "Cannot record IPC session to {}: {}", path, e);
+                None
+            }
+        });
+    Mutex::new(recorder)
+});
 static IPC_LAUNCH_CONFIG: Lazy<Mutex<Option<IpcLaunchConfig>>> = Lazy::new(|| Mutex::new(None));
 static STARTUP_DIAGNOSTICS: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+/// No timeout unless configured: the first commands wait for motis-ipc to
+/// finish loading the dataset, which has no upper bound on slow USB storage.
+static IPC_RESPONSE_TIMEOUT: Lazy<Mutex<Option<Duration>>> = Lazy::new(|| {
+    let timeout = std::env::var("MOTIS_IPC_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs);
+    Mutex::new(timeout)
+});
 
 struct IpcBackend {
     child: Child,
     stdin: ChildStdin,
-    stdout: BufReader<ChildStdout>,
+    /// Lines read by a dedicated thread so a stalled motis-ipc can time out.
+    /// An empty line means stdout was closed.
+    responses: Receiver<io::Result<String>>,
 }
 
 fn remember_startup_diagnostics(message: impl Into<String>) {
     let message = message.into();
-    eprintln!("[MOTIS-GUI] startup-diagnostics: {}", message);
+    logging::warning!(IPC, "startup-diagnostics: {}", message);
     if let Ok(mut guard) = STARTUP_DIAGNOSTICS.lock() {
         *guard = Some(message);
     }
@@ -62,6 +105,81 @@ pub fn get_startup_diagnostics() -> Option<String> {
         .and_then(|guard| guard.clone())
 }
 
+/// Per-command response timeout (`MOTIS_IPC_TIMEOUT_SECS`); `None` waits
+/// for motis-ipc indefinitely, which is the default.
+pub fn set_ipc_response_timeout(timeout: Option<Duration>) {
+    if let Ok(mut guard) = IPC_RESPONSE_TIMEOUT.lock() {
+        *guard = timeout;
+    }
+}
+
+fn ipc_response_timeout() -> Option<Duration> {
+    IPC_RESPONSE_TIMEOUT.lock().ok().and_then(|guard| *guard)
+}
+
+/// Reads one newline-terminated response, refusing to buffer more than
+/// `limit` bytes. Returns an empty string at end of stream.
+pub(crate) fn read_response_line(reader: &mut impl BufRead, limit: usize) -> io::Result<String> {
+    let mut line = String::new();
+    reader.take(limit as u64 + 1).read_line(&mut line)?;
+    if line.len() > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("motis-ipc response exceeds {} bytes", limit),
+        ));
+    }
+    Ok(line)
+}
+
+fn spawn_response_reader(stdout: ChildStdout) -> Receiver<io::Result<String>> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(stdout);
+        loop {
+            let line = read_response_line(&mut reader, MAX_IPC_RESPONSE_BYTES);
+            let done = !matches!(&line, Ok(l) if !l.is_empty());
+            if tx.send(line).is_err() || done {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Calls `f` with each non-empty line of motis-ipc's stderr until it is
+/// closed. Invalid UTF-8 is replaced rather than dropping the line.
+pub(crate) fn for_each_stderr_line(reader: impl Read, mut f: impl FnMut(&str)) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let text = String::from_utf8_lossy(&line);
+                let text = text.trim_end();
+                if !text.is_empty() {
+                    f(text);
+                }
+            }
+        }
+    }
+}
+
+/// Forwards motis-ipc diagnostics to the GUI log (target `ipc`); lines that
+/// mention an error are logged as warnings.
+fn spawn_stderr_forwarder(stderr: ChildStderr, pid: u32) {
+    std::thread::spawn(move || {
+        for_each_stderr_line(stderr, |line| {
+            if line.to_ascii_lowercase().contains("error") {
+                logging::warning!(IPC, "motis-ipc[{}]: {}", pid, line);
+            } else {
+                logging::info!(IPC, "motis-ipc[{}]: {}", pid, line);
+            }
+        });
+    });
+}
+
 pub(crate) fn validate_ipc_command(cmd: &str) -> io::Result<()> {
     if cmd.contains('\n') || cmd.contains('\r') {
         return Err(io::Error::new(
@@ -96,9 +214,24 @@ impl IpcBackend {
         writeln!(self.stdin, "{}", cmd)?;
         self.stdin.flush()?;
 
-        let mut line = String::new();
-        let bytes_read = self.stdout.read_line(&mut line)?;
-        if bytes_read == 0 {
+        let received = match ipc_response_timeout() {
+            Some(timeout) => self.responses.recv_timeout(timeout).map_err(|e| (e, timeout)),
+            None => self
+                .responses
+                .recv()
+                .map_err(|_| (RecvTimeoutError::Disconnected, Duration::ZERO)),
+        };
+        let line = match received {
+            Ok(line) => line?,
+            Err((RecvTimeoutError::Timeout, timeout)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("motis-ipc timed out after {} ms", timeout.as_millis()),
+                ));
+            }
+            Err((RecvTimeoutError::Disconnected, _)) => String::new(),
+        };
+        if line.is_empty() {
             if let Some(status) = self.child.try_wait()? {
                 return Err(io::Error::new(
                     io::ErrorKind::BrokenPipe,
@@ -116,22 +249,23 @@ impl IpcBackend {
     fn terminate(&mut self, reason: &str) {
         match self.child.try_wait() {
             Ok(Some(status)) => {
-                eprintln!("[MOTIS-GUI] motis-ipc already exited ({reason}): {status}");
+                logging::info!(IPC, "motis-ipc already exited ({reason}): {status}");
             }
             Ok(None) => {
-                eprintln!(
-                    "[MOTIS-GUI] Stopping motis-ipc PID {} ({reason})",
+                logging::info!(
+                    IPC,
+                    "Stopping motis-ipc PID {} ({reason})",
                     self.child.id()
                 );
                 if let Err(err) = self.child.kill() {
-                    eprintln!("[MOTIS-GUI] Failed to kill motis-ipc: {}", err);
+                    logging::warning!(IPC, "Failed to kill motis-ipc: {}", err);
                 }
                 if let Err(err) = self.child.wait() {
-                    eprintln!("[MOTIS-GUI] Failed waiting for motis-ipc exit: {}", err);
+                    logging::warning!(IPC, "Failed waiting for motis-ipc exit: {}", err);
                 }
             }
             Err(err) => {
-                eprintln!("[MOTIS-GUI] Failed to query motis-ipc state: {}", err);
+                logging::warning!(IPC, "Failed to query motis-ipc state: {}", err);
             }
         }
     }
@@ -148,14 +282,14 @@ fn spawn_ipc_backend(
     data_path: &str,
 ) -> Result<IpcBackend, Box<dyn std::error::Error>> {
     let actual_exe_path = ensure_executable(exe_path)?;
-    eprintln!("[MOTIS-GUI] Using executable: {}", actual_exe_path);
+    logging::info!(IPC, "Using executable: {}", actual_exe_path);
 
     let spawn = |binary: &str| {
         let mut cmd = Command::new(binary);
         cmd.arg(data_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::inherit());
+            .stderr(Stdio::piped());
         cmd.spawn()
     };
 
@@ -163,12 +297,13 @@ fn spawn_ipc_backend(
         Ok(child) => child,
         #[cfg(unix)]
         Err(err) if err.kind() == ErrorKind::PermissionDenied => {
-            eprintln!(
-                "[MOTIS-GUI] Spawn failed due to permission/noexec mount, retrying from /tmp: {}",
+            logging::warning!(
+                IPC,
+                "Spawn failed due to permission/noexec mount, retrying from /tmp: {}",
                 err
             );
             let tmp_exe = copy_to_tmp_and_make_executable(exe_path)?;
-            eprintln!("[MOTIS-GUI] Retrying spawn with executable: {}", tmp_exe);
+            logging::info!(IPC, "Retrying spawn with executable: {}", tmp_exe);
             spawn(&tmp_exe).map_err(|e| format!("Failed to spawn motis-ipc from /tmp: {}", e))?
         }
         Err(err) => {
@@ -178,7 +313,11 @@ fn spawn_ipc_backend(
         }
     };
 
-    eprintln!("[MOTIS-GUI] motis-ipc process spawned, PID: {}", child.id());
+    logging::info!(IPC, "motis-ipc process spawned, PID: {}", child.id());
+    // Started before the liveness check so a crash at startup is logged too.
+    if let Some(stderr) = child.stderr.take() {
+        spawn_stderr_forwarder(stderr, child.id());
+    }
 
     std::thread::sleep(Duration::from_millis(150));
     if let Some(status) = child.try_wait()? {
@@ -195,7 +334,7 @@ fn spawn_ipc_backend(
     Ok(IpcBackend {
         child,
         stdin,
-        stdout: BufReader::new(stdout),
+        responses: spawn_response_reader(stdout),
     })
 }
 
@@ -211,21 +350,22 @@ fn replace_ipc_backend(backend: IpcBackend, reason: &str) -> Result<(), Box<dyn
 pub(crate) fn recover_ipc_backend(reason: &str) -> Result<bool, Box<dyn std::error::Error>> {
     let launch = IPC_LAUNCH_CONFIG.lock()?.clone();
     let Some(launch) = launch else {
-        eprintln!("[MOTIS-GUI] IPC recovery skipped (no launch config): {reason}");
+        logging::warning!(IPC, "IPC recovery skipped (no launch config): {reason}");
         return Ok(false);
     };
 
-    eprintln!("[MOTIS-GUI] IPC recovery started: {reason}");
+    logging::info!(IPC, "IPC recovery started: {reason}");
     for attempt in 1..=IPC_RECOVERY_MAX_ATTEMPTS {
         match spawn_ipc_backend(&launch.exe_path, &launch.data_path) {
             Ok(backend) => {
                 replace_ipc_backend(backend, "recovery-replace")?;
-                eprintln!("[MOTIS-GUI] IPC recovery succeeded on attempt {}", attempt);
+                logging::info!(IPC, "IPC recovery succeeded on attempt {}", attempt);
                 return Ok(true);
             }
             Err(err) => {
-                eprintln!(
-                    "[MOTIS-GUI] IPC recovery attempt {}/{} failed: {}",
+                logging::warning!(
+                    IPC,
+                    "IPC recovery attempt {}/{} failed: {}",
                     attempt, IPC_RECOVERY_MAX_ATTEMPTS, err
                 );
                 if attempt < IPC_RECOVERY_MAX_ATTEMPTS {
@@ -235,28 +375,68 @@ pub(crate) fn recover_ipc_backend(reason: &str) -> Result<bool, Box<dyn std::err
         }
     }
 
-    eprintln!(
-        "[MOTIS-GUI] IPC recovery failed after {} attempts",
+    logging::error!(
+        IPC,
+        "IPC recovery failed after {} attempts",
         IPC_RECOVERY_MAX_ATTEMPTS
     );
     Ok(false)
 }
 
-fn send_ipc_command_with_recovery(cmd: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// motis-ipc echoes the command's `trace_id`. Another id means the line
+/// answers an earlier command (e.g. one that timed out), so every later
+/// response would be shifted as well.
+fn check_echoed_trace(line: &str, expected: Option<&str>) -> io::Result<()> {
+    #[derive(serde::Deserialize)]
+    struct Echo {
+        #[serde(default)]
+        trace_id: Option<String>,
+    }
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    // Unparseable lines are reported as invalid JSON by the caller.
+    let echoed = serde_json::from_str::<Echo>(line).ok().and_then(|e| e.trace_id);
+    match echoed {
+        Some(echoed) if echoed != expected => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "motis-ipc answered trace {} while {} was expected; IPC stream out of sync",
+                echoed, expected
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Sends `cmd` and restarts motis-ipc on a failed, timed-out or
+/// out-of-sync reply before trying again.
+fn send_ipc_command_with_recovery(
+    cmd: &str,
+    trace_id: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
     let total_attempts = IPC_RECOVERY_MAX_ATTEMPTS + 1;
 
     for attempt in 1..=total_attempts {
         let response = {
+            let waiting = Instant::now();
             let mut guard = IPC_PROCESS.lock()?;
+            metrics::observe_ipc(IpcPhase::QueueWait, waiting.elapsed());
             let backend = guard.as_mut().ok_or("IPC not initialized")?;
-            backend.send_command(cmd)
+            let started = Instant::now();
+            let response = backend
+                .send_command(cmd)
+                .and_then(|line| check_echoed_trace(&line, trace_id).map(|()| line));
+            metrics::observe_ipc(IpcPhase::Backend, started.elapsed());
+            response
         };
 
         match response {
             Ok(line) => return Ok(line),
             Err(err) => {
-                eprintln!(
-                    "[MOTIS-GUI] IPC command attempt {}/{} failed: {}",
+                logging::warning!(
+                    IPC,
+                    "IPC command attempt {}/{} failed: {}",
                     attempt, total_attempts, err
                 );
                 if attempt == total_attempts {
@@ -275,12 +455,153 @@ fn send_ipc_command_with_recovery(cmd: &str) -> Result<String, Box<dyn std::erro
     Err("IPC command failed with unknown recovery state".into())
 }
 
+fn send_embedded_command(cmd: &str) -> Result<String, Box<dyn std::error::Error>> {
+    validate_ipc_command(cmd)?;
+    let waiting = Instant::now();
+    let mut guard = EMBEDDED_BACKEND.lock()?;
+    metrics::observe_ipc(IpcPhase::QueueWait, waiting.elapsed());
+    let backend = guard.as_mut().ok_or("Embedded backend not initialized")?;
+    let started = Instant::now();
+    let response = backend.send_command(cmd);
+    metrics::observe_ipc(IpcPhase::Backend, started.elapsed());
+    Ok(response?)
+}
+
+/// Runs `call` against the typed entry points of the embedded backend.
+/// Returns `None` for the other backends and while a session is recorded
+/// (recordings hold JSON exchanges); callers then send a JSON command.
+pub(crate) fn with_typed_embedded<T>(
+    call: impl FnOnce(&mut EmbeddedBackend) -> Result<T, String>,
+) -> Option<Result<T, Box<dyn std::error::Error>>> {
+    if get_mode() != BackendMode::Embedded || recording_active() {
+        return None;
+    }
+    let waiting = Instant::now();
+    let mut guard = match EMBEDDED_BACKEND.lock() {
+        Ok(guard) => guard,
+        Err(e) => return Some(Err(e.to_string().into())),
+    };
+    metrics::observe_ipc(IpcPhase::QueueWait, waiting.elapsed());
+    let Some(backend) = guard.as_mut() else {
+        return Some(Err("Embedded backend not initialized".into()));
+    };
+    let started = Instant::now();
+    backend.set_trace_id(logging::current_trace_id().as_deref());
+    let result = call(backend);
+    backend.set_trace_id(None);
+    metrics::observe_ipc(IpcPhase::Backend, started.elapsed());
+    Some(result.map_err(Into::into))
+}
+
+fn send_replay_command(cmd: &str) -> Result<String, Box<dyn std::error::Error>> {
+    validate_ipc_command(cmd)?;
+    let mut guard = REPLAY_BACKEND.lock()?;
+    let backend = guard.as_mut().ok_or("Replay backend not initialized")?;
+    Ok(backend.send_command(cmd))
+}
+
+fn recording_active() -> bool {
+    SESSION_RECORDER
+        .lock()
+        .map(|guard| guard.is_some())
+        .unwrap_or(false)
+}
+
+fn record_exchange(cmd: &str, response: &str) {
+    let Ok(mut guard) = SESSION_RECORDER.lock() else {
+        return;
+    };
+    if let Some(recorder) = guard.as_mut() {
+        if let Err(e) = recorder.record(cmd, response) {
+            logging::warning!(IPC, "IPC recording stopped: {}", e);
+            *guard = None;
+        }
+    }
+}
+
+/// Appends every command/response pair to `path` (JSONL) from now on.
+/// Also enabled at startup with `MOTIS_IPC_RECORD=<path>`.
+pub fn start_recording(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let recorder = SessionRecorder::create(path)
+        .map_err(|e| format!("Cannot record IPC session to {}: {}", path, e))?;
+    *SESSION_RECORDER.lock()? = Some(recorder);
+    logging::info!(IPC, "Recording IPC session to {}", path);
+    Ok(())
+}
+
+pub fn stop_recording() {
+    if let Ok(mut guard) = SESSION_RECORDER.lock() {
+        *guard = None;
+    }
+}
+
+/// Serves responses from a session recorded with `start_recording` instead
+/// of starting MOTIS. Used for protocol/UI regression tests without a dataset.
+pub fn init_replay(recording_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let backend = ReplayBackend::load(recording_path).map_err(|e| {
+        let message = format!(
+            "Failed to load IPC recording: {}. Next action: check MOTIS_IPC_REPLAY.",
+            e
+        );
+        remember_startup_diagnostics(message.clone());
+        message
+    })?;
+
+    destroy();
+    *REPLAY_BACKEND.lock()? = Some(backend);
+    *BACKEND_MODE.lock()? = BackendMode::Replay;
+
+    clear_startup_diagnostics();
+    logging::info!(IPC, "Replay backend initialized from {}", recording_path);
+    Ok(())
+}
+
+fn replay_requested() -> Option<String> {
+    std::env::var("MOTIS_IPC_REPLAY")
+        .ok()
+        .filter(|path| !path.is_empty())
+}
+
+/// Adds `trace_id` to a JSON command; motis-ipc echoes it in the response.
+pub(crate) fn with_trace_id(cmd: &str, trace_id: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(cmd) {
+        Ok(serde_json::Value::Object(mut fields)) => {
+            fields.insert("trace_id".to_string(), trace_id.into());
+            serde_json::Value::Object(fields).to_string()
+        }
+        _ => cmd.to_string(),
+    }
+}
+
 pub(crate) fn send_ipc_json_command(
     cmd: &str,
 ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-    let response = send_ipc_command_with_recovery(cmd)?;
+    // Recordings keep the command without the trace id so replays still
+    // match.
+    let mode = get_mode();
+    let sent_trace = logging::current_trace_id().filter(|_| mode != BackendMode::Replay);
+    let traced_cmd = sent_trace.as_deref().map(|id| with_trace_id(cmd, id));
+    let wire_cmd = traced_cmd.as_deref().unwrap_or(cmd);
+
+    let started = Instant::now();
+    let response = match mode {
+        BackendMode::Ipc => send_ipc_command_with_recovery(wire_cmd, sent_trace.as_deref())?,
+        BackendMode::Embedded => send_embedded_command(wire_cmd)?,
+        BackendMode::Replay => send_replay_command(cmd)?,
+    };
+    let round_trip = started.elapsed();
+    record_exchange(cmd, &response);
+    let started = Instant::now();
     let result: serde_json::Value = serde_json::from_str(&response)
         .map_err(|e| format!("Invalid IPC JSON response: {} (raw: {})", e, response.trim()))?;
+    let decode = started.elapsed();
+    metrics::observe_ipc(IpcPhase::Decode, decode);
+    logging::debug!(
+        IPC,
+        "Backend answered in {} ms, decoded in {} ms",
+        round_trip.as_millis(),
+        decode.as_millis()
+    );
 
     if result["status"] == "ok" {
         Ok(result["data"].clone())
@@ -290,10 +611,59 @@ pub(crate) fn send_ipc_json_command(
     }
 }
 
+fn verify_data_before_spawn(data_path: &str) -> Result<(), String> {
+    let Some(mode) = ManifestVerifyMode::from_env() else {
+        logging::info!(IPC, "Data verification disabled (MOTIS_VERIFY_DATA=off)");
+        return Ok(());
+    };
+
+    let report = verify_data_manifest(data_path, mode)?;
+    if !report.manifest_found {
+        logging::info!(IPC, "No data manifest found, skipping verification");
+        return Ok(());
+    }
+    if !report.is_ok() {
+        return Err(report.describe_damage(data_path));
+    }
+    logging::info!(
+        IPC,
+        "Data verification ({:?}) passed for {} file(s)",
+        mode, report.checked_files
+    );
+    Ok(())
+}
+
+/// motis-ipc reports bad configs only as an opaque init failure, so point at
+/// the offending line up front. Missing GTFS/OSM sources are expected on a
+/// portable bundle and stay silent.
+fn log_config_errors(data_path: &str) {
+    let document = match read_config(data_path) {
+        Ok(document) => document,
+        Err(e) => {
+            logging::warning!(IPC, "{}", e);
+            return;
+        }
+    };
+    for issue in document
+        .issues
+        .iter()
+        .filter(|i| i.severity == IssueSeverity::Error)
+    {
+        match issue.line {
+            Some(line) => logging::warning!(IPC, "config.yml:{}: {}", line, issue.message),
+            None => logging::warning!(IPC, "config.yml: {}", issue.message),
+        }
+    }
+}
+
 pub fn init_ipc(exe_path: &str, data_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    eprintln!("[MOTIS-GUI] Starting motis-ipc...");
-    eprintln!("[MOTIS-GUI] Original exe path: {}", exe_path);
-    eprintln!("[MOTIS-GUI] Data path: {}", data_path);
+    logging::info!(IPC, "Starting motis-ipc...");
+    logging::debug!(IPC, "Original exe path: {}", exe_path);
+    logging::info!(IPC, "Data path: {}", data_path);
+
+    if let Some(recording) = replay_requested() {
+        return init_replay(&recording);
+    }
 
     if let Err(message) = validate_ipc_executable_path(exe_path) {
         remember_startup_diagnostics(message.clone());
@@ -305,6 +675,29 @@ pub fn init_ipc(exe_path: &str, data_path: &str) -> Result<(), Box<dyn std::erro
         return Err(message.into());
     }
 
+    if let Err(message) = verify_data_before_spawn(data_path) {
+        remember_startup_diagnostics(message.clone());
+        return Err(message.into());
+    }
+    log_config_errors(data_path);
+
+    if embedded_requested() {
+        match find_embedded_library(exe_path) {
+            Some(library) => match start_embedded_backend(&library.to_string_lossy(), data_path) {
+                Ok(()) => return Ok(()),
+                Err(e) => logging::warning!(
+                    IPC,
+                    "Embedded backend unavailable, falling back to motis-ipc: {}",
+                    e
+                ),
+            },
+            None => logging::warning!(
+                IPC,
+                "MOTIS_BACKEND=embedded but no embedded library found, using motis-ipc"
+            ),
+        }
+    }
+
     let backend = match spawn_ipc_backend(exe_path, data_path) {
         Ok(backend) => backend,
         Err(e) => {
@@ -326,11 +719,30 @@ pub fn init_ipc(exe_path: &str, data_path: &str) -> Result<(), Box<dyn std::erro
         });
     }
 
+    if let Ok(mut guard) = EMBEDDED_BACKEND.lock() {
+        *guard = None;
+    }
+
     let mut mode_guard = BACKEND_MODE.lock()?;
     *mode_guard = BackendMode::Ipc;
 
     clear_startup_diagnostics();
-    eprintln!("[MOTIS-GUI] IPC backend initialized (data loading in progress...)");
+    logging::info!(IPC, "IPC backend initialized (data loading in progress...)");
+    Ok(())
+}
+
+fn start_embedded_backend(library_path: &str, data_path: &str) -> Result<(), String> {
+    logging::info!(IPC, "Loading embedded backend: {}", library_path);
+    // Loads the full dataset synchronously, unlike motis-ipc which loads in
+    // the background after spawn.
+    let backend = EmbeddedBackend::load(library_path, data_path)?;
+
+    destroy();
+    *EMBEDDED_BACKEND.lock().map_err(|e| e.to_string())? = Some(backend);
+    *BACKEND_MODE.lock().map_err(|e| e.to_string())? = BackendMode::Embedded;
+
+    clear_startup_diagnostics();
+    logging::info!(IPC, "Embedded backend initialized");
     Ok(())
 }
 
@@ -339,7 +751,7 @@ pub fn get_mode() -> BackendMode {
 }
 
 pub fn destroy() {
-    eprintln!("[MOTIS-GUI] destroy() called");
+    logging::debug!(IPC, "destroy() called");
 
     if let Ok(mut guard) = IPC_PROCESS.lock() {
         if let Some(mut backend) = guard.take() {
@@ -349,19 +761,33 @@ pub fn destroy() {
 
     if let Ok(mut cfg) = IPC_LAUNCH_CONFIG.lock() {
         if cfg.is_some() {
-            eprintln!("[MOTIS-GUI] Clearing IPC launch config");
+            logging::debug!(IPC, "Clearing IPC launch config");
         }
         *cfg = None;
     }
+
+    if let Ok(mut guard) = EMBEDDED_BACKEND.lock() {
+        if guard.take().is_some() {
+            logging::info!(IPC, "Embedded backend released");
+        }
+    }
+
+    if let Ok(mut guard) = REPLAY_BACKEND.lock() {
+        *guard = None;
+    }
+
+    if let Ok(mut mode) = BACKEND_MODE.lock() {
+        *mode = BackendMode::Ipc;
+    }
 }
 
 pub async fn auto_init(
     exe_path: Option<&str>,
     data_path: Option<&str>,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    eprintln!("[MOTIS-GUI] auto_init() called");
-    eprintln!("[MOTIS-GUI] exe_path: {:?}", exe_path);
-    eprintln!("[MOTIS-GUI] data_path: {:?}", data_path);
+    logging::debug!(IPC, "auto_init() called");
+    logging::debug!(IPC, "exe_path: {:?}", exe_path);
+    logging::debug!(IPC, "data_path: {:?}", data_path);
 
     let exe = match exe_path {
         Some(path) => path,
@@ -380,11 +806,12 @@ pub async fn auto_init(
         }
     };
 
-    eprintln!("[MOTIS-GUI] Checking if files exist...");
+    logging::debug!(IPC, "Checking if files exist...");
     let exe_exists = Path::new(exe).exists();
     let data_exists = Path::new(data).exists();
-    eprintln!(
-        "[MOTIS-GUI] exe exists: {}, data exists: {}",
+    logging::debug!(
+        IPC,
+        "exe exists: {}, data exists: {}",
         exe_exists, data_exists
     );
 
@@ -406,26 +833,37 @@ pub async fn auto_init(
         return Err(message.into());
     }
 
-    eprintln!("[MOTIS-GUI] Attempting IPC initialization...");
+    logging::debug!(IPC, "Attempting IPC initialization...");
     match init_ipc(exe, data) {
         Ok(()) => {
-            eprintln!("[MOTIS-GUI] IPC mode initialized successfully");
+            logging::info!(IPC, "IPC mode initialized successfully");
             Ok("IPC mode initialized".to_string())
         }
         Err(e) => {
             let message = format!("Initialization failed: {}", e);
-            eprintln!("[MOTIS-GUI] IPC init failed: {}", message);
+            logging::error!(IPC, "IPC init failed: {}", message);
             remember_startup_diagnostics(message.clone());
             Err(message.into())
         }
     }
 }
 
+/// True when either backend (motis-ipc or embedded) is ready for commands.
 pub fn is_ipc_initialized() -> bool {
-    IPC_PROCESS
+    let embedded = EMBEDDED_BACKEND
         .lock()
         .map(|guard| guard.is_some())
-        .unwrap_or(false)
+        .unwrap_or(false);
+    let replay = REPLAY_BACKEND
+        .lock()
+        .map(|guard| guard.is_some())
+        .unwrap_or(false);
+    embedded
+        || replay
+        || IPC_PROCESS
+            .lock()
+            .map(|guard| guard.is_some())
+            .unwrap_or(false)
 }
 
 pub fn try_auto_init() -> bool {
@@ -433,7 +871,11 @@ pub fn try_auto_init() -> bool {
         return true;
     }
 
-    eprintln!("[MOTIS-GUI] Trying auto-initialization...");
+    logging::debug!(IPC, "Trying auto-initialization...");
+
+    if let Some(recording) = replay_requested() {
+        return init_replay(&recording).is_ok();
+    }
 
     let ipc_path = std::env::var("MOTIS_IPC_PATH").ok();
     let data_path = std::env::var("MOTIS_DATA_PATH").ok().or_else(|| {
@@ -447,17 +889,17 @@ pub fn try_auto_init() -> bool {
     });
 
     if let (Some(ipc), Some(data)) = (&ipc_path, &data_path) {
-        eprintln!("[MOTIS-GUI] Auto-init with IPC: {}, Data: {}", ipc, data);
+        logging::info!(IPC, "Auto-init with IPC: {}, Data: {}", ipc, data);
         if let Err(e) = init_ipc(ipc, data) {
             let message = format!(
                 "Auto-initialization failed with MOTIS_IPC_PATH='{}' and MOTIS_DATA_PATH='{}': {}",
                 ipc, data, e
             );
             remember_startup_diagnostics(message.clone());
-            eprintln!("[MOTIS-GUI] Auto-init failed: {}", message);
+            logging::warning!(IPC, "Auto-init failed: {}", message);
             return false;
         }
-        eprintln!("[MOTIS-GUI] Auto-init succeeded!");
+        logging::info!(IPC, "Auto-init succeeded!");
         clear_startup_diagnostics();
         return true;
     }
@@ -467,8 +909,9 @@ pub fn try_auto_init() -> bool {
         ipc_path, data_path
     );
     remember_startup_diagnostics(message);
-    eprintln!(
-        "[MOTIS-GUI] Auto-init: missing paths. IPC: {:?}, Data: {:?}",
+    logging::warning!(
+        IPC,
+        "Auto-init: missing paths. IPC: {:?}, Data: {:?}",
         ipc_path, data_path
     );
     false
@@ -1,8 +1,15 @@
-use std::path::{Path, PathBuf};
+use crate::logging::{self, IPC};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
 #[cfg(unix)]
 use std::io::ErrorKind;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Written by `motis-import.sh` next to the imported binaries.
+pub(crate) const DATA_MANIFEST_FILE: &str = "manifest.txt";
+const DATA_MANIFEST_HEADER: &str = "# motis-data-manifest v1";
+
 #[cfg(unix)]
 pub(crate) fn copy_to_tmp_and_make_executable(
     exe_path: &str,
@@ -17,7 +24,7 @@ pub(crate) fn copy_to_tmp_and_make_executable(
         .ok_or("Invalid exe name")?;
     let tmp_path = tmp_dir.join(exe_name);
 
-    eprintln!("[MOTIS-GUI] Copying executable to /tmp: {:?}", tmp_path);
+    logging::debug!(IPC, "Copying executable to /tmp: {:?}", tmp_path);
     std::fs::copy(exe_path, &tmp_path)?;
 
     use std::os::unix::fs::PermissionsExt;
@@ -75,8 +82,9 @@ pub(crate) fn ensure_executable(exe_path: &str) -> Result<String, Box<dyn std::e
         if mode & 0o111 != 0 {
             return Ok(exe_path.to_string());
         }
-        eprintln!(
-            "[MOTIS-GUI] Executable bit missing for {}, using /tmp copy",
+        logging::warning!(
+            IPC,
+            "Executable bit missing for {}, using /tmp copy",
             exe_path
         );
         return copy_to_tmp_and_make_executable(exe_path);
@@ -128,3 +136,191 @@ pub(crate) fn validate_data_directory(data_path: &str) -> Result<(), String> {
     }
     Ok(())
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestVerifyMode {
+    /// Existence and size checks only (fast enough for every launch).
+    Quick,
+    /// Quick checks plus SHA-256 of every file.
+    Full,
+}
+
+impl ManifestVerifyMode {
+    /// Reads `MOTIS_VERIFY_DATA` (`quick`, `full`, `off`). Defaults to quick.
+    pub(crate) fn from_env() -> Option<Self> {
+        match std::env::var("MOTIS_VERIFY_DATA")
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "off" | "0" | "false" => None,
+            "full" => Some(Self::Full),
+            _ => Some(Self::Quick),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct DamagedFile {
+    pub path: String,
+    pub problem: String,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ManifestReport {
+    pub manifest_found: bool,
+    pub checked_files: usize,
+    pub damaged: Vec<DamagedFile>,
+}
+
+impl ManifestReport {
+    pub fn is_ok(&self) -> bool {
+        self.damaged.is_empty()
+    }
+
+    pub(crate) fn describe_damage(&self, data_path: &str) -> String {
+        let files = self
+            .damaged
+            .iter()
+            .map(|f| format!("{} ({})", f.path, f.problem))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "Data bundle '{}' is damaged: {} of {} file(s) failed verification: {}. Next action: copy the data folder again from a good source (safely eject the USB stick) or re-run motis-import.sh.",
+            data_path,
+            self.damaged.len(),
+            self.checked_files,
+            files
+        )
+    }
+}
+
+struct ManifestEntry {
+    sha256: String,
+    size: u64,
+    path: String,
+}
+
+fn parse_manifest_line(line: &str) -> Result<ManifestEntry, String> {
+    let mut parts = line.splitn(3, ' ');
+    let (Some(sha256), Some(size), Some(path)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(format!("malformed manifest line: '{}'", line));
+    };
+    let size = size
+        .parse::<u64>()
+        .map_err(|_| format!("invalid size in manifest line: '{}'", line))?;
+    if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("invalid hash in manifest line: '{}'", line));
+    }
+    Ok(ManifestEntry {
+        sha256: sha256.to_ascii_lowercase(),
+        size,
+        path: path.to_string(),
+    })
+}
+
+fn is_safe_relative_path(path: &str) -> bool {
+    let path = Path::new(path);
+    !path.as_os_str().is_empty()
+        && path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+fn check_manifest_entry(
+    root: &Path,
+    entry: &ManifestEntry,
+    mode: ManifestVerifyMode,
+) -> Option<String> {
+    if !is_safe_relative_path(&entry.path) {
+        return Some("unsafe path in manifest".to_string());
+    }
+    let file = root.join(&entry.path);
+    let actual_size = match std::fs::metadata(&file) {
+        Ok(meta) if meta.is_file() => meta.len(),
+        Ok(_) => return Some("not a regular file".to_string()),
+        Err(_) => return Some("missing".to_string()),
+    };
+    if actual_size != entry.size {
+        return Some(format!(
+            "size {} bytes, expected {} bytes",
+            actual_size, entry.size
+        ));
+    }
+    if mode == ManifestVerifyMode::Full {
+        match sha256_file(&file) {
+            Ok(hash) if hash == entry.sha256 => {}
+            Ok(_) => return Some("checksum mismatch".to_string()),
+            Err(e) => return Some(format!("unreadable: {}", e)),
+        }
+    }
+    None
+}
+
+/// Checks the files listed in the import manifest. Bundles imported before
+/// manifests existed pass with `manifest_found == false`.
+pub fn verify_data_manifest(
+    data_path: &str,
+    mode: ManifestVerifyMode,
+) -> Result<ManifestReport, String> {
+    let root = Path::new(data_path);
+    let manifest_path = root.join(DATA_MANIFEST_FILE);
+    let manifest = match std::fs::read_to_string(&manifest_path) {
+        Ok(manifest) => manifest,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(ManifestReport::default());
+        }
+        Err(e) => {
+            return Err(format!(
+                "Failed to read data manifest '{}': {}. Next action: copy the data folder again or re-run motis-import.sh.",
+                manifest_path.display(),
+                e
+            ))
+        }
+    };
+
+    let mut lines = manifest.lines().filter(|l| !l.trim().is_empty());
+    if lines.next().map(str::trim) != Some(DATA_MANIFEST_HEADER) {
+        return Err(format!(
+            "Data manifest '{}' has an unknown format. Next action: re-run motis-import.sh.",
+            manifest_path.display()
+        ));
+    }
+
+    let mut report = ManifestReport {
+        manifest_found: true,
+        ..ManifestReport::default()
+    };
+    for line in lines {
+        report.checked_files += 1;
+        let entry = match parse_manifest_line(line) {
+            Ok(entry) => entry,
+            Err(problem) => {
+                report.damaged.push(DamagedFile {
+                    path: DATA_MANIFEST_FILE.to_string(),
+                    problem,
+                });
+                continue;
+            }
+        };
+        if let Some(problem) = check_manifest_entry(root, &entry, mode) {
+            report.damaged.push(DamagedFile {
+                path: entry.path,
+                problem,
+            });
+        }
+    }
+    Ok(report)
+}
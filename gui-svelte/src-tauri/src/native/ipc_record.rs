@@ -0,0 +1,111 @@
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+
+/// Sorts object keys recursively so `{"b":1,"a":2}` and `{"a":2,"b":1}`
+/// replay the same recording regardless of how the command was built.
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let mut sorted = Map::new();
+            for key in keys {
+                sorted.insert(key.clone(), canonicalize(&map[key]));
+            }
+            Value::Object(sorted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+fn parse_or_raw(line: &str) -> Value {
+    let line = line.trim_end();
+    serde_json::from_str(line).unwrap_or_else(|_| Value::String(line.to_string()))
+}
+
+fn command_key(cmd: &Value) -> String {
+    canonicalize(cmd).to_string()
+}
+
+/// Appends one `{"cmd": ..., "response": ...}` object per line. Responses that
+/// are not valid JSON are stored as strings and replayed verbatim.
+pub(crate) struct SessionRecorder {
+    file: File,
+}
+
+impl SessionRecorder {
+    pub(crate) fn create(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    pub(crate) fn record(&mut self, cmd: &str, response: &str) -> io::Result<()> {
+        let entry = json!({
+            "cmd": parse_or_raw(cmd),
+            "response": parse_or_raw(response),
+        });
+        writeln!(self.file, "{}", entry)?;
+        self.file.flush()
+    }
+}
+
+/// Serves recorded responses without MOTIS. Repeated commands are answered in
+/// recording order; once exhausted the last response is repeated.
+pub(crate) struct ReplayBackend {
+    responses: HashMap<String, Vec<String>>,
+    served: HashMap<String, usize>,
+}
+
+impl ReplayBackend {
+    pub(crate) fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open '{}': {}", path, e))?;
+
+        let mut responses: HashMap<String, Vec<String>> = HashMap::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: Value = serde_json::from_str(&line)
+                .map_err(|e| format!("{}:{}: invalid recording: {}", path, index + 1, e))?;
+            let (Some(cmd), Some(response)) = (entry.get("cmd"), entry.get("response")) else {
+                return Err(format!(
+                    "{}:{}: recording needs 'cmd' and 'response'",
+                    path,
+                    index + 1
+                ));
+            };
+            let response = match response {
+                Value::String(raw) => raw.clone(),
+                other => other.to_string(),
+            };
+            responses
+                .entry(command_key(cmd))
+                .or_default()
+                .push(response);
+        }
+
+        Ok(Self {
+            responses,
+            served: HashMap::new(),
+        })
+    }
+
+    pub(crate) fn send_command(&mut self, cmd: &str) -> String {
+        let key = command_key(&parse_or_raw(cmd));
+        let Some(recorded) = self.responses.get(&key) else {
+            return json!({
+                "status": "error",
+                "message": format!("No recorded response for command: {}", key),
+            })
+            .to_string();
+        };
+        let served = self.served.entry(key).or_insert(0);
+        let response = recorded[(*served).min(recorded.len() - 1)].clone();
+        *served += 1;
+        response
+    }
+}
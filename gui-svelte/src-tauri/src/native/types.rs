@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LatLon {
     pub lat: f64,
     pub lon: f64,
@@ -28,6 +28,90 @@ pub struct RouteResult {
     pub legs: Vec<RouteLeg>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Agency {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min: LatLon,
+    pub max: LatLon,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetInfo {
+    pub feeds: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_to: Option<String>,
+    #[serde(default)]
+    pub agencies: Vec<Agency>,
+    pub stop_count: u64,
+    pub route_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osm_bbox: Option<BoundingBox>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported_at: Option<String>,
+    /// Filled on the Rust side when today is outside the service range.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validity_warning: Option<String>,
+}
+
+/// Filters and bias for `geocode`, mirroring the `/api/v1/geocode` query
+/// parameters. `limit` is applied by the backend after ranking.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeocodeOptions {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub type_: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub place: Option<LatLon>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub place_bias: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub language: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+impl GeocodeOptions {
+    pub fn is_empty(&self) -> bool {
+        *self == GeocodeOptions::default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(type_) = &self.type_ {
+            if !matches!(type_.as_str(), "STOP" | "PLACE" | "ADDRESS") {
+                return Err(format!(
+                    "Invalid geocode type '{}': expected STOP, PLACE or ADDRESS",
+                    type_
+                ));
+            }
+        }
+        if let Some(place) = &self.place {
+            if !(-90.0..=90.0).contains(&place.lat) || !(-180.0..=180.0).contains(&place.lon) {
+                return Err(format!(
+                    "Invalid geocode place bias coordinate {},{}",
+                    place.lat, place.lon
+                ));
+            }
+        }
+        if let Some(bias) = self.place_bias {
+            if !bias.is_finite() || bias < 0.0 {
+                return Err(format!("Invalid geocode placeBias {}", bias));
+            }
+        }
+        if self.limit == Some(0) {
+            return Err("Invalid geocode limit 0".to_string());
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Area {
     pub name: String,
@@ -65,6 +149,10 @@ pub struct LocationResult {
     pub country: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub zip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tz: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,7 +170,7 @@ pub struct MatchArea {
 
 pub type Token = [i32; 2];
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Match {
     #[serde(rename = "type")]
     pub type_: String,
@@ -111,6 +199,10 @@ pub struct Match {
     pub modes: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub importance: Option<f64>,
+    /// Set for entries from the user store (`FAVORITE` or `RECENT`); `type`
+    /// keeps the MOTIS match type so the UI still recognizes stops.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 impl Match {
@@ -138,10 +230,11 @@ impl Match {
             house_number: loc.house_number.clone(),
             country: loc.country.clone(),
             zip: loc.zip.clone(),
-            tz: None,
-            level: None,
+            tz: loc.tz.clone(),
+            level: loc.level,
             modes: loc.modes.clone(),
             importance: loc.importance,
+            source: None,
         }
     }
 }
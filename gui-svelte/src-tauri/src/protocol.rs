@@ -4,11 +4,23 @@
 //! to the IPC backend. This allows the Svelte UI to use standard fetch()
 //! while communicating via IPC subprocess (no localhost HTTP server needed).
 
-use tauri::http::{Request, Response, StatusCode, header::CONTENT_TYPE};
+use tauri::http::{HeaderValue, Method, Request, Response, StatusCode, header::{ALLOW, CONTENT_TYPE}};
 use std::borrow::Cow;
 use serde_json::json;
-use crate::native;
+use crate::logging::{self, PROTOCOL};
+use crate::{kiosk, metrics, native, store};
 use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+// Map-matching and graph inspection are for data QA; served only when the
+// app runs with --debug.
+static DATA_QA_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enables `/api/matches` and `/api/graph` (set from `--debug`).
+pub fn set_data_qa_enabled(enabled: bool) {
+    DATA_QA_ENABLED.store(enabled, Ordering::Relaxed);
+}
 
 fn error_response(
     status: StatusCode,
@@ -38,6 +50,12 @@ fn classify_error(error: &str) -> (StatusCode, &'static str) {
     if lower.starts_with("unknown endpoint:") {
         return (StatusCode::NOT_FOUND, "endpoint");
     }
+    if lower.starts_with("invalid json request body") {
+        return (StatusCode::BAD_REQUEST, "request");
+    }
+    if lower.starts_with("method not allowed:") {
+        return (StatusCode::METHOD_NOT_ALLOWED, "request");
+    }
     if lower.contains("initialization")
         || lower.contains("not initialized")
         || lower.contains("data directory")
@@ -46,6 +64,9 @@ fn classify_error(error: &str) -> (StatusCode, &'static str) {
     {
         return (StatusCode::SERVICE_UNAVAILABLE, "initialization");
     }
+    if lower.contains("timed out") {
+        return (StatusCode::GATEWAY_TIMEOUT, "ipc");
+    }
     if lower.contains("ipc")
         || lower.contains("motis-ipc")
         || lower.contains("broken pipe")
@@ -61,11 +82,24 @@ enum RouteKind {
     Passthrough,
     Glyph,
     Tiles,
-    DebugTransfers,
-    UnsupportedDebug,
+    Debug,
+    DataQa,
     Unknown,
 }
 
+impl RouteKind {
+    fn label(self) -> &'static str {
+        match self {
+            RouteKind::Passthrough => "passthrough",
+            RouteKind::Glyph => "glyph",
+            RouteKind::Tiles => "tiles",
+            RouteKind::Debug => "debug",
+            RouteKind::DataQa => "data_qa",
+            RouteKind::Unknown => "unknown",
+        }
+    }
+}
+
 fn classify_path(path: &str) -> RouteKind {
     match path {
         // Route planning + core transit API
@@ -103,27 +137,100 @@ fn classify_path(path: &str) -> RouteKind {
             RouteKind::Tiles
         }
 
-        // Debug
-        "/api/debug/transfers" => RouteKind::DebugTransfers,
+        // Debug and street routing endpoints backed by MOTIS via api_get
+        // (GET) or api_post (POST with a JSON body)
         "/api/route"
-        | "/api/matches"
+        | "/api/debug/transfers"
+        | "/api/debug/flex"
         | "/api/elevators"
-        | "/api/update_elevator"
-        | "/api/graph"
-        | "/api/debug/flex" => RouteKind::UnsupportedDebug,
+        | "/api/update_elevator" => RouteKind::Debug,
+        // Data QA endpoints (POST), gated behind --debug
+        "/api/matches" | "/api/graph" => RouteKind::DataQa,
         _ => RouteKind::Unknown,
     }
 }
 
+/// Endpoints the backend only serves through api_post; any other method
+/// would fall through to api_get and fail there.
+fn is_post_only(path: &str) -> bool {
+    matches!(
+        path,
+        "/api/route" | "/api/elevators" | "/api/update_elevator" | "/api/matches" | "/api/graph"
+    )
+}
+
+fn method_not_allowed(method: &Method, path: &str) -> String {
+    format!(
+        "Method not allowed: {} {}. Next action: send a POST request with a JSON body.",
+        method, path
+    )
+}
+
+/// Request/response header carrying the trace id. fetch() may supply its
+/// own; otherwise one is generated.
+pub const TRACE_HEADER: &str = "x-trace-id";
+
 /// Handle motis:// scheme requests
 pub fn handle_motis_request(
     request: Request<Vec<u8>>,
 ) -> Response<Cow<'static, [u8]>> {
+    let trace_id = request
+        .headers()
+        .get(TRACE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| logging::is_valid_trace_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(logging::new_trace_id);
+    let _trace = logging::enter_trace(&trace_id);
+
+    let started = Instant::now();
+    let mut response = route_request(&request);
+    logging::debug!(
+        PROTOCOL,
+        "Response {} in {} ms",
+        response.status().as_u16(),
+        started.elapsed().as_millis()
+    );
+
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&trace_id) {
+        headers.insert(TRACE_HEADER, value);
+    }
+    headers.insert(
+        "access-control-expose-headers",
+        HeaderValue::from_static(TRACE_HEADER),
+    );
+    response
+}
+
+fn route_request(request: &Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
     let path = request.uri().path();
     let query = request.uri().query().unwrap_or("");
     
-    eprintln!("[MOTIS-PROTOCOL] Request: {}?{}", path, query);
-    
+    logging::debug!(PROTOCOL, "Request: {}?{}", path, query);
+
+    // CORS preflight for the debug panel's JSON POSTs.
+    if request.method() == Method::OPTIONS {
+        return preflight_response();
+    }
+
+    // Kiosk board is served from the poller's cache, so it renders (stale)
+    // even while the backend is down.
+    if let Some(response) = handle_kiosk(path) {
+        return response;
+    }
+    if let Some(response) = handle_metrics(path) {
+        return response;
+    }
+
+    let started = Instant::now();
+    let route = classify_path(path);
+    // Only passthrough and debug paths come from a fixed list; anything
+    // else would make the label set unbounded.
+    let metric_path =
+        matches!(route, RouteKind::Passthrough | RouteKind::Debug | RouteKind::DataQa)
+            .then_some(path);
+
     // Check if IPC is initialized.
     let mut is_initialized = native::is_ipc_initialized();
     
@@ -132,13 +239,19 @@ pub fn handle_motis_request(
         is_initialized = native::try_auto_init();
     }
     
-    eprintln!("[MOTIS-PROTOCOL] IPC initialized: {}, path: {}", is_initialized, path);
+    logging::trace!(PROTOCOL, "IPC initialized: {}, path: {}", is_initialized, path);
     
     if !is_initialized {
         let message = native::get_startup_diagnostics().unwrap_or_else(|| {
             "MOTIS IPC not initialized. Next action: launch via RUN.sh or set MOTIS_IPC_PATH and MOTIS_DATA_PATH."
                 .to_string()
         });
+        metrics::observe_request(
+            route.label(),
+            metric_path,
+            started.elapsed(),
+            Some("initialization"),
+        );
         return error_response(StatusCode::SERVICE_UNAVAILABLE, "initialization", path, &message);
     }
     
@@ -156,22 +269,34 @@ pub fn handle_motis_request(
     
     // Debug: log all parameters
     if !params.is_empty() {
-        eprintln!("[MOTIS-PROTOCOL] Params: {:?}", params);
+        logging::trace!(PROTOCOL, "Params: {:?}", params);
     }
     
     // Route to appropriate handler
-    let result = match classify_path(path) {
+    let result = match route {
+        RouteKind::Passthrough if is_geocode_path(path) => handle_geocode(path, query, &params),
         RouteKind::Passthrough => handle_api_passthrough(path, query),
         RouteKind::Glyph => handle_glyphs(path),
         RouteKind::Tiles => handle_tiles(path),
-        RouteKind::DebugTransfers => handle_debug_transfers(&params),
-        RouteKind::UnsupportedDebug => Err(format!(
-            "Unsupported protocol endpoint: {}. This endpoint is not available in portable IPC mode.",
+        RouteKind::Debug if request.method() == Method::POST => {
+            handle_api_post(path, request.body())
+        }
+        RouteKind::Debug if is_post_only(path) => Err(method_not_allowed(request.method(), path)),
+        RouteKind::Debug => handle_api_passthrough(path, query),
+        RouteKind::DataQa if !DATA_QA_ENABLED.load(Ordering::Relaxed) => Err(format!(
+            "Unsupported protocol endpoint: {}. Next action: start the app with --debug (./RUN.sh --debug) to enable data QA endpoints.",
             path
         )),
+        RouteKind::DataQa if request.method() != Method::POST => {
+            Err(method_not_allowed(request.method(), path))
+        }
+        RouteKind::DataQa => handle_api_post(path, request.body()),
         RouteKind::Unknown => Err(format!("Unknown endpoint: {}", path)),
     };
     
+    let error_stage = result.as_ref().err().map(|e| classify_error(e).1);
+    metrics::observe_request(route.label(), metric_path, started.elapsed(), error_stage);
+
     match result {
         Ok((body, content_type)) => {
             Response::builder()
@@ -182,13 +307,68 @@ pub fn handle_motis_request(
                 .unwrap()
         }
         Err(e) => {
-            eprintln!("[MOTIS-PROTOCOL] Error: {}", e);
+            logging::warning!(PROTOCOL, "Error: {}", e);
             let (status, stage) = classify_error(&e);
-            error_response(status, stage, path, &e)
+            let mut response = error_response(status, stage, path, &e);
+            if status == StatusCode::METHOD_NOT_ALLOWED {
+                response
+                    .headers_mut()
+                    .insert(ALLOW, HeaderValue::from_static("POST, OPTIONS"));
+            }
+            response
         }
     }
 }
 
+fn preflight_response() -> Response<Cow<'static, [u8]>> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Allow-Methods", "GET, POST, OPTIONS")
+        .header("Access-Control-Allow-Headers", "*")
+        .body(Cow::Borrowed(&[][..]))
+        .unwrap()
+}
+
+fn handle_kiosk(path: &str) -> Option<Response<Cow<'static, [u8]>>> {
+    let (body, content_type) = match path {
+        "/kiosk/" | "/kiosk/index.html" => (
+            kiosk::BOARD_HTML.as_bytes().to_vec(),
+            "text/html; charset=utf-8",
+        ),
+        "/kiosk/board.js" => (
+            kiosk::BOARD_JS.as_bytes().to_vec(),
+            "text/javascript; charset=utf-8",
+        ),
+        "/kiosk/board.json" => (kiosk::board_json(), "application/json"),
+        _ => return None,
+    };
+    Some(
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, content_type)
+            .header("Cache-Control", "no-store")
+            .body(Cow::Owned(body))
+            .unwrap(),
+    )
+}
+
+/// Prometheus scrape endpoint, answered even before the backend is up.
+fn handle_metrics(path: &str) -> Option<Response<Cow<'static, [u8]>>> {
+    if !matches!(path, "/metrics" | "/api/metrics") {
+        return None;
+    }
+    Some(
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")
+            .header("Cache-Control", "no-store")
+            .header("Access-Control-Allow-Origin", "*")
+            .body(Cow::Owned(metrics::prometheus_text().into_bytes()))
+            .unwrap(),
+    )
+}
+
 fn build_passthrough_path_and_query(path: &str, query: &str) -> String {
     if query.is_empty() {
         path.to_string()
@@ -205,8 +385,50 @@ fn handle_api_passthrough(path: &str, query: &str) -> Result<(Vec<u8>, &'static
     Ok((serde_json::to_vec(&value).unwrap_or_else(|_| b"{}".to_vec()), "application/json"))
 }
 
+/// POST endpoints (street routing, elevators, data QA) take a JSON body.
+fn handle_api_post(path: &str, body: &[u8]) -> Result<(Vec<u8>, &'static str), String> {
+    let body: serde_json::Value = serde_json::from_slice(body)
+        .map_err(|e| format!("Invalid JSON request body for {}: {}", path, e))?;
+    let value = native::api_post_sync(path, &body).map_err(|e| e.to_string())?;
+    Ok((serde_json::to_vec(&value).unwrap_or_else(|_| b"{}".to_vec()), "application/json"))
+}
+
+fn is_geocode_path(path: &str) -> bool {
+    matches!(path, "/api/v1/geocode" | "/api/v5/geocode")
+}
+
+/// Geocode passthrough with the user's favorites and recent places blended
+/// in front. A `type` filter (e.g. `type=STOP` in the departures view)
+/// applies to saved places as well.
+fn handle_geocode(
+    path: &str,
+    query: &str,
+    params: &std::collections::HashMap<String, String>,
+) -> Result<(Vec<u8>, &'static str), String> {
+    let path_and_query = build_passthrough_path_and_query(path, query);
+    let value = native::api_get_sync(&path_and_query).map_err(|e| e.to_string())?;
+
+    let text = params.get("text").map(String::as_str).unwrap_or("");
+    let saved = store::saved_suggestions(text, params.get("type").map(String::as_str));
+    let value = blend_geocode_json(saved, value);
+    Ok((serde_json::to_vec(&value).unwrap_or_else(|_| b"[]".to_vec()), "application/json"))
+}
+
+fn blend_geocode_json(saved: Vec<native::Match>, results: serde_json::Value) -> serde_json::Value {
+    let serde_json::Value::Array(results) = results else {
+        return results;
+    };
+    let saved = saved
+        .iter()
+        .filter_map(|m| serde_json::to_value(m).ok())
+        .collect();
+    serde_json::Value::Array(store::blend_by_id(saved, results, |m| {
+        m["id"].as_str().unwrap_or_default()
+    }))
+}
+
 fn handle_glyphs(path: &str) -> Result<(Vec<u8>, &'static str), String> {
-    eprintln!("[MOTIS-PROTOCOL] Glyph request: {}", path);
+    logging::trace!(PROTOCOL, "Glyph request: {}", path);
     match native::get_glyph_sync(path) {
         Ok(Some(base64_data)) => {
             match base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &base64_data) {
@@ -214,15 +436,15 @@ fn handle_glyphs(path: &str) -> Result<(Vec<u8>, &'static str), String> {
                     return Ok((binary_data, "application/x-protobuf"));
                 }
                 Err(e) => {
-                    eprintln!("[MOTIS-PROTOCOL] Glyph base64 decode error: {}", e);
+                    logging::warning!(PROTOCOL, "Glyph base64 decode error: {}", e);
                 }
             }
         }
         Ok(None) => {
-            eprintln!("[MOTIS-PROTOCOL] Glyph not found: {}", path);
+            logging::debug!(PROTOCOL, "Glyph not found: {}", path);
         }
         Err(e) => {
-            eprintln!("[MOTIS-PROTOCOL] Glyph fetch error: {}", e);
+            logging::warning!(PROTOCOL, "Glyph fetch error: {}", e);
         }
     }
 
@@ -247,11 +469,11 @@ fn maybe_inflate_zlib(data: &[u8]) -> Vec<u8> {
 
 fn handle_tiles(path: &str) -> Result<(Vec<u8>, &'static str), String> {
     // Vector tiles are binary MVT format
-    eprintln!("[MOTIS-PROTOCOL] Tile request: {}", path);
+    logging::trace!(PROTOCOL, "Tile request: {}", path);
     
     // Parse tile coordinates from path: /api/v1/tiles/{z}/{x}/{y}.mvt or /tiles/{z}/{x}/{y}.mvt
     let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
-    eprintln!("[MOTIS-PROTOCOL] Path parts: {:?}", parts);
+    logging::trace!(PROTOCOL, "Path parts: {:?}", parts);
     
     // Need at least 3 parts: tiles/z/x/y.mvt
     if parts.len() >= 3 {
@@ -260,54 +482,53 @@ fn handle_tiles(path: &str) -> Result<(Vec<u8>, &'static str), String> {
         let y_str = parts.last().unwrap_or(&"");
         let y: i32 = y_str.trim_end_matches(".mvt").parse().map_err(|e| format!("Invalid y: {}", e))?;
         
-        eprintln!("[MOTIS-PROTOCOL] Tile: z={}, x={}, y={}", z, x, y);
+        logging::trace!(PROTOCOL, "Tile: z={}, x={}, y={}", z, x, y);
         
         // Fetch tile from IPC backend
         match native::get_tile_sync(z, x, y) {
             Ok(Some(base64_data)) => {
-                eprintln!("[MOTIS-PROTOCOL] Got base64 data: {} chars", base64_data.len());
+                logging::trace!(PROTOCOL, "Got base64 data: {} chars", base64_data.len());
                 // Decode base64 to binary
                 match base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &base64_data) {
                     Ok(binary_data) => {
                         let tile_bytes = maybe_inflate_zlib(&binary_data);
-                        eprintln!(
-                            "[MOTIS-PROTOCOL] Tile decoded: {} bytes (inflated: {} bytes)",
+                        logging::trace!(
+                            PROTOCOL,
+                            "Tile decoded: {} bytes (inflated: {} bytes)",
                             binary_data.len(),
                             tile_bytes.len()
                         );
                         if tile_bytes.is_empty() {
-                            eprintln!("[MOTIS-PROTOCOL] Warning: tile data is empty!");
+                            logging::warning!(PROTOCOL, "Tile data is empty");
                         }
                         return Ok((tile_bytes, "application/vnd.mapbox-vector-tile"));
                     }
                     Err(e) => {
-                        eprintln!("[MOTIS-PROTOCOL] Base64 decode error: {}", e);
+                        logging::warning!(PROTOCOL, "Base64 decode error: {}", e);
                     }
                 }
             }
             Ok(None) => {
-                eprintln!("[MOTIS-PROTOCOL] Tile not found");
+                logging::trace!(PROTOCOL, "Tile not found");
             }
             Err(e) => {
-                eprintln!("[MOTIS-PROTOCOL] Tile fetch error: {}", e);
+                logging::warning!(PROTOCOL, "Tile fetch error: {}", e);
             }
         }
     }
     
     // Return empty tile on error (this is what map libraries expect for missing tiles)
-    eprintln!("[MOTIS-PROTOCOL] Returning empty tile");
+    logging::trace!(PROTOCOL, "Returning empty tile");
     Ok((vec![], "application/vnd.mapbox-vector-tile"))
 }
 
-fn handle_debug_transfers(_params: &std::collections::HashMap<String, String>)
-    -> Result<(Vec<u8>, &'static str), String> {
-    Ok(("[]".as_bytes().to_vec(), "application/json"))
-}
-
 #[cfg(test)]
 mod tests {
-    use super::{build_passthrough_path_and_query, classify_error, classify_path, RouteKind};
-    use tauri::http::StatusCode;
+    use super::{
+        build_passthrough_path_and_query, classify_error, classify_path, is_post_only,
+        method_not_allowed, RouteKind,
+    };
+    use tauri::http::{Method, StatusCode};
 
     #[test]
     fn passthrough_path_without_query() {
@@ -373,10 +594,62 @@ mod tests {
         }
     }
 
+    #[test]
+    fn debug_transfers_is_routed_to_motis() {
+        assert_eq!(classify_path("/api/debug/transfers"), RouteKind::Debug);
+    }
+
+    #[test]
+    fn debug_flex_is_routed_to_motis() {
+        assert_eq!(classify_path("/api/debug/flex"), RouteKind::Debug);
+    }
+
+    #[test]
+    fn street_routing_is_routed_to_motis() {
+        assert_eq!(classify_path("/api/route"), RouteKind::Debug);
+    }
+
+    #[test]
+    fn data_qa_endpoints_are_gated() {
+        assert_eq!(classify_path("/api/matches"), RouteKind::DataQa);
+        assert_eq!(classify_path("/api/graph"), RouteKind::DataQa);
+    }
+
+    #[test]
+    fn elevator_endpoints_are_routed_to_motis() {
+        assert_eq!(classify_path("/api/elevators"), RouteKind::Debug);
+        assert_eq!(classify_path("/api/update_elevator"), RouteKind::Debug);
+    }
+
+    #[test]
+    fn classify_error_maps_invalid_request_body_to_400() {
+        let (status, stage) = classify_error(
+            "Invalid JSON request body for /api/update_elevator: EOF while parsing a value",
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(stage, "request");
+    }
+
+    #[test]
+    fn classify_error_maps_wrong_method_to_405() {
+        let (status, stage) =
+            classify_error(&method_not_allowed(&Method::GET, "/api/update_elevator"));
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(stage, "request");
+    }
+
+    #[test]
+    fn post_only_endpoints_exclude_debug_get_endpoints() {
+        assert!(is_post_only("/api/update_elevator"));
+        assert!(is_post_only("/api/route"));
+        assert!(!is_post_only("/api/debug/transfers"));
+        assert!(!is_post_only("/api/debug/flex"));
+    }
+
     #[test]
     fn classify_error_maps_unsupported_protocol_endpoint_to_501() {
         let (status, stage) = classify_error(
-            "Unsupported protocol endpoint: /api/debug/flex. This endpoint is not available in portable IPC mode.",
+            "Unsupported protocol endpoint: /api/graph. This endpoint is not available in portable IPC mode.",
         );
         assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
         assert_eq!(stage, "endpoint");
@@ -395,4 +668,13 @@ mod tests {
         assert_eq!(status, StatusCode::BAD_GATEWAY);
         assert_eq!(stage, "ipc");
     }
+
+    #[test]
+    fn classify_error_maps_ipc_timeouts_to_504() {
+        let (status, stage) = classify_error(
+            "IPC command failed after 3 attempts: motis-ipc timed out after 300000 ms",
+        );
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(stage, "ipc");
+    }
 }
@@ -0,0 +1,663 @@
+//! Persistent user state: favorite places, recent searches, saved
+//! itineraries and pinned stops.
+//!
+//! Stored as one JSON file, by default in the user config dir. Portable
+//! bundles keep it on the stick instead when a `user-data/` directory exists
+//! next to `data/` (or `MOTIS_USER_STORE_PATH` points somewhere else).
+//!
+//! Writes go to a temp file in the same directory, are fsynced and then
+//! renamed over the old file; the previous version is kept as `.bak`. FAT32
+//! has no atomic replace guarantees across power loss, so a torn write
+//! falls back to the backup on the next load.
+
+use crate::export::write_export_bytes;
+use crate::logging::{self, UserDir};
+use crate::native::Match;
+use chrono::{SecondsFormat, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+pub const STORE_FILE: &str = "motis-user.json";
+const STORE_VERSION: u32 = 1;
+const MAX_RECENT_SEARCHES: usize = 50;
+
+/// `Match.source` for saved places blended into geocode results; `type`
+/// stays the MOTIS match type.
+pub const FAVORITE_SOURCE: &str = "FAVORITE";
+pub const RECENT_SOURCE: &str = "RECENT";
+const MIN_SUGGESTION_CHARS: usize = 2;
+const MAX_SUGGESTIONS: usize = 5;
+/// Added to `score` so saved places sort above geocoder hits in the
+/// typeahead, which ranks by type and score.
+const SUGGESTION_SCORE_BOOST: f64 = 100_000.0;
+
+static STORE: Lazy<Mutex<Option<(PathBuf, UserStore)>>> = Lazy::new(|| Mutex::new(None));
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FavoritePlace {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub place: Match,
+    pub added_at: String,
+    #[serde(default)]
+    pub use_count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentSearch {
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selected: Option<Match>,
+    pub searched_at: String,
+    #[serde(default = "one")]
+    pub count: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedItinerary {
+    pub id: String,
+    pub name: String,
+    /// Itinerary as returned by `/api/v5/plan` (or a `RouteResult`).
+    pub itinerary: serde_json::Value,
+    pub saved_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedStop {
+    pub stop_id: String,
+    pub name: String,
+    pub pinned_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserStore {
+    #[serde(default = "current_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub favorites: Vec<FavoritePlace>,
+    #[serde(default)]
+    pub recent_searches: Vec<RecentSearch>,
+    #[serde(default)]
+    pub saved_itineraries: Vec<SavedItinerary>,
+    #[serde(default)]
+    pub pinned_stops: Vec<PinnedStop>,
+}
+
+fn current_version() -> u32 {
+    STORE_VERSION
+}
+
+impl Default for UserStore {
+    fn default() -> Self {
+        UserStore {
+            version: STORE_VERSION,
+            favorites: Vec::new(),
+            recent_searches: Vec::new(),
+            saved_itineraries: Vec::new(),
+            pinned_stops: Vec::new(),
+        }
+    }
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn new_id() -> String {
+    let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    format!(
+        "{:x}-{:x}",
+        nanos,
+        ID_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+impl UserStore {
+    pub fn add_favorite(&mut self, place: Match, label: Option<String>) -> FavoritePlace {
+        if let Some(existing) = self.favorites.iter_mut().find(|f| f.place.id == place.id) {
+            if label.is_some() {
+                existing.label = label;
+            }
+            return existing.clone();
+        }
+        let favorite = FavoritePlace {
+            id: new_id(),
+            label,
+            place,
+            added_at: now(),
+            use_count: 0,
+            last_used: None,
+        };
+        self.favorites.push(favorite.clone());
+        favorite
+    }
+
+    pub fn remove_favorite(&mut self, id: &str) -> bool {
+        let before = self.favorites.len();
+        self.favorites.retain(|f| f.id != id);
+        before != self.favorites.len()
+    }
+
+    pub fn set_favorite_label(&mut self, id: &str, label: Option<String>) -> bool {
+        match self.favorites.iter_mut().find(|f| f.id == id) {
+            Some(favorite) => {
+                favorite.label = label;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remembers a search; a selected place also bumps the matching favorite.
+    pub fn record_search(&mut self, query: &str, selected: Option<Match>) {
+        let query = query.trim();
+        if query.is_empty() && selected.is_none() {
+            return;
+        }
+        let timestamp = now();
+
+        if let Some(place) = &selected {
+            if let Some(favorite) = self.favorites.iter_mut().find(|f| f.place.id == place.id) {
+                favorite.use_count += 1;
+                favorite.last_used = Some(timestamp.clone());
+            }
+        }
+
+        let key = |search: &RecentSearch| match &search.selected {
+            Some(place) => place.id.clone(),
+            None => search.query.to_lowercase(),
+        };
+        let entry = RecentSearch {
+            query: query.to_string(),
+            selected,
+            searched_at: timestamp,
+            count: 1,
+        };
+        let entry_key = key(&entry);
+        let count = match self
+            .recent_searches
+            .iter()
+            .position(|s| key(s) == entry_key)
+        {
+            Some(index) => self.recent_searches.remove(index).count + 1,
+            None => 1,
+        };
+        self.recent_searches
+            .insert(0, RecentSearch { count, ..entry });
+        self.recent_searches.truncate(MAX_RECENT_SEARCHES);
+    }
+
+    pub fn save_itinerary(&mut self, name: String, itinerary: serde_json::Value) -> SavedItinerary {
+        let saved = SavedItinerary {
+            id: new_id(),
+            name,
+            itinerary,
+            saved_at: now(),
+        };
+        self.saved_itineraries.push(saved.clone());
+        saved
+    }
+
+    pub fn remove_itinerary(&mut self, id: &str) -> bool {
+        let before = self.saved_itineraries.len();
+        self.saved_itineraries.retain(|i| i.id != id);
+        before != self.saved_itineraries.len()
+    }
+
+    pub fn pin_stop(&mut self, stop_id: String, name: String) {
+        match self.pinned_stops.iter_mut().find(|s| s.stop_id == stop_id) {
+            Some(stop) => stop.name = name,
+            None => self.pinned_stops.push(PinnedStop {
+                stop_id,
+                name,
+                pinned_at: now(),
+            }),
+        }
+    }
+
+    pub fn unpin_stop(&mut self, stop_id: &str) -> bool {
+        let before = self.pinned_stops.len();
+        self.pinned_stops.retain(|s| s.stop_id != stop_id);
+        before != self.pinned_stops.len()
+    }
+
+    /// Adds entries from `other` that are not present yet (by id / place /
+    /// stop); existing entries win.
+    pub fn merge(&mut self, other: UserStore) {
+        for favorite in other.favorites {
+            if !self
+                .favorites
+                .iter()
+                .any(|f| f.id == favorite.id || f.place.id == favorite.place.id)
+            {
+                self.favorites.push(favorite);
+            }
+        }
+        for search in other.recent_searches {
+            if !self
+                .recent_searches
+                .iter()
+                .any(|s| s.query == search.query && s.searched_at == search.searched_at)
+            {
+                self.recent_searches.push(search);
+            }
+        }
+        self.recent_searches
+            .sort_by(|a, b| b.searched_at.cmp(&a.searched_at));
+        self.recent_searches.truncate(MAX_RECENT_SEARCHES);
+        for itinerary in other.saved_itineraries {
+            if !self.saved_itineraries.iter().any(|i| i.id == itinerary.id) {
+                self.saved_itineraries.push(itinerary);
+            }
+        }
+        for stop in other.pinned_stops {
+            if !self.pinned_stops.iter().any(|s| s.stop_id == stop.stop_id) {
+                self.pinned_stops.push(stop);
+            }
+        }
+    }
+}
+
+fn matches_query(text: &str, query: &str) -> Option<f64> {
+    let text = text.to_lowercase();
+    if text.starts_with(query) {
+        Some(2.0)
+    } else if text
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(query))
+    {
+        Some(1.0)
+    } else {
+        None
+    }
+}
+
+fn as_suggestion(place: &Match, source: &str, name: String, score: f64) -> Match {
+    Match {
+        name,
+        // Highlight ranges refer to the geocoder's query, not this one.
+        tokens: Vec::new(),
+        score: SUGGESTION_SCORE_BOOST + score,
+        source: Some(source.to_string()),
+        ..place.clone()
+    }
+}
+
+impl UserStore {
+    /// Favorites and recently selected places matching `query` (from two
+    /// typed characters), in `Match` shape with source FAVORITE/RECENT.
+    /// `type_` keeps only places of that MOTIS type, as a type-filtered
+    /// geocode would. Frequently used places rank first.
+    pub fn suggestions(&self, query: &str, type_: Option<&str>) -> Vec<Match> {
+        let query = query.trim().to_lowercase();
+        if query.chars().count() < MIN_SUGGESTION_CHARS {
+            return Vec::new();
+        }
+
+        let wanted = |place: &Match| type_.iter().all(|t| place.type_ == *t);
+        let mut suggestions: Vec<Match> = Vec::new();
+        for favorite in self.favorites.iter().filter(|f| wanted(&f.place)) {
+            let label_hit = favorite
+                .label
+                .as_deref()
+                .and_then(|label| matches_query(label, &query));
+            let Some(hit) = label_hit.or_else(|| matches_query(&favorite.place.name, &query))
+            else {
+                continue;
+            };
+            let name = match &favorite.label {
+                Some(label) if label != &favorite.place.name => {
+                    format!("{} ({})", label, favorite.place.name)
+                }
+                _ => favorite.place.name.clone(),
+            };
+            let score = 1_000.0 + hit * 100.0 + f64::from(favorite.use_count);
+            suggestions.push(as_suggestion(&favorite.place, FAVORITE_SOURCE, name, score));
+        }
+
+        for search in &self.recent_searches {
+            let Some(place) = search.selected.as_ref().filter(|p| wanted(p)) else {
+                continue;
+            };
+            if suggestions.iter().any(|s| s.id == place.id) {
+                continue;
+            }
+            let Some(hit) = matches_query(&place.name, &query) else {
+                continue;
+            };
+            let score = hit * 100.0 + f64::from(search.count);
+            suggestions.push(as_suggestion(place, RECENT_SOURCE, place.name.clone(), score));
+        }
+
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+}
+
+/// Puts `saved` first and drops geocoder hits for the same place.
+pub fn blend_matches(saved: Vec<Match>, results: Vec<Match>) -> Vec<Match> {
+    blend_by_id(saved, results, |m| m.id.as_str())
+}
+
+/// [`blend_matches`] for any result shape; the motis:// geocode passthrough
+/// blends raw JSON so MOTIS fields `Match` does not model survive.
+pub fn blend_by_id<T>(saved: Vec<T>, results: Vec<T>, id: impl Fn(&T) -> &str) -> Vec<T> {
+    let mut blended = saved;
+    for result in results {
+        if !blended.iter().any(|s| id(s) == id(&result)) {
+            blended.push(result);
+        }
+    }
+    blended
+}
+
+/// Suggestions from the process-wide store; an unavailable store only
+/// means no suggestions.
+pub fn saved_suggestions(query: &str, type_: Option<&str>) -> Vec<Match> {
+    match read_store() {
+        Ok(store) => store.suggestions(query, type_),
+        Err(e) => {
+            logging::warning!(logging::STORE, "User store unavailable: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// `MOTIS_USER_STORE_PATH`, then `<bundle>/user-data/` next to
+/// `MOTIS_DATA_PATH`, then `<config dir>/motis-gui/`.
+pub fn default_store_path() -> Result<PathBuf, String> {
+    if let Some(path) = std::env::var_os("MOTIS_USER_STORE_PATH").filter(|p| !p.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    if let Some(data_path) = std::env::var_os("MOTIS_DATA_PATH") {
+        if let Some(bundle) = Path::new(&data_path).parent() {
+            let portable = bundle.join("user-data");
+            if portable.is_dir() {
+                return Ok(portable.join(STORE_FILE));
+            }
+        }
+    }
+    UserDir::Config
+        .path()
+        .map(|dir| dir.join("motis-gui").join(STORE_FILE))
+        .ok_or_else(|| {
+            "No user config directory. Next action: set MOTIS_USER_STORE_PATH.".to_string()
+        })
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("json.bak")
+}
+
+fn read_store_file(path: &Path) -> Result<UserStore, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let store: UserStore = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid user store '{}': {}", path.display(), e))?;
+    if store.version > STORE_VERSION {
+        return Err(format!(
+            "User store '{}' was written by a newer version (v{})",
+            path.display(),
+            store.version
+        ));
+    }
+    Ok(store)
+}
+
+/// Missing file → empty store; unreadable file → `.bak` if that parses.
+pub fn load_store(path: &Path) -> Result<UserStore, String> {
+    if !path.exists() {
+        return Ok(UserStore::default());
+    }
+    match read_store_file(path) {
+        Ok(store) => Ok(store),
+        Err(e) if e.contains("newer version") => Err(e),
+        Err(e) => {
+            let backup = backup_path(path);
+            let store = read_store_file(&backup).map_err(|_| e.clone())?;
+            logging::info!(logging::STORE, "{}; restored from {}", e, backup.display());
+            Ok(store)
+        }
+    }
+}
+
+pub fn save_store(path: &Path, store: &UserStore) -> Result<(), String> {
+    write_store_file(path, store, true)
+}
+
+/// Writes through [`write_export_bytes`]. `keep_backup` copies the
+/// previous file to `.json.bak` first; only the app's own store wants that.
+fn write_store_file(path: &Path, store: &UserStore, keep_backup: bool) -> Result<(), String> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("Invalid user store path '{}'", path.display()))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?;
+
+    let text = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
+    if keep_backup && path.exists() {
+        // Copy rather than rename so `path` never disappears on FAT32.
+        let _ = fs::copy(path, backup_path(path));
+    }
+    write_export_bytes(path, text.as_bytes())?;
+    // Persist the rename; some filesystems (vfat) reject fsync on dirs.
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+fn with_loaded<T>(f: impl FnOnce(&Path, &mut UserStore) -> Result<T, String>) -> Result<T, String> {
+    let mut guard = STORE
+        .lock()
+        .map_err(|_| "User store lock poisoned".to_string())?;
+    if guard.is_none() {
+        let path = default_store_path()?;
+        let store = load_store(&path)?;
+        *guard = Some((path, store));
+    }
+    let (path, store) = guard.as_mut().expect("store loaded above");
+    f(path, store)
+}
+
+/// Applies `f` to the process-wide store and saves it. The in-memory copy
+/// only changes once the file was written.
+pub fn update_store<T>(f: impl FnOnce(&mut UserStore) -> T) -> Result<T, String> {
+    with_loaded(|path, store| {
+        let mut next = store.clone();
+        let result = f(&mut next);
+        save_store(path, &next)?;
+        *store = next;
+        Ok(result)
+    })
+}
+
+pub fn read_store() -> Result<UserStore, String> {
+    with_loaded(|_, store| Ok(store.clone()))
+}
+
+pub fn store_path() -> Result<PathBuf, String> {
+    with_loaded(|path, _| Ok(path.to_path_buf()))
+}
+
+/// Writes a copy of the store to a user-chosen path, without leaving a
+/// backup or temporary file next to it.
+pub fn export_store(path: &Path) -> Result<(), String> {
+    write_store_file(path, &read_store()?, false)
+}
+
+/// Imports a store file; `replace` discards current entries, otherwise
+/// missing entries are merged in.
+pub fn import_store(path: &Path, replace: bool) -> Result<UserStore, String> {
+    let imported = read_store_file(path)?;
+    update_store(|store| {
+        if replace {
+            *store = imported;
+        } else {
+            store.merge(imported);
+        }
+        store.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        blend_matches, load_store, save_store, write_store_file, UserStore, FAVORITE_SOURCE,
+        RECENT_SOURCE,
+    };
+    use crate::native::Match;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn place(id: &str, name: &str) -> Match {
+        Match {
+            type_: "STOP".to_string(),
+            name: name.to_string(),
+            id: id.to_string(),
+            lat: 59.33,
+            lon: 18.06,
+            ..Match::default()
+        }
+    }
+
+    fn address(id: &str, name: &str) -> Match {
+        Match {
+            type_: "ADDRESS".to_string(),
+            ..place(id, name)
+        }
+    }
+
+    #[test]
+    fn store_round_trips_and_recovers_from_backup() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("unix epoch")
+            .as_nanos();
+        let dir =
+            std::env::temp_dir().join(format!("motis-store-{}-{}", std::process::id(), timestamp));
+        let path = dir.join("motis-user.json");
+
+        let mut store = UserStore::default();
+        store.add_favorite(place("stop-1", "Odenplan"), Some("Work".to_string()));
+        save_store(&path, &store).expect("first save");
+        store.pin_stop("stop-2".to_string(), "Slussen".to_string());
+        save_store(&path, &store).expect("second save");
+
+        let loaded = load_store(&path).expect("load");
+        assert_eq!(loaded.favorites.len(), 1);
+        assert_eq!(loaded.pinned_stops.len(), 1);
+
+        // Torn write: the backup (first save) is used.
+        std::fs::write(&path, "{\"favorites\": [").expect("corrupt store");
+        let recovered = load_store(&path).expect("recover from backup");
+        assert_eq!(recovered.favorites[0].label.as_deref(), Some("Work"));
+        assert!(recovered.pinned_stops.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn export_leaves_only_the_chosen_file() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("unix epoch")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!(
+            "motis-store-export-{}-{}",
+            std::process::id(),
+            timestamp
+        ));
+        let path = dir.join("favorites.json");
+
+        let mut store = UserStore::default();
+        store.add_favorite(place("stop-1", "Odenplan"), None);
+        write_store_file(&path, &store, false).expect("first export");
+        write_store_file(&path, &store, false).expect("second export");
+
+        let names: Vec<_> = std::fs::read_dir(&dir)
+            .expect("read dir")
+            .map(|entry| entry.expect("entry").file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("favorites.json")]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recent_searches_are_deduplicated_and_bump_favorites() {
+        let mut store = UserStore::default();
+        store.add_favorite(place("stop-1", "Odenplan"), None);
+        store.record_search("oden", Some(place("stop-1", "Odenplan")));
+        store.record_search("slussen", None);
+        store.record_search("odenp", Some(place("stop-1", "Odenplan")));
+        store.record_search("  ", None);
+
+        assert_eq!(store.recent_searches.len(), 2);
+        assert_eq!(store.recent_searches[0].query, "odenp");
+        assert_eq!(store.recent_searches[0].count, 2);
+        assert_eq!(store.favorites[0].use_count, 2);
+    }
+
+    #[test]
+    fn merge_keeps_existing_entries() {
+        let mut store = UserStore::default();
+        store.add_favorite(place("stop-1", "Odenplan"), Some("Mine".to_string()));
+
+        let mut other = UserStore::default();
+        other.add_favorite(place("stop-1", "Odenplan"), Some("Theirs".to_string()));
+        other.add_favorite(place("stop-3", "Gullmarsplan"), None);
+        other.pin_stop("stop-3".to_string(), "Gullmarsplan".to_string());
+
+        store.merge(other);
+        assert_eq!(store.favorites.len(), 2);
+        assert_eq!(store.favorites[0].label.as_deref(), Some("Mine"));
+        assert_eq!(store.pinned_stops.len(), 1);
+    }
+
+    #[test]
+    fn suggestions_rank_favorites_and_recent_places() {
+        let mut store = UserStore::default();
+        store.add_favorite(place("stop-1", "Odenplan"), Some("Jobbet".to_string()));
+        store.record_search("ode", Some(place("stop-2", "Odengatan 5")));
+        store.record_search("slu", Some(place("stop-3", "Slussen")));
+
+        store.record_search("odenv", Some(address("addr-1", "Vid Odenvägen 3")));
+
+        assert!(store.suggestions("o", None).is_empty(), "needs two characters");
+
+        let suggestions = store.suggestions("od", None);
+        let ids: Vec<&str> = suggestions.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["stop-1", "stop-2", "addr-1"]);
+        assert_eq!(suggestions[0].source.as_deref(), Some(FAVORITE_SOURCE));
+        assert_eq!(suggestions[0].type_, "STOP", "MOTIS type is kept");
+        assert_eq!(suggestions[0].name, "Jobbet (Odenplan)");
+        assert_eq!(suggestions[1].source.as_deref(), Some(RECENT_SOURCE));
+        assert!(store.suggestions("job", None)[0].score > 100_000.0);
+
+        let stops = store.suggestions("od", Some("STOP"));
+        let ids: Vec<&str> = stops.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["stop-1", "stop-2"]);
+        let addresses = store.suggestions("od", Some("ADDRESS"));
+        assert_eq!(addresses.len(), 1);
+        assert_eq!(addresses[0].id, "addr-1");
+
+        let blended = blend_matches(
+            suggestions,
+            vec![place("stop-1", "Odenplan"), place("stop-9", "Odenvägen")],
+        );
+        let ids: Vec<&str> = blended.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["stop-1", "stop-2", "addr-1", "stop-9"]);
+    }
+}
@@ -0,0 +1,270 @@
+//! Integration tests for the IPC backend and motis:// protocol against the
+//! `mock-motis-ipc` test binary.
+#![cfg(unix)]
+
+use motis_gui_svelte::{native, protocol};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::http::{Request, StatusCode};
+
+// The IPC backend is process-global; run one scenario at a time.
+static BACKEND_LOCK: Mutex<()> = Mutex::new(());
+
+const MOCK_IPC: &str = env!("CARGO_BIN_EXE_mock-motis-ipc");
+
+struct MockSession {
+    root: PathBuf,
+    _guard: std::sync::MutexGuard<'static, ()>,
+}
+
+impl MockSession {
+    fn start(name: &str, behavior: serde_json::Value) -> Self {
+        let guard = BACKEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        native::destroy();
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("unix epoch")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!(
+            "motis-mock-{}-{}-{}",
+            name,
+            std::process::id(),
+            timestamp
+        ));
+        std::fs::create_dir_all(&root).expect("create mock data dir");
+        std::fs::write(root.join("config.yml"), "# mock dataset\n").expect("write config.yml");
+        std::fs::write(root.join("mock-ipc.json"), behavior.to_string())
+            .expect("write mock-ipc.json");
+
+        native::set_ipc_response_timeout(Some(Duration::from_secs(10)));
+        native::init_ipc(MOCK_IPC, root.to_str().expect("mock data path"))
+            .expect("start mock-motis-ipc");
+
+        MockSession {
+            root,
+            _guard: guard,
+        }
+    }
+}
+
+impl Drop for MockSession {
+    fn drop(&mut self) {
+        native::destroy();
+        native::set_ipc_response_timeout(None);
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+fn protocol_get(path_and_query: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .uri(format!("motis://localhost{}", path_and_query))
+        .body(Vec::new())
+        .expect("build request");
+    let response = protocol::handle_motis_request(request);
+    let body = serde_json::from_slice(response.body()).unwrap_or(serde_json::Value::Null);
+    (response.status(), body)
+}
+
+#[test]
+fn scripted_responses_reach_native_api() {
+    let _session = MockSession::start(
+        "responses",
+        serde_json::json!({
+            "responses": {
+                "dataset_info": {
+                    "feeds": ["mock-feed"],
+                    "service_from": "2026-01-01",
+                    "service_to": "2026-12-31",
+                    "agencies": [],
+                    "stop_count": 3,
+                    "route_count": 1
+                }
+            }
+        }),
+    );
+
+    let info = native::dataset_info_sync().expect("dataset_info");
+    assert_eq!(info.feeds, vec!["mock-feed".to_string()]);
+    assert_eq!(info.stop_count, 3);
+}
+
+#[test]
+fn crash_after_n_commands_recovers_transparently() {
+    let _session = MockSession::start("crash", serde_json::json!({ "crash_after": 2 }));
+
+    for _ in 0..5 {
+        native::geocode_sync("centralen", &Default::default()).expect("geocode survives mock crashes");
+    }
+}
+
+#[test]
+fn latency_within_timeout_succeeds() {
+    let _session = MockSession::start("latency", serde_json::json!({ "latency_ms": 200 }));
+
+    let (status, _) = protocol_get("/api/v1/geocode?text=odenplan");
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[test]
+fn malformed_json_maps_to_bad_gateway() {
+    let _session = MockSession::start(
+        "malformed",
+        serde_json::json!({ "malformed_on": ["api_get"] }),
+    );
+
+    let (status, body) = protocol_get("/api/v1/geocode?text=odenplan");
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["stage"], "ipc");
+    assert!(native::geocode_sync("still alive", &Default::default()).is_ok());
+}
+
+#[test]
+fn stalled_backend_times_out_with_gateway_timeout() {
+    let _session = MockSession::start("stall", serde_json::json!({ "stall_on": ["api_get"] }));
+    native::set_ipc_response_timeout(Some(Duration::from_millis(300)));
+
+    let started = std::time::Instant::now();
+    let (status, body) = protocol_get("/api/v1/stoptimes?stopId=test");
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(body["stage"], "ipc");
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    // The stalled process was replaced; other commands keep working.
+    native::set_ipc_response_timeout(Some(Duration::from_secs(10)));
+    assert!(native::geocode_sync("after stall", &Default::default()).is_ok());
+}
+
+#[test]
+fn large_responses_are_read_completely() {
+    let size = 8 * 1024 * 1024;
+    let _session = MockSession::start(
+        "oversized",
+        serde_json::json!({ "oversized_on": { "api_get": size } }),
+    );
+
+    let value = native::api_get_sync("/api/v1/map/stops").expect("large response");
+    assert_eq!(value["padding"].as_str().map(str::len), Some(size));
+}
+
+#[test]
+fn trace_id_reaches_backend_and_response_header() {
+    let _session = MockSession::start("trace", serde_json::json!({}));
+
+    let request = Request::builder()
+        .uri("motis://localhost/api/v1/map/initial")
+        .header(protocol::TRACE_HEADER, "search-42")
+        .body(Vec::new())
+        .expect("build request");
+    let response = protocol::handle_motis_request(request);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[protocol::TRACE_HEADER], "search-42");
+    let body: serde_json::Value = serde_json::from_slice(response.body()).expect("json body");
+    assert_eq!(body["trace_id"], "search-42");
+
+    // Without a caller id one is generated per request.
+    let request = Request::builder()
+        .uri("motis://localhost/api/v1/map/initial")
+        .body(Vec::new())
+        .expect("build request");
+    let response = protocol::handle_motis_request(request);
+    let generated = response.headers()[protocol::TRACE_HEADER]
+        .to_str()
+        .expect("ascii header")
+        .to_string();
+    assert_eq!(generated.len(), 16);
+    let body: serde_json::Value = serde_json::from_slice(response.body()).expect("json body");
+    assert_eq!(body["trace_id"], generated.as_str());
+}
+
+#[test]
+fn out_of_sync_reply_restarts_the_backend() {
+    let _session = MockSession::start("stray", serde_json::json!({ "stray_reply_at": 1 }));
+
+    // Every request gets its own response even though the mock puts a stray
+    // reply in front of its second answer (again after each restart).
+    for trace in ["sync-1", "sync-2", "sync-3"] {
+        let request = Request::builder()
+            .uri("motis://localhost/api/v1/map/initial")
+            .header(protocol::TRACE_HEADER, trace)
+            .body(Vec::new())
+            .expect("build request");
+        let response = protocol::handle_motis_request(request);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).expect("json body");
+        assert_eq!(body["trace_id"], trace);
+    }
+}
+
+#[test]
+fn elevator_updates_are_posted_as_json() {
+    let _session = MockSession::start("elevators", serde_json::json!({}));
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("motis://localhost/api/update_elevator")
+        .body(br#"{"id":42,"status":"INACTIVE"}"#.to_vec())
+        .expect("build request");
+    let response = protocol::handle_motis_request(request);
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_slice(response.body()).expect("json body");
+    assert_eq!(body["path"], "/api/update_elevator");
+    assert_eq!(body["body"]["id"], 42);
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("motis://localhost/api/elevators")
+        .body(b"not json".to_vec())
+        .expect("build request");
+    let response = protocol::handle_motis_request(request);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = Request::builder()
+        .method("GET")
+        .uri("motis://localhost/api/update_elevator")
+        .body(Vec::new())
+        .expect("build request");
+    let response = protocol::handle_motis_request(request);
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()["allow"], "POST, OPTIONS");
+
+    native::set_elevator_status_sync(42, true, &[]).expect("toggle elevator");
+    assert_eq!(native::load_elevators_sync("fasta.json").expect("load elevators"), 0);
+}
+
+#[test]
+fn street_route_without_path_is_an_error() {
+    let _session = MockSession::start(
+        "street-route",
+        serde_json::json!({ "responses": { "api_post": { "error": "no path found" } } }),
+    );
+
+    let from = native::LatLon { lat: 59.3303, lon: 18.0586 };
+    let to = native::LatLon { lat: 59.3326, lon: 18.0649 };
+    let err = native::street_route_sync(&from, &to, "foot", None).expect_err("no path");
+    assert!(err.to_string().contains("no path found"), "{err}");
+}
+
+#[test]
+fn data_qa_endpoints_require_debug_mode() {
+    let _session = MockSession::start("data-qa", serde_json::json!({}));
+    let post_matches = || {
+        let request = Request::builder()
+            .method("POST")
+            .uri("motis://localhost/api/matches")
+            .body(b"[8.62, 49.86, 8.64, 49.88]".to_vec())
+            .expect("build request");
+        protocol::handle_motis_request(request)
+    };
+
+    let response = post_matches();
+    assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+
+    protocol::set_data_qa_enabled(true);
+    let response = post_matches();
+    protocol::set_data_qa_enabled(false);
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_slice(response.body()).expect("json body");
+    assert_eq!(body["path"], "/api/matches");
+}
@@ -0,0 +1,68 @@
+//! motis:// protocol regression tests against a recorded IPC session
+//! (`tests/fixtures/replay-session.jsonl`), replayed without MOTIS.
+
+use motis_gui_svelte::{native, protocol};
+use std::path::Path;
+use tauri::http::{Request, StatusCode};
+
+const RECORDING: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/replay-session.jsonl"
+);
+
+fn protocol_get(path_and_query: &str) -> (StatusCode, Vec<u8>) {
+    let request = Request::builder()
+        .uri(format!("motis://localhost{}", path_and_query))
+        .body(Vec::new())
+        .expect("build request");
+    let response = protocol::handle_motis_request(request);
+    (response.status(), response.body().to_vec())
+}
+
+fn json_body(body: &[u8]) -> serde_json::Value {
+    serde_json::from_slice(body).expect("json body")
+}
+
+#[test]
+fn protocol_replays_recorded_session() {
+    assert!(
+        Path::new(RECORDING).is_file(),
+        "missing fixture {}",
+        RECORDING
+    );
+    native::init_replay(RECORDING).expect("load recording");
+    assert_eq!(native::get_mode(), native::BackendMode::Replay);
+
+    let plan = "/api/v5/plan?fromPlace=59.3303,18.0586&toPlace=59.3326,18.0649";
+    let (status, body) = protocol_get(plan);
+    assert_eq!(status, StatusCode::OK);
+    let body = json_body(&body);
+    assert_eq!(body["itineraries"][0]["duration"], 420);
+    assert_eq!(body["itineraries"][0]["legs"][0]["mode"], "WALK");
+    // Replays are deterministic: the same request gets the same answer.
+    assert_eq!(json_body(&protocol_get(plan).1), body);
+
+    let (status, tile) = protocol_get("/api/v1/tiles/10/563/301.mvt");
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tile, b"\x1a\x02xy");
+    let (status, tile) = protocol_get("/api/v1/tiles/0/0/0.mvt");
+    assert_eq!(status, StatusCode::OK);
+    assert!(tile.is_empty(), "missing tiles are served empty");
+
+    let (status, body) = protocol_get("/api/debug/transfers?id=missing");
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    let body = json_body(&body);
+    assert_eq!(body["stage"], "endpoint");
+    assert_eq!(body["path"], "/api/debug/transfers");
+
+    // Requests that were not recorded fail instead of reaching a backend.
+    let (status, body) = protocol_get("/api/v5/plan?fromPlace=0,0&toPlace=1,1");
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    let message = json_body(&body)["error"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    assert!(message.contains("No recorded response"), "{message}");
+
+    native::destroy();
+}
@@ -142,8 +142,15 @@ cp -f "$SCRIPT_DIR/motis-gui-svelte" "$TMP_GUI" || fail "Failed to copy motis-gu
 cp -f "$SCRIPT_DIR/motis-ipc" "$TMP_IPC" || fail "Failed to copy motis-ipc to temp directory"
 chmod 755 "$TMP_GUI" "$TMP_IPC" || fail "Failed to set executable permissions in temp directory"
 
+# Optional in-process backend (MOTIS_BACKEND=embedded); must be loaded from an exec-capable mount too.
+if [ -f "$SCRIPT_DIR/libmotis-native-embedded.so" ]; then
+  cp -f "$SCRIPT_DIR/libmotis-native-embedded.so" "$TMP_DIR/" || fail "Failed to copy libmotis-native-embedded.so to temp directory"
+fi
+
 export MOTIS_DATA_PATH="$DATA_PATH"
 export MOTIS_IPC_PATH="$TMP_IPC"
+# App log goes next to launcher.log; the app falls back to ~/.cache when the stick is read-only.
+export MOTIS_LOG_DIR="${MOTIS_LOG_DIR:-$SCRIPT_DIR}"
 
 log "Data path: $MOTIS_DATA_PATH"
 log "IPC path: $MOTIS_IPC_PATH"
@@ -4,41 +4,40 @@ set -e
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 DATA_DIR="$SCRIPT_DIR/data"
 
-if [ $# -lt 2 ]; then
-    echo "Usage: $0 <gtfs.zip> <osm.pbf>"
+CONFIG_FILE="$SCRIPT_DIR/config.yml"
+
+usage() {
+    echo "Usage: $0 <gtfs.zip> <osm.pbf>   (first import: writes config.yml)"
+    echo "       $0                        (re-import with the existing config.yml)"
     echo ""
     echo "Example:"
     echo "  $0 ~/downloads/gtfs.zip ~/downloads/city.osm.pbf"
+}
+
+if [ $# -ne 0 ] && [ $# -ne 2 ]; then
+    usage
     exit 1
 fi
 
-GTFS_FILE="$1"
-OSM_FILE="$2"
-
-if [ ! -f "$GTFS_FILE" ]; then
-    echo "ERROR: GTFS file not found: $GTFS_FILE"
-    exit 1
+# data/config.yml is the file the GUI edits (added datasets, feature
+# toggles). It replaces the copy left here by the previous import so those
+# edits are imported instead of being overwritten.
+if [ -f "$DATA_DIR/config.yml" ]; then
+    cp "$DATA_DIR/config.yml" "$CONFIG_FILE"
 fi
 
-if [ ! -f "$OSM_FILE" ]; then
-    echo "ERROR: OSM file not found: $OSM_FILE"
+if [ -f "$CONFIG_FILE" ] && [ $# -eq 2 ]; then
+    echo "ERROR: $CONFIG_FILE already exists, so the GTFS and OSM arguments would be ignored."
+    echo "Change the osm/timetable paths in data/config.yml (or in the app), then run: $0"
+    echo "To start over from the arguments, delete $CONFIG_FILE and $DATA_DIR/config.yml first."
     exit 1
 fi
 
-if [ ! -f "$SCRIPT_DIR/sweden-route-fix.lua" ]; then
-    echo "ERROR: Missing route fix script: $SCRIPT_DIR/sweden-route-fix.lua"
+if [ ! -f "$CONFIG_FILE" ] && [ $# -eq 0 ]; then
+    usage
     exit 1
 fi
 
-GTFS_ABS="$(cd "$(dirname "$GTFS_FILE")" && pwd)/$(basename "$GTFS_FILE")"
-OSM_ABS="$(cd "$(dirname "$OSM_FILE")" && pwd)/$(basename "$OSM_FILE")"
-
-echo "MOTIS Data Import"
-echo "================="
-echo "GTFS: $GTFS_ABS"
-echo "OSM:  $OSM_ABS"
-echo ""
-
 if [ -f "$SCRIPT_DIR/motis" ]; then
     MOTIS_BIN="$SCRIPT_DIR/motis"
 elif [ -f "$SCRIPT_DIR/../build/motis" ]; then
@@ -49,13 +48,30 @@ else
     exit 1
 fi
 
+# The app binary checks config.yml with the same validation it applies to
+# edits made in the GUI.
+if [ -n "${MOTIS_GUI_BIN:-}" ]; then
+    GUI_BIN="$MOTIS_GUI_BIN"
+elif [ -f "$SCRIPT_DIR/motis-gui-svelte" ]; then
+    GUI_BIN="$SCRIPT_DIR/motis-gui-svelte"
+elif [ -f "$SCRIPT_DIR/../src-tauri/target/release/motis-gui-svelte" ]; then
+    GUI_BIN="$SCRIPT_DIR/../src-tauri/target/release/motis-gui-svelte"
+else
+    echo "ERROR: motis-gui-svelte executable not found (needed to check config.yml)"
+    echo "Please build the app first: cd gui-svelte/src-tauri && cargo build --release"
+    exit 1
+fi
+
+echo "MOTIS Data Import"
+echo "================="
 echo "Using: $MOTIS_BIN"
 echo ""
 
-rm -rf "$DATA_DIR"
-mkdir -p "$DATA_DIR"
+if [ ! -f "$CONFIG_FILE" ]; then
+    GTFS_ABS="$(cd "$(dirname "$1")" && pwd)/$(basename "$1")"
+    OSM_ABS="$(cd "$(dirname "$2")" && pwd)/$(basename "$2")"
 
-cat > "$SCRIPT_DIR/config.yml" << EOF
+    cat > "$CONFIG_FILE" << EOF
 osm: "$OSM_ABS"
 timetable:
   first_day: TODAY
@@ -72,6 +88,10 @@ street_routing: true
 geocoding: true
 reverse_geocoding: true
 EOF
+    echo "Config created: $CONFIG_FILE"
+else
+    echo "Reusing config: $CONFIG_FILE"
+fi
 
 if [ ! -f "$SCRIPT_DIR/tiles-profile.lua" ]; then
     if [ -f "$SCRIPT_DIR/../deps/tiles/profile/full.lua" ]; then
@@ -81,9 +101,17 @@ if [ ! -f "$SCRIPT_DIR/tiles-profile.lua" ]; then
     fi
 fi
 
-echo "Config created: $SCRIPT_DIR/config.yml"
+# Missing GTFS/OSM/script/profile files and invalid settings, with line
+# numbers. Runs before data/ is cleared, so a bad config loses nothing.
+if ! "$GUI_BIN" --check-import-config "$SCRIPT_DIR"; then
+    echo "ERROR: $CONFIG_FILE is not ready for import (see above)"
+    exit 1
+fi
 echo ""
 
+rm -rf "$DATA_DIR"
+mkdir -p "$DATA_DIR"
+
 echo "Starting import (this may take several minutes)..."
 cd "$SCRIPT_DIR"
 "$MOTIS_BIN" import
@@ -96,7 +124,23 @@ for item in adr matches flex_areas osr tt tags.bin timetable_metrics.json; do
     fi
 done
 
-cp "$SCRIPT_DIR/config.yml" "$DATA_DIR/"
+cp "$CONFIG_FILE" "$DATA_DIR/"
+
+# Integrity manifest: "<sha256> <size> <path>" per file, checked by the GUI
+# before motis-ipc starts (MOTIS_VERIFY_DATA=quick|full|off). config.yml is
+# left out on purpose so it can be edited after import.
+echo "Writing data manifest..."
+(
+    cd "$DATA_DIR"
+    echo "# motis-data-manifest v1"
+    find . -type f ! -name manifest.txt ! -name manifest.txt.tmp ! -path ./config.yml -print \
+        | sed 's|^\./||' | LC_ALL=C sort | while IFS= read -r file; do
+            sum="$(sha256sum "$file" | cut -d' ' -f1)"
+            size="$(stat -c %s "$file")"
+            echo "$sum $size $file"
+        done
+) > "$DATA_DIR/manifest.txt.tmp"
+mv "$DATA_DIR/manifest.txt.tmp" "$DATA_DIR/manifest.txt"
 
 echo ""
 echo "Import complete!"
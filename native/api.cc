@@ -1,5 +1,4 @@
 #include "native/api_internal.h"
-#include "native/base64.h"
 
 #include <cctype>
 #include <iomanip>
@@ -28,6 +27,8 @@ namespace {
 std::mutex g_fault_mutex;
 std::optional<std::string> g_fault_once;
 
+thread_local std::string t_trace_id;
+
 void maybe_throw_injected_fault(char const* fault_name) {
   std::lock_guard const lock{g_fault_mutex};
   if (!g_fault_once || *g_fault_once != fault_name) {
@@ -40,6 +41,12 @@ void maybe_throw_injected_fault(char const* fault_name) {
 
 }  // namespace
 
+void set_trace_id(std::string trace_id) { t_trace_id = std::move(trace_id); }
+
+std::string trace_log_prefix() {
+  return t_trace_id.empty() ? std::string{} : "[trace=" + t_trace_id + "] ";
+}
+
 namespace test_support {
 
 void inject_fault_once(std::string fault_name) {
@@ -183,102 +190,6 @@ std::vector<route> plan_route(native_instance& inst,
   return results;
 }
 
-std::vector<location> geocode(native_instance& inst, std::string const& query) {
-  maybe_throw_injected_fault("geocode");
-  std::vector<location> results;
-
-  if (!inst.data_.t_ || !inst.data_.f_ || !inst.data_.tc_) {
-    return results;
-  }
-
-  std::string url_str = "/api/v1/geocode?text=" + url_encode(query);
-  auto url = boost::urls::url_view(url_str);
-
-  auto geocoder = motis::ep::geocode{
-      inst.data_.w_.get(),       inst.data_.pl_.get(), inst.data_.matches_.get(),
-      inst.data_.tt_.get(),      inst.data_.tags_.get(), *inst.data_.t_,
-      *inst.data_.f_,            *inst.data_.tc_, inst.data_.adr_ext_.get()};
-
-  auto response = geocoder(url);
-  for (auto const& place : response) {
-    location loc;
-    loc.name = place.name_;
-    loc.place_id = place.id_;
-    loc.pos.lat = place.lat_;
-    loc.pos.lon = place.lon_;
-    loc.score = place.score_;
-
-    // Type (not optional in Match)
-    switch (place.type_) {
-      case api::LocationTypeEnum::STOP:
-        loc.type = "STOP";
-        break;
-      case api::LocationTypeEnum::PLACE:
-        loc.type = "PLACE";
-        break;
-      case api::LocationTypeEnum::ADDRESS:
-        loc.type = "ADDRESS";
-        break;
-    }
-
-    // Category
-    if (place.category_) {
-      loc.category = *place.category_;
-    }
-
-    // Areas
-    for (auto const& a : place.areas_) {
-      area ar;
-      ar.name = a.name_;
-      ar.admin_level = static_cast<int>(a.adminLevel_);
-      ar.matched = a.matched_;
-      ar.unique = a.unique_.value_or(false);
-      ar.is_default = a.default_.value_or(false);
-      loc.areas.push_back(std::move(ar));
-    }
-
-    // Tokens
-    for (auto const& t : place.tokens_) {
-      if (t.size() >= 2) {
-        loc.tokens.push_back(
-            token{static_cast<int>(t[0]), static_cast<int>(t[1])});
-      }
-    }
-
-    // Modes
-    if (place.modes_) {
-      std::vector<std::string> mode_strs;
-      for (auto const& m : *place.modes_) {
-        mode_strs.push_back(mode_to_string(m));
-      }
-      loc.modes = std::move(mode_strs);
-    }
-
-    // Importance
-    if (place.importance_) {
-      loc.importance = *place.importance_;
-    }
-
-    // Address fields
-    if (place.street_) {
-      loc.street = *place.street_;
-    }
-    if (place.houseNumber_) {
-      loc.house_number = *place.houseNumber_;
-    }
-    if (place.country_) {
-      loc.country = *place.country_;
-    }
-    if (place.zip_) {
-      loc.zip = *place.zip_;
-    }
-
-    results.push_back(std::move(loc));
-  }
-
-  return results;
-}
-
 // Helper to convert API Match to location struct
 static location match_to_location(api::Match const& match) {
   location loc;
@@ -353,9 +264,80 @@ static location match_to_location(api::Match const& match) {
     loc.zip = *match.zip_;
   }
 
+  // Stop timezone and indoor level
+  if (match.tz_) {
+    loc.tz = *match.tz_;
+  }
+  if (match.level_) {
+    loc.level = *match.level_;
+  }
+
   return loc;
 }
 
+static std::string geocode_url(std::string const& query,
+                               geocode_options const& opts) {
+  auto const join = [](std::vector<std::string> const& items) {
+    auto joined = std::string{};
+    for (auto const& item : items) {
+      if (!joined.empty()) {
+        joined += ',';
+      }
+      joined += item;
+    }
+    return joined;
+  };
+
+  std::ostringstream url;
+  url << "/api/v1/geocode?text=" << url_encode(query);
+  if (opts.type) {
+    url << "&type=" << url_encode(*opts.type);
+  }
+  if (!opts.modes.empty()) {
+    url << "&mode=" << url_encode(join(opts.modes));
+  }
+  if (opts.place) {
+    url << std::setprecision(9) << "&place=" << opts.place->lat << ","
+        << opts.place->lon;
+  }
+  if (opts.place_bias) {
+    url << "&placeBias=" << *opts.place_bias;
+  }
+  if (!opts.language.empty()) {
+    url << "&language=" << url_encode(join(opts.language));
+  }
+  return url.str();
+}
+
+std::vector<location> geocode(native_instance& inst,
+                              std::string const& query,
+                              geocode_options const& opts) {
+  maybe_throw_injected_fault("geocode");
+  std::vector<location> results;
+
+  if (!inst.data_.t_ || !inst.data_.f_ || !inst.data_.tc_) {
+    return results;
+  }
+
+  auto const url_str = geocode_url(query, opts);
+  auto url = boost::urls::url_view(url_str);
+
+  auto geocoder = motis::ep::geocode{
+      inst.data_.w_.get(),       inst.data_.pl_.get(), inst.data_.matches_.get(),
+      inst.data_.tt_.get(),      inst.data_.tags_.get(), *inst.data_.t_,
+      *inst.data_.f_,            *inst.data_.tc_, inst.data_.adr_ext_.get()};
+
+  auto response = geocoder(url);
+  for (auto const& place : response) {
+    if (opts.limit && results.size() >= *opts.limit) {
+      break;
+    }
+    results.push_back(match_to_location(place));
+  }
+
+  return results;
+}
+
 std::optional<location> reverse_geocode(native_instance& inst, coord pos) {
   if (!inst.data_.r_ || !inst.data_.t_ || !inst.data_.f_) {
     return std::nullopt;
@@ -383,7 +365,8 @@ std::optional<location> reverse_geocode(native_instance& inst, coord pos) {
     }
     return match_to_location(response[0]);
   } catch (std::exception const& e) {
-    std::cerr << "Reverse geocode error: " << e.what() << "\n";
+    std::cerr << trace_log_prefix() << "Reverse geocode error: " << e.what()
+              << "\n";
     return std::nullopt;
   }
 }
@@ -393,13 +376,14 @@ tile_result get_tile(native_instance& inst, int z, int x, int y) {
   result.found = false;
 
   if (!inst.data_.tiles_) {
-    std::cerr << "Tiles data not available\n";
+    std::cerr << trace_log_prefix() << "Tiles data not available\n";
     return result;
   }
 
   try {
     if (z < 0 || z > static_cast<int>(::tiles::kMaxZoomLevel)) {
-      std::cerr << "Tile fetch error: invalid z=" << z << "\n";
+      std::cerr << trace_log_prefix() << "Tile fetch error: invalid z=" << z
+                << "\n";
       return result;
     }
 
@@ -407,8 +391,8 @@ tile_result get_tile(native_instance& inst, int z, int x, int y) {
         (1ULL << static_cast<unsigned>(z)) - 1ULL;
     if (x < 0 || y < 0 || static_cast<unsigned long long>(x) > max_coord ||
         static_cast<unsigned long long>(y) > max_coord) {
-      std::cerr << "Tile fetch error: invalid x/y for z (" << x << "," << y
-                << "," << z << ")\n";
+      std::cerr << trace_log_prefix() << "Tile fetch error: invalid x/y for z ("
+                << x << "," << y << "," << z << ")\n";
       return result;
     }
 
@@ -426,12 +410,12 @@ tile_result get_tile(native_instance& inst, int z, int x, int y) {
         pc);
 
     if (rendered_tile) {
-      result.data_base64 = encode_base64(*rendered_tile);
+      result.data = std::move(*rendered_tile);
       result.found = true;
     }
 
   } catch (std::exception const& e) {
-    std::cerr << "Tile fetch error: " << e.what() << "\n";
+    std::cerr << trace_log_prefix() << "Tile fetch error: " << e.what() << "\n";
   }
 
   return result;
@@ -460,14 +444,13 @@ glyph_result get_glyph(native_instance& /*inst*/, std::string const& path) {
     }
 
     auto const mem = pbf_sdf_fonts_res::get_resource(res_name);
-    auto const payload = std::string{
+    result.data = std::string{
         reinterpret_cast<char const*>(mem.ptr_), static_cast<std::size_t>(mem.size_)};
-    result.data_base64 = encode_base64(payload);
     result.found = true;
   } catch (std::out_of_range const&) {
     // Glyph not found in embedded resources.
   } catch (std::exception const& e) {
-    std::cerr << "Glyph fetch error: " << e.what() << "\n";
+    std::cerr << trace_log_prefix() << "Glyph fetch error: " << e.what() << "\n";
   }
 
   return result;
@@ -479,7 +462,31 @@ std::optional<std::string> api_get(native_instance& inst,
     maybe_throw_injected_fault("api_get");
     return dispatch_api_get(inst, path_and_query);
   } catch (std::exception const& e) {
-    std::cerr << "api_get error: " << e.what() << "\n";
+    std::cerr << trace_log_prefix() << "api_get error: " << e.what() << "\n";
+    return std::nullopt;
+  }
+}
+
+std::optional<std::string> api_post(native_instance& inst,
+                                    std::string const& path,
+                                    std::string const& body) {
+  try {
+    maybe_throw_injected_fault("api_post");
+    return dispatch_api_post(inst, path, body);
+  } catch (std::exception const& e) {
+    std::cerr << trace_log_prefix() << "api_post error: " << e.what() << "\n";
+    return std::nullopt;
+  }
+}
+
+std::optional<std::size_t> load_elevators(native_instance& inst,
+                                          std::string const& fasta_path) {
+  try {
+    maybe_throw_injected_fault("load_elevators");
+    return load_elevators_file(inst, fasta_path);
+  } catch (std::exception const& e) {
+    std::cerr << trace_log_prefix() << "load_elevators error: " << e.what()
+              << "\n";
     return std::nullopt;
   }
 }
@@ -1,5 +1,6 @@
 #pragma once
 
+#include <cstddef>
 #include <optional>
 #include <string>
 #include <vector>
@@ -65,6 +66,32 @@ struct location {
   std::optional<std::string> house_number;
   std::optional<std::string> country;
   std::optional<std::string> zip;
+  std::optional<std::string> tz;    // IANA timezone (e.g. "Europe/Stockholm")
+  std::optional<double> level;      // OSM indoor level (public transport)
+};
+
+// Agency (GTFS provider) of the loaded timetable
+struct agency {
+  std::string id;
+  std::string name;
+};
+
+// Geographic bounding box
+struct bbox {
+  coord min;
+  coord max;
+};
+
+// Metadata describing the loaded data directory
+struct dataset_info {
+  std::vector<std::string> feeds;            // Timetable dataset names
+  std::optional<std::string> service_from;   // First day with service (YYYY-MM-DD)
+  std::optional<std::string> service_to;     // Last day with service (YYYY-MM-DD)
+  std::vector<agency> agencies;
+  std::size_t n_stops;
+  std::size_t n_routes;
+  std::optional<bbox> osm_bbox;              // Extent of the imported OSM extract
+  std::optional<std::string> imported_at;    // Import timestamp (ISO 8601, UTC)
 };
 
 // Opaque handle to MOTIS instance
@@ -84,39 +111,73 @@ std::vector<route> plan_route(native_instance& inst,
                               std::optional<std::string> departure_time =
                                   std::nullopt);
 
+// Geocode filters and bias (same meaning as the /api/v1/geocode params)
+struct geocode_options {
+  std::optional<std::string> type;   // "STOP", "PLACE" or "ADDRESS"
+  std::vector<std::string> modes;    // Stop modes, e.g. {"BUS", "TRAM"}
+  std::optional<coord> place;        // Bias results towards this coordinate
+  std::optional<double> place_bias;  // Bias strength (MOTIS default: 1)
+  std::vector<std::string> language; // OSM language tags
+  std::optional<std::size_t> limit;  // Max results, applied after ranking
+};
+
 // Geocoding
 std::vector<location> geocode(native_instance& inst,
-                              std::string const& query);
+                              std::string const& query,
+                              geocode_options const& opts = {});
 
 // Reverse geocoding
 std::optional<location> reverse_geocode(native_instance& inst, coord pos);
 
-// Tile data (base64 encoded MVT)
+// Tile data (raw MVT bytes)
 struct tile_result {
-  std::string data_base64;  // Base64 encoded tile data
+  std::string data;
   bool found;
 };
 
 // Get map tile (MVT format)
 tile_result get_tile(native_instance& inst, int z, int x, int y);
 
-// Glyph data (base64 encoded PBF)
+// Glyph data (raw PBF bytes)
 struct glyph_result {
-  std::string data_base64;
+  std::string data;
   bool found;
 };
 
 // Get glyph data from embedded SDF font resources.
 glyph_result get_glyph(native_instance& inst, std::string const& path);
 
+// Dataset metadata (feeds, service range, agencies, counts, OSM extent)
+dataset_info get_dataset_info(native_instance& inst);
+
 // Call supported MOTIS GET endpoints and return JSON payload.
 // Input must be a path such as "/api/v1/stoptimes?stopId=...".
 std::optional<std::string> api_get(native_instance& inst,
                                    std::string const& path_and_query);
 
+// Call supported MOTIS POST endpoints with a JSON body and return the JSON
+// payload. Input must be a path such as "/api/update_elevator".
+std::optional<std::string> api_post(native_instance& inst,
+                                    std::string const& path,
+                                    std::string const& body);
+
+// Replace the elevator status with a FASTA JSON export (same format as the
+// `elevators.init` config file). Returns the number of elevators loaded.
+std::optional<std::size_t> load_elevators(native_instance& inst,
+                                          std::string const& fasta_path);
+
+// Trace id of the GUI request handled on the calling thread. Diagnostics
+// written to stderr while it is set start with "[trace=<id>] ". An empty id
+// clears it.
+void set_trace_id(std::string trace_id);
+
+// "[trace=<id>] " while a trace id is set on this thread, otherwise "".
+std::string trace_log_prefix();
+
 namespace test_support {
 // Test hook to inject a one-time exception into selected wrappers.
-// Valid hook names: "plan_route", "geocode", "api_get".
+// Valid hook names: "plan_route", "geocode", "api_get", "api_post",
+// "load_elevators".
 void inject_fault_once(std::string fault_name);
 void clear_fault_injection();
 }  // namespace test_support
@@ -1,18 +1,33 @@
 #include "native/api_internal.h"
 
+#include <memory>
 #include <optional>
 #include <string>
 
 #include "boost/json.hpp"
 #include "boost/url/url_view.hpp"
 
+#include "cista/mmap.h"
+
+#include "nigiri/rt/create_rt_timetable.h"
+#include "nigiri/rt/rt_timetable.h"
+
+#include "motis/elevators/elevators.h"
+#include "motis/elevators/update_elevators.h"
+#include "motis/endpoints/elevators.h"
+#include "motis/endpoints/graph.h"
 #include "motis/endpoints/initial.h"
 #include "motis/endpoints/levels.h"
+#include "motis/endpoints/matches.h"
 #include "motis/endpoints/stop_times.h"
+#include "motis/endpoints/transfers.h"
 #include "motis/endpoints/trip.h"
+#include "motis/endpoints/update_elevator.h"
 #include "motis/endpoints/one_to_all.h"
 #include "motis/endpoints/one_to_many.h"
+#include "motis/endpoints/osr_routing.h"
 #include "motis/endpoints/routing.h"
+#include "motis/endpoints/map/flex_locations.h"
 #include "motis/endpoints/map/stops.h"
 #include "motis/endpoints/map/trips.h"
 #include "motis/endpoints/map/rental.h"
@@ -33,6 +48,34 @@ static std::optional<std::string> run_api_endpoint(
   return boost::json::serialize(boost::json::value_from(endpoint(url)));
 }
 
+template <typename EndpointFactory>
+static std::optional<std::string> run_post_endpoint(
+    bool const preconditions_ok,
+    std::string const& body,
+    EndpointFactory&& endpoint_factory) {
+  if (!preconditions_ok) {
+    return std::nullopt;
+  }
+  auto endpoint = endpoint_factory();
+  return boost::json::serialize(
+      boost::json::value_from(endpoint(boost::json::parse(body))));
+}
+
+static bool elevators_supported(native_instance const& inst) {
+  return inst.data_.rt_ && inst.data_.w_ && inst.data_.elevator_nodes_;
+}
+
+// Street routing is loaded but no elevator status was configured: start from
+// an empty set (every elevator active) so status can be loaded or toggled.
+// Only call once elevators_supported() holds.
+static void ensure_elevators(native_instance& inst) {
+  if (!inst.data_.rt_->e_) {
+    inst.data_.rt_->e_ = std::make_unique<motis::elevators>(
+        *inst.data_.w_, *inst.data_.elevator_nodes_,
+        vector_map<elevator_idx_t, elevator>{});
+  }
+}
+
 std::optional<std::string> dispatch_api_get(
     native_instance& inst, std::string const& path_and_query) {
   auto const url = boost::urls::url_view{path_and_query};
@@ -227,7 +270,119 @@ std::optional<std::string> dispatch_api_get(
     });
   }
 
+  if (path == "/api/debug/transfers") {
+    return run_api_endpoint(
+        inst.config_.timetable_.has_value() && inst.data_.w_ &&
+            inst.data_.l_ && inst.data_.pl_ && inst.data_.matches_ &&
+            inst.data_.location_rtree_ && inst.data_.tt_ && inst.data_.tags_ &&
+            inst.data_.rt_,
+        url,
+        [&] {
+          return motis::ep::transfers{
+              inst.config_,                *inst.data_.tags_,
+              *inst.data_.tt_,             *inst.data_.w_,
+              *inst.data_.l_,              *inst.data_.pl_,
+              *inst.data_.location_rtree_, *inst.data_.matches_,
+              inst.data_.rt_};
+        });
+  }
+
+  if (path == "/api/debug/flex") {
+    return run_api_endpoint(
+        inst.data_.tt_ && inst.data_.tags_ && inst.data_.location_rtree_, url,
+        [&] {
+          return motis::ep::flex_locations{*inst.data_.tags_, *inst.data_.tt_,
+                                           *inst.data_.location_rtree_};
+        });
+  }
+
+  return std::nullopt;
+}
+
+std::optional<std::string> dispatch_api_post(native_instance& inst,
+                                             std::string const& path,
+                                             std::string const& body) {
+  if (path == "/api/elevators") {
+    return run_post_endpoint(
+        inst.data_.rt_ && inst.data_.w_ && inst.data_.l_, body, [&] {
+          return motis::ep::elevators{
+              inst.data_.rt_, *inst.data_.w_, *inst.data_.l_};
+        });
+  }
+
+  if (path == "/api/route") {
+    return run_post_endpoint(
+        inst.data_.rt_ && inst.data_.w_ && inst.data_.l_, body, [&] {
+          return motis::ep::osr_routing{
+              *inst.data_.w_, *inst.data_.l_, inst.data_.rt_};
+        });
+  }
+
+  if (path == "/api/matches") {
+    return run_post_endpoint(
+        inst.data_.location_rtree_ && inst.data_.tags_ && inst.data_.tt_ &&
+            inst.data_.w_ && inst.data_.l_ && inst.data_.pl_,
+        body,
+        [&] {
+          return motis::ep::matches{
+              *inst.data_.location_rtree_, *inst.data_.tags_, *inst.data_.tt_,
+              *inst.data_.w_,              *inst.data_.l_,    *inst.data_.pl_};
+        });
+  }
+
+  if (path == "/api/graph") {
+    return run_post_endpoint(inst.data_.w_ && inst.data_.l_, body, [&] {
+      return motis::ep::graph{*inst.data_.w_, *inst.data_.l_};
+    });
+  }
+
+  if (path == "/api/update_elevator") {
+    auto const preconditions_ok =
+        inst.config_.timetable_.has_value() && inst.data_.l_ &&
+        inst.data_.pl_ && inst.data_.matches_ && inst.data_.location_rtree_ &&
+        inst.data_.tt_ && elevators_supported(inst);
+    if (preconditions_ok) {
+      ensure_elevators(inst);
+    }
+    return run_post_endpoint(
+        preconditions_ok,
+        body,
+        [&] {
+          return motis::ep::update_elevator{
+              inst.config_,                *inst.data_.tt_,
+              *inst.data_.w_,              *inst.data_.l_,
+              *inst.data_.pl_,             *inst.data_.location_rtree_,
+              *inst.data_.elevator_nodes_, *inst.data_.matches_,
+              inst.data_.rt_};
+        });
+  }
+
   return std::nullopt;
 }
 
+std::optional<std::size_t> load_elevators_file(native_instance& inst,
+                                               std::string const& fasta_path) {
+  if (!inst.config_.timetable_.has_value() || !inst.data_.l_ ||
+      !inst.data_.pl_ || !inst.data_.matches_ || !inst.data_.location_rtree_ ||
+      !inst.data_.tt_ || !elevators_supported(inst) ||
+      !inst.data_.rt_->rtt_) {
+    return std::nullopt;
+  }
+  ensure_elevators(inst);
+
+  // Same steps as for the `elevators.init` file at startup; the footpaths of
+  // stations near changed elevators are recomputed in a fresh RT timetable.
+  auto const fasta =
+      cista::mmap{fasta_path.c_str(), cista::mmap::protection::READ};
+  auto new_rtt = std::make_unique<nigiri::rt_timetable>(
+      nigiri::rt::create_rt_timetable(*inst.data_.tt_,
+                                      inst.data_.rt_->rtt_->base_day_));
+  auto new_e =
+      update_elevators(inst.config_, inst.data_, fasta.view(), *new_rtt);
+  auto const n_elevators = new_e->elevators_.size();
+  inst.data_.rt_ = std::make_shared<rt>(std::move(new_rtt), std::move(new_e),
+                                        std::move(inst.data_.rt_->railviz_rt_));
+  return n_elevators;
+}
+
 }  // namespace motis::native
@@ -1,5 +1,6 @@
 #pragma once
 
+#include <optional>
 #include <string>
 
 #include "native/api.h"
@@ -14,9 +15,21 @@ struct native_instance {
 
   motis::data data_;
   motis::config config_;
+
+  // Extent of the street graph for dataset_info. The graph does not change
+  // after loading, so the node walk runs once, on first request.
+  bool osm_bbox_computed_{false};
+  std::optional<bbox> osm_bbox_;
 };
 
 std::optional<std::string> dispatch_api_get(
     native_instance& inst, std::string const& path_and_query);
 
+std::optional<std::string> dispatch_api_post(native_instance& inst,
+                                             std::string const& path,
+                                             std::string const& body);
+
+std::optional<std::size_t> load_elevators_file(native_instance& inst,
+                                               std::string const& fasta_path);
+
 }  // namespace motis::native
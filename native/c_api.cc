@@ -0,0 +1,403 @@
+#include "native/c_api.h"
+
+#include <cstdlib>
+#include <cstring>
+#include <exception>
+#include <memory>
+#include <optional>
+#include <string>
+#include <utility>
+#include <vector>
+
+#include "native/api.h"
+#include "native/ipc_json.h"
+
+struct motis_native_handle {
+  motis::native::native_instance* inst_;
+};
+
+namespace {
+
+char* copy_string(std::string const& s) {
+  auto const out = static_cast<char*>(std::malloc(s.size() + 1U));
+  if (out != nullptr) {
+    std::memcpy(out, s.c_str(), s.size() + 1U);
+  }
+  return out;
+}
+
+char* error_response(std::string const& msg) {
+  // Hand-built so this path does not depend on the JSON library.
+  auto escaped = std::string{};
+  for (auto const c : msg) {
+    if (c == '"' || c == '\\') {
+      escaped += '\\';
+    }
+    escaped += static_cast<unsigned char>(c) < 0x20U ? ' ' : c;
+  }
+  return copy_string(R"({"status":"error","message":")" + escaped + R"("})");
+}
+
+void set_error(char** error, std::string const& msg) {
+  if (error != nullptr) {
+    *error = copy_string(msg);
+  }
+}
+
+void clear_error(char** error) {
+  if (error != nullptr) {
+    *error = nullptr;
+  }
+}
+
+char const* c_str_or_null(std::optional<std::string> const& s) {
+  return s ? s->c_str() : nullptr;
+}
+
+// Keeps the C++ results alive so the C views can point into them.
+struct routes_storage {
+  std::vector<motis::native::route> src_;
+  std::vector<std::vector<motis_native_leg>> legs_;
+  std::vector<motis_native_route> routes_;
+};
+
+struct locations_storage {
+  std::vector<motis::native::location> src_;
+  std::vector<std::vector<motis_native_area>> areas_;
+  std::vector<std::vector<int>> tokens_;
+  std::vector<std::vector<char const*>> modes_;
+  std::vector<motis_native_location> locations_;
+};
+
+std::vector<std::string> strings_from(char const* const* values,
+                                      std::size_t n) {
+  auto out = std::vector<std::string>{};
+  if (values == nullptr) {
+    return out;
+  }
+  for (auto i = std::size_t{0U}; i != n; ++i) {
+    if (values[i] != nullptr) {
+      out.emplace_back(values[i]);
+    }
+  }
+  return out;
+}
+
+motis::native::geocode_options geocode_options_from(
+    motis_native_geocode_options const* options) {
+  auto opts = motis::native::geocode_options{};
+  if (options == nullptr) {
+    return opts;
+  }
+  if (options->type != nullptr) {
+    opts.type = options->type;
+  }
+  opts.modes = strings_from(options->modes, options->n_modes);
+  if (options->has_place != 0) {
+    opts.place = motis::native::coord{options->place_lat, options->place_lon};
+  }
+  if (options->has_place_bias != 0) {
+    opts.place_bias = options->place_bias;
+  }
+  opts.language = strings_from(options->language, options->n_language);
+  if (options->limit != 0U) {
+    opts.limit = options->limit;
+  }
+  return opts;
+}
+
+// Returns 1 and hands out a malloc'd copy of `bytes`, or -1 when out of memory.
+int copy_bytes(std::string const& bytes,
+               unsigned char** data,
+               std::size_t* size,
+               char** error) {
+  auto const out = static_cast<unsigned char*>(
+      std::malloc(bytes.empty() ? 1U : bytes.size()));
+  if (out == nullptr) {
+    set_error(error, "Out of memory");
+    return -1;
+  }
+  std::memcpy(out, bytes.data(), bytes.size());
+  *data = out;
+  *size = bytes.size();
+  return 1;
+}
+
+}  // namespace
+
+extern "C" {
+
+motis_native_handle* motis_native_init(char const* data_path, char** error) {
+  if (error != nullptr) {
+    *error = nullptr;
+  }
+  try {
+    auto const inst = motis::native::init(data_path == nullptr ? "" : data_path);
+    if (inst == nullptr) {
+      if (error != nullptr) {
+        *error = copy_string("Failed to initialize MOTIS");
+      }
+      return nullptr;
+    }
+    return new motis_native_handle{inst};
+  } catch (std::exception const& e) {
+    if (error != nullptr) {
+      *error = copy_string(std::string{"Failed to initialize MOTIS: "} + e.what());
+    }
+    return nullptr;
+  } catch (...) {
+    if (error != nullptr) {
+      *error = copy_string("Failed to initialize MOTIS: unknown error");
+    }
+    return nullptr;
+  }
+}
+
+void motis_native_destroy(motis_native_handle* handle) {
+  if (handle == nullptr) {
+    return;
+  }
+  motis::native::destroy(handle->inst_);
+  delete handle;
+}
+
+char* motis_native_command(motis_native_handle* handle,
+                           char const* request_json) {
+  if (handle == nullptr || request_json == nullptr) {
+    return error_response("Embedded backend not initialized");
+  }
+  try {
+    auto const response =
+        motis::native::handle_json_command(*handle->inst_, request_json);
+    auto const out = copy_string(response);
+    return out != nullptr ? out : error_response("Out of memory");
+  } catch (std::exception const& e) {
+    return error_response(std::string{"Error: "} + e.what());
+  } catch (...) {
+    return error_response("Error: unknown exception");
+  }
+}
+
+void motis_native_free_string(char* s) { std::free(s); }
+
+void motis_native_set_trace_id(char const* trace_id) {
+  motis::native::set_trace_id(trace_id == nullptr ? "" : trace_id);
+}
+
+int motis_native_plan_route(motis_native_handle* handle,
+                            double from_lat,
+                            double from_lon,
+                            double to_lat,
+                            double to_lon,
+                            char const* departure_time,
+                            motis_native_routes* out,
+                            char** error) {
+  clear_error(error);
+  if (handle == nullptr || out == nullptr) {
+    set_error(error, "Embedded backend not initialized");
+    return -1;
+  }
+  *out = motis_native_routes{0U, nullptr, nullptr};
+  try {
+    auto storage = std::make_unique<routes_storage>();
+    storage->src_ = motis::native::plan_route(
+        *handle->inst_, {from_lat, from_lon}, {to_lat, to_lon},
+        departure_time == nullptr
+            ? std::nullopt
+            : std::optional<std::string>{departure_time});
+    for (auto const& r : storage->src_) {
+      auto& legs = storage->legs_.emplace_back();
+      for (auto const& l : r.legs) {
+        legs.push_back(motis_native_leg{
+            l.mode.c_str(), l.from_name.c_str(), l.to_name.c_str(),
+            l.from.lat, l.from.lon, l.to.lat, l.to.lon, l.duration_seconds,
+            l.distance_meters, c_str_or_null(l.route_short_name),
+            c_str_or_null(l.headsign)});
+      }
+    }
+    for (auto i = std::size_t{0U}; i != storage->src_.size(); ++i) {
+      storage->routes_.push_back(motis_native_route{
+          storage->src_[i].duration_seconds, storage->src_[i].transfers,
+          storage->legs_[i].size(), storage->legs_[i].data()});
+    }
+    auto const n_routes = storage->routes_.size();
+    auto const routes = storage->routes_.data();
+    *out = motis_native_routes{n_routes, routes, storage.release()};
+    return 0;
+  } catch (std::exception const& e) {
+    set_error(error, std::string{"Error: "} + e.what());
+    return -1;
+  } catch (...) {
+    set_error(error, "Error: unknown exception");
+    return -1;
+  }
+}
+
+void motis_native_free_routes(motis_native_routes* routes) {
+  if (routes == nullptr) {
+    return;
+  }
+  delete static_cast<routes_storage*>(routes->storage);
+  *routes = motis_native_routes{0U, nullptr, nullptr};
+}
+
+int motis_native_geocode(motis_native_handle* handle,
+                         char const* query,
+                         motis_native_geocode_options const* options,
+                         motis_native_locations* out,
+                         char** error) {
+  clear_error(error);
+  if (handle == nullptr || out == nullptr) {
+    set_error(error, "Embedded backend not initialized");
+    return -1;
+  }
+  *out = motis_native_locations{0U, nullptr, nullptr};
+  try {
+    auto storage = std::make_unique<locations_storage>();
+    storage->src_ = motis::native::geocode(*handle->inst_,
+                                           query == nullptr ? "" : query,
+                                           geocode_options_from(options));
+    for (auto const& loc : storage->src_) {
+      auto& areas = storage->areas_.emplace_back();
+      for (auto const& a : loc.areas) {
+        areas.push_back(motis_native_area{a.name.c_str(), a.admin_level,
+                                          a.matched ? 1 : 0, a.unique ? 1 : 0,
+                                          a.is_default ? 1 : 0});
+      }
+      auto& tokens = storage->tokens_.emplace_back();
+      for (auto const& t : loc.tokens) {
+        tokens.push_back(t.start);
+        tokens.push_back(t.length);
+      }
+      auto& modes = storage->modes_.emplace_back();
+      if (loc.modes) {
+        for (auto const& m : *loc.modes) {
+          modes.push_back(m.c_str());
+        }
+      }
+    }
+    for (auto i = std::size_t{0U}; i != storage->src_.size(); ++i) {
+      auto const& loc = storage->src_[i];
+      storage->locations_.push_back(motis_native_location{
+          loc.name.c_str(), loc.place_id.c_str(), loc.pos.lat, loc.pos.lon,
+          c_str_or_null(loc.type), loc.score, c_str_or_null(loc.category),
+          storage->areas_[i].size(), storage->areas_[i].data(),
+          loc.tokens.size(), storage->tokens_[i].data(),
+          loc.modes.has_value() ? 1 : 0, storage->modes_[i].size(),
+          storage->modes_[i].data(), loc.importance.has_value() ? 1 : 0,
+          loc.importance.value_or(0.0), c_str_or_null(loc.street),
+          c_str_or_null(loc.house_number), c_str_or_null(loc.country),
+          c_str_or_null(loc.zip), c_str_or_null(loc.tz),
+          loc.level.has_value() ? 1 : 0, loc.level.value_or(0.0)});
+    }
+    auto const n_locations = storage->locations_.size();
+    auto const locations = storage->locations_.data();
+    *out = motis_native_locations{n_locations, locations, storage.release()};
+    return 0;
+  } catch (std::exception const& e) {
+    set_error(error, std::string{"Error: "} + e.what());
+    return -1;
+  } catch (...) {
+    set_error(error, "Error: unknown exception");
+    return -1;
+  }
+}
+
+void motis_native_free_locations(motis_native_locations* locations) {
+  if (locations == nullptr) {
+    return;
+  }
+  delete static_cast<locations_storage*>(locations->storage);
+  *locations = motis_native_locations{0U, nullptr, nullptr};
+}
+
+int motis_native_get_tile(motis_native_handle* handle,
+                          int z,
+                          int x,
+                          int y,
+                          unsigned char** data,
+                          std::size_t* size,
+                          char** error) {
+  clear_error(error);
+  if (handle == nullptr || data == nullptr || size == nullptr) {
+    set_error(error, "Embedded backend not initialized");
+    return -1;
+  }
+  *data = nullptr;
+  *size = 0U;
+  try {
+    auto const tile = motis::native::get_tile(*handle->inst_, z, x, y);
+    return tile.found ? copy_bytes(tile.data, data, size, error) : 0;
+  } catch (std::exception const& e) {
+    set_error(error, std::string{"Error: "} + e.what());
+    return -1;
+  } catch (...) {
+    set_error(error, "Error: unknown exception");
+    return -1;
+  }
+}
+
+int motis_native_get_glyph(motis_native_handle* handle,
+                           char const* path,
+                           unsigned char** data,
+                           std::size_t* size,
+                           char** error) {
+  clear_error(error);
+  if (handle == nullptr || data == nullptr || size == nullptr) {
+    set_error(error, "Embedded backend not initialized");
+    return -1;
+  }
+  *data = nullptr;
+  *size = 0U;
+  if (path == nullptr || *path == '\0') {
+    set_error(error, "Missing path");
+    return -1;
+  }
+  try {
+    auto const glyph = motis::native::get_glyph(*handle->inst_, path);
+    return glyph.found ? copy_bytes(glyph.data, data, size, error) : 0;
+  } catch (std::exception const& e) {
+    set_error(error, std::string{"Error: "} + e.what());
+    return -1;
+  } catch (...) {
+    set_error(error, "Error: unknown exception");
+    return -1;
+  }
+}
+
+void motis_native_free_bytes(unsigned char* data) { std::free(data); }
+
+char* motis_native_api_get(motis_native_handle* handle,
+                           char const* path_and_query,
+                           char** error) {
+  clear_error(error);
+  if (handle == nullptr) {
+    set_error(error, "Embedded backend not initialized");
+    return nullptr;
+  }
+  if (path_and_query == nullptr || *path_and_query == '\0') {
+    set_error(error, "Missing path");
+    return nullptr;
+  }
+  try {
+    auto const payload = motis::native::api_get(*handle->inst_, path_and_query);
+    if (!payload) {
+      set_error(error, std::string{"Unsupported endpoint or endpoint failed: "} +
+                           path_and_query);
+      return nullptr;
+    }
+    auto const out = copy_string(*payload);
+    if (out == nullptr) {
+      set_error(error, "Out of memory");
+    }
+    return out;
+  } catch (std::exception const& e) {
+    set_error(error, std::string{"Error: "} + e.what());
+    return nullptr;
+  } catch (...) {
+    set_error(error, "Error: unknown exception");
+    return nullptr;
+  }
+}
+
+}  // extern "C"
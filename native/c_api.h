@@ -0,0 +1,183 @@
+#pragma once
+
+// C ABI for loading the native API in-process (GUI "embedded" backend).
+// Route planning, geocoding, tiles, glyphs and `api_get` have typed entry
+// points; everything else goes through `motis_native_command`, which uses
+// the same JSON format as motis-ipc.
+
+#include <stddef.h>
+
+#if defined(MOTIS_NATIVE_C_API_EXPORT) && defined(__GNUC__)
+#define MOTIS_NATIVE_API __attribute__((visibility("default")))
+#else
+#define MOTIS_NATIVE_API
+#endif
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct motis_native_handle motis_native_handle;
+
+// Load the data directory. Returns NULL on failure; if `error` is not NULL it
+// receives a message that must be released with motis_native_free_string.
+MOTIS_NATIVE_API motis_native_handle* motis_native_init(char const* data_path, char** error);
+
+// Release the instance. Accepts NULL.
+MOTIS_NATIVE_API void motis_native_destroy(motis_native_handle* handle);
+
+// Execute one JSON command. The returned string is never NULL and must be
+// released with motis_native_free_string. Not thread-safe per handle.
+MOTIS_NATIVE_API char* motis_native_command(motis_native_handle* handle,
+                                            char const* request_json);
+
+MOTIS_NATIVE_API void motis_native_free_string(char* s);
+
+// Trace id for diagnostics ("[trace=<id>] ...") written by calls on the
+// calling thread; NULL or "" clears it. motis_native_command takes the id
+// from the request's "trace_id" instead.
+MOTIS_NATIVE_API void motis_native_set_trace_id(char const* trace_id);
+
+// Typed entry points. Like motis_native_command they are not thread-safe per
+// handle. Unless noted, they return 0 on success and -1 on failure; on
+// failure `*error` (if `error` is not NULL) receives a message to release
+// with motis_native_free_string. Optional strings are NULL when absent.
+
+typedef struct motis_native_leg {
+  char const* mode;
+  char const* from_name;
+  char const* to_name;
+  double from_lat;
+  double from_lon;
+  double to_lat;
+  double to_lon;
+  int duration_seconds;
+  int distance_meters;
+  char const* route_short_name;
+  char const* headsign;
+} motis_native_leg;
+
+typedef struct motis_native_route {
+  int duration_seconds;
+  int transfers;
+  size_t n_legs;
+  motis_native_leg const* legs;
+} motis_native_route;
+
+// Owns all strings and arrays it points to until motis_native_free_routes.
+typedef struct motis_native_routes {
+  size_t n_routes;
+  motis_native_route const* routes;
+  void* storage;
+} motis_native_routes;
+
+// `departure_time` (ISO 8601) may be NULL for "now".
+MOTIS_NATIVE_API int motis_native_plan_route(motis_native_handle* handle,
+                                             double from_lat,
+                                             double from_lon,
+                                             double to_lat,
+                                             double to_lon,
+                                             char const* departure_time,
+                                             motis_native_routes* out,
+                                             char** error);
+
+MOTIS_NATIVE_API void motis_native_free_routes(motis_native_routes* routes);
+
+// Same meaning as the /api/v1/geocode params. `type` may be NULL,
+// `has_place`/`has_place_bias` select the optional fields, `limit` 0 means
+// no limit.
+typedef struct motis_native_geocode_options {
+  char const* type;
+  char const* const* modes;
+  size_t n_modes;
+  int has_place;
+  double place_lat;
+  double place_lon;
+  int has_place_bias;
+  double place_bias;
+  char const* const* language;
+  size_t n_language;
+  size_t limit;
+} motis_native_geocode_options;
+
+typedef struct motis_native_area {
+  char const* name;
+  int admin_level;
+  int matched;
+  int unique;
+  int is_default;
+} motis_native_area;
+
+typedef struct motis_native_location {
+  char const* name;
+  char const* place_id;
+  double lat;
+  double lon;
+  char const* type;
+  double score;
+  char const* category;
+  size_t n_areas;
+  motis_native_area const* areas;
+  size_t n_tokens;
+  int const* tokens;  // n_tokens pairs of [start, length]
+  int has_modes;
+  size_t n_modes;
+  char const* const* modes;
+  int has_importance;
+  double importance;
+  char const* street;
+  char const* house_number;
+  char const* country;
+  char const* zip;
+  char const* tz;
+  int has_level;
+  double level;
+} motis_native_location;
+
+// Owns all strings and arrays it points to until motis_native_free_locations.
+typedef struct motis_native_locations {
+  size_t n_locations;
+  motis_native_location const* locations;
+  void* storage;
+} motis_native_locations;
+
+// `options` may be NULL for defaults.
+MOTIS_NATIVE_API int motis_native_geocode(
+    motis_native_handle* handle,
+    char const* query,
+    motis_native_geocode_options const* options,
+    motis_native_locations* out,
+    char** error);
+
+MOTIS_NATIVE_API void motis_native_free_locations(
+    motis_native_locations* locations);
+
+// Raw tile (MVT) or glyph (PBF) bytes: return 1 and set `*data`/`*size` when
+// found (release `*data` with motis_native_free_bytes), 0 when not found and
+// -1 on failure.
+MOTIS_NATIVE_API int motis_native_get_tile(motis_native_handle* handle,
+                                           int z,
+                                           int x,
+                                           int y,
+                                           unsigned char** data,
+                                           size_t* size,
+                                           char** error);
+
+MOTIS_NATIVE_API int motis_native_get_glyph(motis_native_handle* handle,
+                                            char const* path,
+                                            unsigned char** data,
+                                            size_t* size,
+                                            char** error);
+
+MOTIS_NATIVE_API void motis_native_free_bytes(unsigned char* data);
+
+// Supported MOTIS GET endpoint (e.g. "/api/v5/plan?..."). Returns the
+// endpoint's JSON payload as is (release with motis_native_free_string), or
+// NULL with `*error` set when the endpoint is unsupported or failed.
+MOTIS_NATIVE_API char* motis_native_api_get(motis_native_handle* handle,
+                                            char const* path_and_query,
+                                            char** error);
+
+#ifdef __cplusplus
+}
+#endif
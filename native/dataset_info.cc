@@ -0,0 +1,111 @@
+#include "native/api_internal.h"
+
+#include <chrono>
+#include <filesystem>
+#include <optional>
+#include <string>
+#include <system_error>
+
+#include "date/date.h"
+
+#include "geo/box.h"
+
+#include "nigiri/timetable.h"
+
+#include "osr/ways.h"
+
+namespace motis::native {
+
+namespace {
+
+std::string format_day(nigiri::unixtime_t const t) {
+  return date::format("%F", std::chrono::floor<date::days>(t));
+}
+
+void set_service_range(nigiri::timetable const& tt, dataset_info& info) {
+  auto active = nigiri::bitfield{};
+  for (auto const& bf : tt.bitfields_) {
+    active |= bf;
+  }
+
+  auto first = std::optional<std::size_t>{};
+  auto last = std::optional<std::size_t>{};
+  for (auto i = 0U; i != active.size(); ++i) {
+    if (!active.test(i)) {
+      continue;
+    }
+    if (!first) {
+      first = i;
+    }
+    last = i;
+  }
+  if (!first) {
+    return;
+  }
+
+  auto const base = tt.internal_interval().from_;
+  info.service_from = format_day(base + *first * date::days{1U});
+  info.service_to = format_day(base + *last * date::days{1U});
+}
+
+std::optional<bbox> get_osm_bbox(osr::ways const& w) {
+  if (w.n_nodes() == 0U) {
+    return std::nullopt;
+  }
+  auto b = geo::box{};
+  for (auto n = osr::node_idx_t{0U}; n != w.n_nodes(); ++n) {
+    b.extend(w.get_node_pos(n).as_latlng());
+  }
+  return bbox{.min = {b.min_.lat_, b.min_.lng_},
+              .max = {b.max_.lat_, b.max_.lng_}};
+}
+
+std::optional<std::string> get_import_time(std::filesystem::path const& p) {
+  for (auto const& f : {p / "tt.bin", p / "config.yml"}) {
+    auto ec = std::error_code{};
+    auto const ft = std::filesystem::last_write_time(f, ec);
+    if (ec) {
+      continue;
+    }
+    auto const t = std::chrono::floor<std::chrono::seconds>(
+        std::chrono::file_clock::to_sys(ft));
+    return date::format("%FT%TZ", t);
+  }
+  return std::nullopt;
+}
+
+}  // namespace
+
+dataset_info get_dataset_info(native_instance& inst) {
+  auto info = dataset_info{};
+
+  if (inst.config_.timetable_) {
+    for (auto const& [name, _] : inst.config_.timetable_->datasets_) {
+      info.feeds.push_back(name);
+    }
+  }
+
+  if (inst.data_.tt_) {
+    auto const& tt = *inst.data_.tt_;
+    set_service_range(tt, info);
+    for (auto i = nigiri::provider_idx_t{0}; i < tt.n_agencies(); ++i) {
+      auto const& p = tt.providers_[i];
+      info.agencies.push_back(
+          agency{.id = std::string{tt.strings_.get(p.id_)},
+                 .name = std::string{tt.get_default_translation(p.name_)}});
+    }
+    info.n_stops = tt.n_locations() - nigiri::kNSpecialStations;
+    info.n_routes = tt.n_routes();
+  }
+
+  if (inst.data_.w_ && !inst.osm_bbox_computed_) {
+    inst.osm_bbox_ = get_osm_bbox(*inst.data_.w_);
+    inst.osm_bbox_computed_ = true;
+  }
+  info.osm_bbox = inst.osm_bbox_;
+
+  info.imported_at = get_import_time(inst.data_.path_);
+  return info;
+}
+
+}  // namespace motis::native
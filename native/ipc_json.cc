@@ -0,0 +1,354 @@
+#include "native/ipc_json.h"
+#include "native/base64.h"
+
+#include <iostream>
+#include <string>
+#include <vector>
+
+#include <nlohmann/json.hpp>
+
+using json = nlohmann::json;
+
+namespace motis::native {
+
+namespace {
+
+json coord_to_json(const coord& c) {
+    return json{{"lat", c.lat}, {"lon", c.lon}};
+}
+
+json leg_to_json(const leg& l) {
+    json j = {
+        {"mode", l.mode},
+        {"from_name", l.from_name},
+        {"to_name", l.to_name},
+        {"from", coord_to_json(l.from)},
+        {"to", coord_to_json(l.to)},
+        {"duration_seconds", l.duration_seconds},
+        {"distance_meters", l.distance_meters}
+    };
+    if (l.route_short_name) {
+        j["route_short_name"] = *l.route_short_name;
+    }
+    if (l.headsign) {
+        j["headsign"] = *l.headsign;
+    }
+    return j;
+}
+
+json route_to_json(const route& r) {
+    json legs = json::array();
+    for (auto const& l : r.legs) {
+        legs.push_back(leg_to_json(l));
+    }
+    return json{
+        {"duration_seconds", r.duration_seconds},
+        {"transfers", r.transfers},
+        {"legs", legs}
+    };
+}
+
+json area_to_json(const area& a) {
+    return json{
+        {"name", a.name},
+        {"admin_level", a.admin_level},
+        {"matched", a.matched},
+        {"unique", a.unique},
+        {"default", a.is_default}
+    };
+}
+
+json token_to_json(const token& t) {
+    return json::array({t.start, t.length});
+}
+
+json location_to_json(const location& loc) {
+    json j = {
+        {"name", loc.name},
+        {"place_id", loc.place_id},
+        {"lat", loc.pos.lat},
+        {"lon", loc.pos.lon},
+        {"score", loc.score}
+    };
+
+    if (loc.type) {
+        j["type"] = *loc.type;
+    }
+    if (loc.category) {
+        j["category"] = *loc.category;
+    }
+
+    // Areas
+    json areas = json::array();
+    for (auto const& a : loc.areas) {
+        areas.push_back(area_to_json(a));
+    }
+    j["areas"] = areas;
+
+    // Tokens
+    json tokens = json::array();
+    for (auto const& t : loc.tokens) {
+        tokens.push_back(token_to_json(t));
+    }
+    j["tokens"] = tokens;
+
+    // Modes
+    if (loc.modes) {
+        j["modes"] = *loc.modes;
+    }
+
+    // Importance
+    if (loc.importance) {
+        j["importance"] = *loc.importance;
+    }
+
+    // Address fields
+    if (loc.street) {
+        j["street"] = *loc.street;
+    }
+    if (loc.house_number) {
+        j["house_number"] = *loc.house_number;
+    }
+    if (loc.country) {
+        j["country"] = *loc.country;
+    }
+    if (loc.zip) {
+        j["zip"] = *loc.zip;
+    }
+    if (loc.tz) {
+        j["tz"] = *loc.tz;
+    }
+    if (loc.level) {
+        j["level"] = *loc.level;
+    }
+
+    return j;
+}
+
+json dataset_info_to_json(const dataset_info& info) {
+    json agencies = json::array();
+    for (auto const& a : info.agencies) {
+        agencies.push_back(json{{"id", a.id}, {"name", a.name}});
+    }
+
+    json j = {
+        {"feeds", info.feeds},
+        {"agencies", agencies},
+        {"stop_count", info.n_stops},
+        {"route_count", info.n_routes}
+    };
+
+    if (info.service_from) {
+        j["service_from"] = *info.service_from;
+    }
+    if (info.service_to) {
+        j["service_to"] = *info.service_to;
+    }
+    if (info.osm_bbox) {
+        j["osm_bbox"] = {
+            {"min", coord_to_json(info.osm_bbox->min)},
+            {"max", coord_to_json(info.osm_bbox->max)}
+        };
+    }
+    if (info.imported_at) {
+        j["imported_at"] = *info.imported_at;
+    }
+
+    return j;
+}
+
+json ok(const json& data) {
+    return json{{"status", "ok"}, {"data", data}};
+}
+
+geocode_options geocode_options_from_json(const json& req) {
+    geocode_options opts;
+    if (!req.contains("options") || !req["options"].is_object()) {
+        return opts;
+    }
+    auto const& o = req["options"];
+    if (o.contains("type")) {
+        opts.type = o["type"].get<std::string>();
+    }
+    if (o.contains("modes")) {
+        opts.modes = o["modes"].get<std::vector<std::string>>();
+    }
+    if (o.contains("place")) {
+        opts.place = coord{o["place"]["lat"], o["place"]["lon"]};
+    }
+    if (o.contains("place_bias")) {
+        opts.place_bias = o["place_bias"].get<double>();
+    }
+    if (o.contains("language")) {
+        opts.language = o["language"].get<std::vector<std::string>>();
+    }
+    if (o.contains("limit")) {
+        opts.limit = o["limit"].get<std::size_t>();
+    }
+    return opts;
+}
+
+json error(std::string const& msg) {
+    return json{{"status", "error"}, {"message", msg}};
+}
+
+// Clears the trace id when the command is done, also on exceptions.
+struct trace_scope {
+    trace_scope() = default;
+    trace_scope(trace_scope const&) = delete;
+    trace_scope& operator=(trace_scope const&) = delete;
+    ~trace_scope() { set_trace_id({}); }
+};
+
+json dispatch(native_instance& inst, json& req) {
+    std::string cmd = req.value("cmd", "");
+
+    if (cmd == "geocode") {
+        std::string query = req.value("query", "");
+        auto locations = geocode(inst, query, geocode_options_from_json(req));
+
+        json result = json::array();
+        for (auto const& loc : locations) {
+            result.push_back(location_to_json(loc));
+        }
+        return ok(result);
+    }
+    else if (cmd == "plan_route") {
+        coord from{req["from_lat"], req["from_lon"]};
+        coord to{req["to_lat"], req["to_lon"]};
+
+        auto routes = plan_route(inst, from, to);
+
+        json result = json::array();
+        for (auto const& r : routes) {
+            result.push_back(route_to_json(r));
+        }
+        return ok(result);
+    }
+    else if (cmd == "reverse_geocode") {
+        coord pos{req["lat"], req["lon"]};
+        auto loc = reverse_geocode(inst, pos);
+
+        if (loc) {
+            return ok(location_to_json(*loc));
+        } else {
+            return ok(nullptr);
+        }
+    }
+    else if (cmd == "get_tile") {
+        int z = req["z"];
+        int x = req["x"];
+        int y = req["y"];
+
+        auto tile = get_tile(inst, z, x, y);
+
+        if (tile.found) {
+            json result = {
+                {"data_base64", encode_base64(tile.data)},
+                {"found", true}
+            };
+            return ok(result);
+        } else {
+            json result = {{"found", false}};
+            return ok(result);
+        }
+    }
+    else if (cmd == "get_glyph") {
+        std::string path = req.value("path", "");
+        if (path.empty()) {
+            return error("Missing path");
+        }
+
+        auto glyph = get_glyph(inst, path);
+        if (glyph.found) {
+            json result = {
+                {"data_base64", encode_base64(glyph.data)},
+                {"found", true}
+            };
+            return ok(result);
+        } else {
+            json result = {{"found", false}};
+            return ok(result);
+        }
+    }
+    else if (cmd == "dataset_info") {
+        return ok(dataset_info_to_json(get_dataset_info(inst)));
+    }
+    else if (cmd == "api_get") {
+        std::string path = req.value("path", "");
+        if (path.empty()) {
+            return error("Missing path");
+        }
+
+        auto payload = api_get(inst, path);
+        if (!payload) {
+            return error("Unsupported endpoint or endpoint failed: " + path);
+        }
+
+        auto parsed = json::parse(*payload, nullptr, false);
+        if (parsed.is_discarded()) {
+            return error("Endpoint did not return valid JSON: " + path);
+        }
+        return ok(parsed);
+    }
+    else if (cmd == "api_post") {
+        std::string path = req.value("path", "");
+        if (path.empty()) {
+            return error("Missing path");
+        }
+
+        auto body = req.contains("body") ? req["body"].dump() : "null";
+        auto payload = api_post(inst, path, body);
+        if (!payload) {
+            return error("Unsupported endpoint or endpoint failed: " + path);
+        }
+
+        auto parsed = json::parse(*payload, nullptr, false);
+        if (parsed.is_discarded()) {
+            return error("Endpoint did not return valid JSON: " + path);
+        }
+        return ok(parsed);
+    }
+    else if (cmd == "load_elevators") {
+        std::string path = req.value("path", "");
+        if (path.empty()) {
+            return error("Missing path");
+        }
+
+        auto count = load_elevators(inst, path);
+        if (!count) {
+            return error("Could not load elevator status from " + path);
+        }
+        return ok(json{{"elevators", *count}});
+    }
+    else {
+        return error("Unknown command: " + cmd);
+    }
+}
+
+}  // namespace
+
+std::string handle_json_command(native_instance& inst, std::string const& request) {
+    json req;
+    json response;
+    trace_scope trace;
+    try {
+        req = json::parse(request);
+        if (req.is_object() && req.contains("trace_id") && req["trace_id"].is_string()) {
+            set_trace_id(req["trace_id"].get<std::string>());
+        }
+        response = dispatch(inst, req);
+    } catch (const std::exception& e) {
+        std::cerr << trace_log_prefix() << "Command failed: " << e.what() << "\n";
+        response = error(std::string("Error: ") + e.what());
+    }
+
+    // Echo the caller's trace id so one GUI request can be followed from
+    // the protocol layer into the backend and back.
+    if (req.is_object() && req.contains("trace_id") && req["trace_id"].is_string()) {
+        response["trace_id"] = req["trace_id"];
+    }
+    return response.dump();
+}
+
+}  // namespace motis::native
@@ -0,0 +1,16 @@
+#pragma once
+
+#include <string>
+
+#include "native/api.h"
+
+namespace motis::native {
+
+// Handle one JSON request ({"cmd": "...", ...}) and return the single-line
+// response ({"status": "ok", "data": ...} / {"status": "error", ...}).
+// A string "trace_id" in the request is echoed in the response.
+// Shared by motis-ipc (stdin/stdout) and the embedded C API.
+std::string handle_json_command(native_instance& inst,
+                                std::string const& request);
+
+}  // namespace motis::native
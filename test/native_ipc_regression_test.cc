@@ -4,9 +4,11 @@
 #include <cstdio>
 #include <array>
 #include <filesystem>
+#include <fstream>
 #include <optional>
 #include <string>
 #include <system_error>
+#include <vector>
 
 #include "boost/json.hpp"
 
@@ -174,3 +176,204 @@ TEST_F(native_wrapper_regression_test, endpoint_wrapper_fault_contracts_hold) {
       motis::native::api_get(*inst_, "/api/v1/geocode?text=FFM");
   EXPECT_FALSE(result.has_value());
 }
+
+TEST_F(native_wrapper_regression_test, glyph_is_returned_as_raw_pbf) {
+  auto const glyph = motis::native::get_glyph(
+      *inst_, "/tiles/glyphs/Noto%20Sans%20Regular/0-255.pbf");
+  ASSERT_TRUE(glyph.found);
+  ASSERT_FALSE(glyph.data.empty());
+  // glyphs.stacks (field 1, length-delimited); base64 text would start with
+  // a printable character instead.
+  EXPECT_EQ('\x0a', glyph.data.front());
+
+  EXPECT_FALSE(motis::native::get_glyph(
+                   *inst_, "/tiles/glyphs/No%20Such%20Font/0-255.pbf")
+                   .found);
+}
+
+TEST_F(native_wrapper_regression_test, diagnostics_carry_trace_id) {
+  motis::native::set_trace_id("search-7");
+  motis::native::test_support::inject_fault_once("api_get");
+  testing::internal::CaptureStderr();
+  EXPECT_FALSE(
+      motis::native::api_get(*inst_, "/api/v1/geocode?text=FFM").has_value());
+  auto const traced = testing::internal::GetCapturedStderr();
+  EXPECT_NE(std::string::npos,
+            traced.find("[trace=search-7] api_get error: Injected native fault"));
+
+  motis::native::set_trace_id("");
+  motis::native::test_support::inject_fault_once("api_get");
+  testing::internal::CaptureStderr();
+  EXPECT_FALSE(
+      motis::native::api_get(*inst_, "/api/v1/geocode?text=FFM").has_value());
+  auto const untraced = testing::internal::GetCapturedStderr();
+  EXPECT_TRUE(untraced.starts_with("api_get error:"));
+}
+
+TEST_F(native_wrapper_regression_test, debug_transfers_via_api_get) {
+  auto const result =
+      motis::native::api_get(*inst_, "/api/debug/transfers?id=test_DA_10");
+  ASSERT_TRUE(result.has_value());
+
+  auto const payload = json::parse(*result).as_object();
+  // The debug endpoint reports raw GTFS ids (without the dataset tag).
+  EXPECT_EQ("DA_10", payload.at("place").as_object().at("stopId").as_string());
+  EXPECT_EQ("DA", payload.at("root").as_object().at("stopId").as_string());
+  ASSERT_TRUE(payload.at("transfers").is_array());
+
+  EXPECT_FALSE(
+      motis::native::api_get(*inst_, "/api/debug/transfers?id=test_unknown")
+          .has_value());
+}
+
+TEST_F(native_wrapper_regression_test, debug_flex_via_api_get) {
+  auto const result = motis::native::api_get(
+      *inst_, "/api/debug/flex?min=49.86,8.62&max=49.88,8.64");
+  ASSERT_TRUE(result.has_value());
+
+  // The test feed has no GTFS-Flex zones or location groups.
+  auto const payload = json::parse(*result).as_object();
+  EXPECT_EQ("FeatureCollection", payload.at("type").as_string());
+  EXPECT_TRUE(payload.at("features").as_array().empty());
+
+  EXPECT_FALSE(
+      motis::native::api_get(*inst_, "/api/debug/flex?max=49.88,8.64")
+          .has_value());
+}
+
+TEST_F(native_wrapper_regression_test, elevators_via_api_post) {
+  // [min lon, min lat, max lon, max lat] around DA Hbf.
+  auto const bbox = R"([8.62, 49.86, 8.64, 49.88])";
+  auto const result = motis::native::api_post(*inst_, "/api/elevators", bbox);
+  ASSERT_TRUE(result.has_value());
+  auto const payload = json::parse(*result).as_object();
+  EXPECT_EQ("FeatureCollection", payload.at("type").as_string());
+  EXPECT_TRUE(payload.at("features").as_array().empty());
+
+  EXPECT_FALSE(motis::native::api_post(*inst_, "/api/update_elevator",
+                                       R"({"id": 1, "status": "INACTIVE"})")
+                   .has_value());
+  EXPECT_FALSE(
+      motis::native::api_post(*inst_, "/api/unknown", "{}").has_value());
+}
+
+TEST_F(native_wrapper_regression_test, street_route_via_api_post) {
+  // DA_10 platform to the DA Hbf station node.
+  auto const result = motis::native::api_post(*inst_, "/api/route", R"({
+    "start": {"lat": 49.87336, "lng": 8.62926},
+    "destination": {"lat": 49.87260, "lng": 8.63085},
+    "profile": "foot"
+  })");
+  ASSERT_TRUE(result.has_value());
+
+  auto const payload = json::parse(*result).as_object();
+  ASSERT_TRUE(payload.contains("metadata"));
+  auto const& metadata = payload.at("metadata").as_object();
+  EXPECT_GT(metadata.at("distance").to_number<double>(), 0.0);
+  EXPECT_FALSE(payload.at("features").as_array().empty());
+
+  EXPECT_FALSE(motis::native::api_post(*inst_, "/api/route",
+                                       R"({"profile": "foot"})")
+                   .has_value());
+}
+
+TEST_F(native_wrapper_regression_test, matches_and_graph_via_api_post) {
+  // [min lon, min lat, max lon, max lat] around DA Hbf.
+  auto const bbox = R"([8.62, 49.86, 8.64, 49.88])";
+  auto const matches = motis::native::api_post(*inst_, "/api/matches", bbox);
+  ASSERT_TRUE(matches.has_value());
+
+  auto has_platform = false;
+  for (auto const& f :
+       json::parse(*matches).as_object().at("features").as_array()) {
+    auto const& props = f.as_object().at("properties").as_object();
+    has_platform |= props.contains("id") && props.at("id").is_string() &&
+                    props.at("id").as_string() == "test_DA_10";
+  }
+  EXPECT_TRUE(has_platform);
+
+  auto const graph = motis::native::api_post(
+      *inst_, "/api/graph",
+      R"({"level": 0, "waypoints": [8.628, 49.872, 8.632, 49.874]})");
+  ASSERT_TRUE(graph.has_value());
+  EXPECT_EQ("FeatureCollection",
+            json::parse(*graph).as_object().at("type").as_string());
+}
+
+TEST_F(native_wrapper_regression_test, load_elevators_and_toggle_status) {
+  auto const fasta_path = kNativeApiDataPath / "fasta.json";
+  {
+    auto out = std::ofstream{fasta_path};
+    out << R"([{
+      "description": "DA Hbf zu Gleis 10",
+      "equipmentnumber": 42,
+      "geocoordX": 8.62926,
+      "geocoordY": 49.87336,
+      "state": "INACTIVE",
+      "type": "ELEVATOR"
+    }])";
+  }
+
+  auto const count =
+      motis::native::load_elevators(*inst_, fasta_path.string());
+  ASSERT_TRUE(count.has_value());
+  EXPECT_EQ(1U, *count);
+
+  auto const status = [&]() -> std::string {
+    auto const result = motis::native::api_post(
+        *inst_, "/api/elevators", R"([8.62, 49.86, 8.64, 49.88])");
+    if (!result.has_value()) {
+      return "";
+    }
+    for (auto const& f :
+         json::parse(*result).as_object().at("features").as_array()) {
+      auto const& props = f.as_object().at("properties").as_object();
+      if (props.at("id").to_number<std::int64_t>() == 42) {
+        return std::string{props.at("status").as_string()};
+      }
+    }
+    return "";
+  };
+  EXPECT_EQ("INACTIVE", status());
+
+  ASSERT_TRUE(motis::native::api_post(*inst_, "/api/update_elevator",
+                                      R"({"id": 42, "status": "ACTIVE"})")
+                  .has_value());
+  EXPECT_EQ("ACTIVE", status());
+
+  EXPECT_FALSE(motis::native::load_elevators(
+                   *inst_, (kNativeApiDataPath / "missing.json").string())
+                   .has_value());
+}
+
+TEST_F(native_wrapper_regression_test, ipc_echoes_trace_id) {
+  auto const ipc_binary = find_ipc_binary();
+  ASSERT_TRUE(ipc_binary.has_value());
+  auto const cmd =
+      "printf '%s\\n' "
+      "'{\"cmd\":\"no_such_cmd\",\"trace_id\":\"search-42\"}' "
+      "'{\"cmd\":\"dataset_info\",\"trace_id\":\"search-43\"}' "
+      "'{\"cmd\":\"dataset_info\"}' | " +
+      ipc_binary->string() + " " + kNativeApiDataPath.string() +
+      " 2>/dev/null";
+  auto const result = run_command_capture_stdout(cmd);
+  ASSERT_EQ(0, result.exit_code_);
+
+  auto lines = std::vector<json::object>{};
+  auto const output = result.stdout_;
+  for (auto start = std::size_t{0}; start < output.size();) {
+    auto const end = output.find('\n', start);
+    lines.push_back(json::parse(output.substr(start, end - start)).as_object());
+    if (end == std::string::npos) {
+      break;
+    }
+    start = end + 1;
+  }
+  ASSERT_EQ(3U, lines.size());
+
+  EXPECT_EQ("error", lines[0].at("status").as_string());
+  EXPECT_EQ("search-42", lines[0].at("trace_id").as_string());
+  EXPECT_EQ("ok", lines[1].at("status").as_string());
+  EXPECT_EQ("search-43", lines[1].at("trace_id").as_string());
+  EXPECT_FALSE(lines[2].contains("trace_id"));
+}